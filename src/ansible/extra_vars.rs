@@ -9,7 +9,7 @@ use super::provisioning::PrivateNodeProvisionInventory;
 use crate::inventory::VirtualMachine;
 use crate::NodeType;
 use crate::{ansible::provisioning::ProvisionOptions, CloudProvider, EvmNetwork};
-use crate::{BinaryOption, Error, Result};
+use crate::{secrets, BinaryOption, Error, Result};
 use alloy::hex::ToHexExt;
 use alloy::signers::local::PrivateKeySigner;
 use log::error;
@@ -681,7 +681,11 @@ pub fn build_clients_extra_vars_doc(
     for (k, v) in sk_map {
         let sks = v
             .iter()
-            .map(|sk| sk.to_bytes().encode_hex_with_prefix())
+            .map(|sk| {
+                let sk_hex = sk.to_bytes().encode_hex_with_prefix();
+                secrets::register(&sk_hex);
+                sk_hex
+            })
             .collect::<Vec<String>>();
         let sks = Value::Array(sks.into_iter().map(Value::String).collect());
         serde_map.insert(k.name.clone(), sks);
@@ -9,7 +9,7 @@ use crate::{
     ansible::{provisioning::PrivateNodeProvisionInventory, AnsibleBinary},
     error::Error,
     inventory::VirtualMachine,
-    run_external_command, Result,
+    Result,
 };
 use log::{debug, error, warn};
 use serde::{Deserialize, Deserializer};
@@ -215,8 +215,8 @@ impl AnsibleRunner {
             } else {
                 debug!("Running inventory list.");
             }
-            let output = run_external_command(
-                AnsibleBinary::AnsibleInventory.get_binary_path()?,
+            let output = self.executor.run(
+                PathBuf::from(AnsibleBinary::AnsibleInventory.to_string()),
                 self.working_directory_path.clone(),
                 vec![
                     "--inventory".to_string(),
@@ -9,12 +9,15 @@ pub mod inventory;
 pub mod provisioning;
 
 use crate::{
+    dry_run::{self, PlannedAction},
     error::{Error, Result},
-    is_binary_on_path, run_external_command, CloudProvider,
+    executor::{get_system_command_executor, CommandExecutor},
+    is_binary_on_path, secrets, CloudProvider,
 };
 use inventory::AnsibleInventoryType;
 use log::debug;
-use std::path::PathBuf;
+use std::{io::Write, path::PathBuf, sync::Arc};
+use tempfile::NamedTempFile;
 
 /// Ansible has multiple 'binaries', e.g., `ansible-playbook`, `ansible-inventory` etc. that are
 /// wrappers around the main `ansible` program. It would be a bit cumbersome to create a different
@@ -342,6 +345,7 @@ pub struct AnsibleRunner {
     pub ansible_forks: usize,
     pub ansible_verbose_mode: bool,
     pub environment_name: String,
+    pub executor: Arc<dyn CommandExecutor>,
     pub provider: CloudProvider,
     pub ssh_sk_path: PathBuf,
     pub vault_password_file_path: PathBuf,
@@ -365,6 +369,7 @@ impl AnsibleRunner {
             ansible_forks,
             ansible_verbose_mode,
             environment_name: environment_name.to_string(),
+            executor: get_system_command_executor(),
             provider,
             working_directory_path,
             ssh_sk_path,
@@ -406,8 +411,9 @@ impl AnsibleRunner {
             inventory_type = AnsibleInventoryType::FullConePrivateNodesStatic;
         }
 
+        let redacted_extra_vars = extra_vars_document.as_deref().map(secrets::redact);
         debug!(
-            "Running playbook: {:?} on {inventory_type:?} with extra vars: {extra_vars_document:?}",
+            "Running playbook: {:?} on {inventory_type:?} with extra vars: {redacted_extra_vars:?}",
             playbook.get_playbook_name()
         );
 
@@ -427,9 +433,19 @@ impl AnsibleRunner {
             "--vault-password-file".to_string(),
             self.vault_password_file_path.to_string_lossy().to_string(),
         ];
-        if let Some(extra_vars) = extra_vars_document {
+        // The extra vars can contain secrets, so they are passed in a file rather than as an
+        // argument, which would be visible in the process table. The file is removed when it is
+        // dropped, after the playbook has run.
+        let extra_vars_file = match &extra_vars_document {
+            Some(extra_vars) if !dry_run::is_enabled() => Some(write_extra_vars_file(extra_vars)?),
+            _ => None,
+        };
+        if extra_vars_document.is_some() {
             args.push("--extra-vars".to_string());
-            args.push(extra_vars);
+            args.push(match &extra_vars_file {
+                Some(file) => format!("@{}", file.path().to_string_lossy()),
+                None => "@extra-vars.json".to_string(),
+            });
         }
         if self.ansible_verbose_mode {
             args.push("-vvvvv".to_string());
@@ -437,7 +453,19 @@ impl AnsibleRunner {
         args.push("--forks".to_string());
         args.push(self.ansible_forks.to_string());
         args.push(playbook.get_playbook_name());
-        run_external_command(
+        if dry_run::is_enabled() {
+            dry_run::record(PlannedAction::AnsiblePlaybook {
+                playbook: playbook.get_playbook_name(),
+                inventory_type: inventory_type.to_string(),
+                extra_vars: redacted_extra_vars,
+                command: dry_run::format_command(
+                    &AnsibleBinary::AnsiblePlaybook.to_string(),
+                    &args,
+                ),
+            });
+            return Ok(());
+        }
+        self.executor.run(
             PathBuf::from(AnsibleBinary::AnsiblePlaybook.to_string()),
             self.working_directory_path.clone(),
             args,
@@ -464,3 +492,21 @@ impl AnsibleRunner {
         }
     }
 }
+
+/// Write the extra vars to a temporary file that only the current user can read, to be passed to
+/// `ansible-playbook` as `@path`.
+fn write_extra_vars_file(extra_vars: &str) -> Result<NamedTempFile> {
+    let mut file = tempfile::Builder::new()
+        .prefix("extra-vars-")
+        .suffix(".json")
+        .tempfile()?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.as_file()
+            .set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(extra_vars.as_bytes())?;
+    file.flush()?;
+    Ok(file)
+}
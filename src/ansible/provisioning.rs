@@ -25,8 +25,9 @@ use crate::{
     error::{Error, Result},
     funding::FundingOptions,
     inventory::{DeploymentNodeRegistries, VirtualMachine},
-    print_duration, run_external_command, BinaryOption, CloudProvider, EvmNetwork, LogFormat,
-    NodeType, SshClient, UpgradeOptions,
+    object_store::ObjectStore,
+    print_duration, BinaryOption, CloudProvider, EvmNetwork, LogFormat, NodeType, SshClient,
+    UpgradeOptions,
 };
 use ant_service_management::NodeRegistry;
 use evmlib::common::U256;
@@ -37,6 +38,7 @@ use std::{
     collections::HashMap,
     net::IpAddr,
     path::PathBuf,
+    sync::Arc,
     time::{Duration, Instant},
 };
 use walkdir::WalkDir;
@@ -74,6 +76,8 @@ pub struct ProvisionOptions {
     pub expected_size: Option<u64>,
     pub file_address: Option<String>,
     pub full_cone_private_node_count: u16,
+    /// The resolved secret is not saved with the rest of the options.
+    #[serde(skip)]
     pub funding_wallet_secret_key: Option<String>,
     pub gas_amount: Option<U256>,
     pub interval: Option<Duration>,
@@ -107,6 +111,8 @@ pub struct ProvisionOptions {
     pub upload_interval: Option<u16>,
     pub uploaders_count: Option<u16>,
     pub upnp_private_node_count: u16,
+    /// The resolved secrets are not saved with the rest of the options.
+    #[serde(skip)]
     pub wallet_secret_keys: Option<Vec<String>>,
 }
 
@@ -521,6 +527,8 @@ impl From<ClientsDeployOptions> for ProvisionOptions {
 pub struct AnsibleProvisioner {
     pub ansible_runner: AnsibleRunner,
     pub cloud_provider: CloudProvider,
+    /// Used to keep the ledger of the funding transactions.
+    pub object_store: Arc<dyn ObjectStore>,
     pub ssh_client: SshClient,
 }
 
@@ -528,11 +536,13 @@ impl AnsibleProvisioner {
     pub fn new(
         ansible_runner: AnsibleRunner,
         cloud_provider: CloudProvider,
+        object_store: Arc<dyn ObjectStore>,
         ssh_client: SshClient,
     ) -> Self {
         Self {
             ansible_runner,
             cloud_provider,
+            object_store,
             ssh_client,
         }
     }
@@ -938,7 +948,7 @@ impl AnsibleProvisioner {
         })?;
         let known_hosts_path = format!("{home_dir}/.ssh/known_hosts");
         debug!("Cleaning up known hosts file at {known_hosts_path} ");
-        run_external_command(
+        self.ansible_runner.executor.run(
             PathBuf::from("rm"),
             std::env::current_dir()?,
             vec![known_hosts_path],
@@ -1150,7 +1160,7 @@ impl AnsibleProvisioner {
         })?;
         let known_hosts_path = format!("{home_dir}/.ssh/known_hosts");
         debug!("Cleaning up known hosts file at {known_hosts_path} ");
-        run_external_command(
+        self.ansible_runner.executor.run(
             PathBuf::from("rm"),
             std::env::current_dir()?,
             vec![known_hosts_path],
@@ -2128,3 +2138,114 @@ impl AnsibleProvisioner {
         println!("{line}\n{ansible_run_msg}{s}\n{line}");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{inventory::DeploymentInventory, EnvironmentType};
+
+    const SECRET_KEY: &str = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+    fn get_deploy_options() -> DeployOptions {
+        let binary_option = BinaryOption::Versioned {
+            ant_version: None,
+            antctl_version: Some(Version::new(0, 12, 0)),
+            antnode_version: Some(Version::new(0, 3, 5)),
+        };
+        DeployOptions {
+            allow_destructive_changes: false,
+            binary_option: binary_option.clone(),
+            chunk_size: None,
+            chunk_tracker_data_addresses: Vec::new(),
+            chunk_tracker_services: 0,
+            client_env_variables: None,
+            client_vm_count: None,
+            client_vm_size: None,
+            current_inventory: DeploymentInventory::empty("alpha", binary_option),
+            enable_logging: true,
+            enable_metrics: true,
+            environment_type: EnvironmentType::Development,
+            evm_data_payments_address: None,
+            evm_network: EvmNetwork::ArbitrumSepoliaTest,
+            evm_node_vm_size: None,
+            evm_payment_token_address: None,
+            evm_rpc_url: None,
+            full_cone_vm_size: None,
+            full_cone_private_node_count: 0,
+            full_cone_private_node_vm_count: None,
+            full_cone_private_node_volume_size: None,
+            funding_wallet_secret_key: Some(SECRET_KEY.to_string()),
+            genesis_node_volume_size: None,
+            initial_gas: None,
+            initial_tokens: None,
+            interval: Duration::from_millis(200),
+            lifetime: None,
+            log_format: None,
+            max_archived_log_files: 1,
+            max_log_files: 1,
+            max_uploads: None,
+            name: "alpha".to_string(),
+            network_id: 2,
+            network_dashboard_branch: None,
+            node_count: 25,
+            node_env_variables: None,
+            node_vm_count: None,
+            node_vm_size: None,
+            node_volume_size: None,
+            output_inventory_dir_path: PathBuf::from("inventory"),
+            owner: None,
+            peer_cache_node_count: 5,
+            peer_cache_node_vm_count: None,
+            peer_cache_node_vm_size: None,
+            peer_cache_node_volume_size: None,
+            port_restricted_cone_vm_size: None,
+            port_restricted_cone_private_node_count: 0,
+            port_restricted_cone_private_node_vm_count: 0,
+            port_restricted_cone_private_node_volume_size: None,
+            single_node_payment: false,
+            start_chunk_trackers: false,
+            start_delayed_verifier: false,
+            start_performance_verifier: false,
+            start_random_verifier: false,
+            symmetric_nat_gateway_vm_size: None,
+            symmetric_private_node_count: 0,
+            symmetric_private_node_vm_count: None,
+            symmetric_private_node_volume_size: None,
+            public_rpc: false,
+            region: "lon1".to_string(),
+            rewards_address: "0x03B770D9cD32077cC0bF330c13C114a87643B124".to_string(),
+            uploaders_count: 1,
+            upload_interval: 10,
+            upload_size: 100,
+            upnp_vm_size: None,
+            upnp_private_node_count: 0,
+            upnp_private_node_vm_count: None,
+            upnp_private_node_volume_size: None,
+        }
+    }
+
+    #[test]
+    fn test_saved_options_do_not_contain_secret_keys() {
+        let deploy_options = get_deploy_options();
+        let mut provision_options = ProvisionOptions::from(deploy_options.clone());
+        provision_options.wallet_secret_keys = Some(vec![SECRET_KEY.to_string()]);
+        assert_eq!(
+            provision_options.funding_wallet_secret_key.as_deref(),
+            Some(SECRET_KEY)
+        );
+
+        // These are the documents written to the deployment options bucket.
+        let deploy_json = serde_json::to_string(&deploy_options).unwrap();
+        let provision_json = serde_json::to_string(&provision_options).unwrap();
+        for json in [&deploy_json, &provision_json] {
+            assert!(!json.contains(SECRET_KEY));
+            assert!(!json.contains(SECRET_KEY.trim_start_matches("0x")));
+        }
+
+        // The saved options can still be read back, without the secrets.
+        let provision_options: ProvisionOptions = serde_json::from_str(&provision_json).unwrap();
+        assert!(provision_options.funding_wallet_secret_key.is_none());
+        assert!(provision_options.wallet_secret_keys.is_none());
+        assert_eq!(provision_options.node_count, 25);
+    }
+}
@@ -9,14 +9,17 @@ use std::{path::PathBuf, time::Duration};
 use crate::{
     ansible::provisioning::{PrivateNodeProvisionInventory, ProvisionOptions},
     error::Result,
-    write_environment_details, BinaryOption, DeploymentType, EnvironmentDetails, EnvironmentType,
-    EvmDetails, EvmNetwork, InfraRunOptions, LogFormat, NodeType, TestnetDeployer,
+    get_environment_expiry_time, get_environment_owner, write_environment_details, BinaryOption,
+    DeploymentType, EnvironmentDetails, EnvironmentType, EvmDetails, EvmNetwork, InfraRunOptions,
+    LogFormat, NodeType, TestnetDeployer,
 };
+use chrono::Utc;
 use colored::Colorize;
 use log::error;
 
 #[derive(Clone)]
 pub struct BootstrapOptions {
+    pub allow_destructive_changes: bool,
     pub binary_option: BinaryOption,
     pub chunk_size: Option<u64>,
     pub enable_logging: bool,
@@ -29,6 +32,8 @@ pub struct BootstrapOptions {
     pub full_cone_private_node_vm_count: Option<u16>,
     pub full_cone_private_node_volume_size: Option<u16>,
     pub interval: Duration,
+    /// The environment can be reaped once this has elapsed since it was deployed.
+    pub lifetime: Option<Duration>,
     pub log_format: Option<LogFormat>,
     pub max_archived_log_files: u16,
     pub max_log_files: u16,
@@ -41,6 +46,7 @@ pub struct BootstrapOptions {
     pub node_vm_size: Option<String>,
     pub node_volume_size: Option<u16>,
     pub output_inventory_dir_path: PathBuf,
+    pub owner: Option<String>,
     pub peer: Option<String>,
     pub region: String,
     pub rewards_address: String,
@@ -56,10 +62,12 @@ impl TestnetDeployer {
     pub async fn bootstrap(&self, options: &BootstrapOptions) -> Result<()> {
         let build_custom_binaries = options.binary_option.should_provision_build_machine();
 
+        let created_at = Utc::now();
         write_environment_details(
-            &self.s3_repository,
+            &self.object_store,
             &options.name,
             &EnvironmentDetails {
+                created_at: Some(created_at.to_rfc3339()),
                 deployment_type: DeploymentType::Bootstrap,
                 environment_type: options.environment_type.clone(),
                 evm_details: EvmDetails {
@@ -68,8 +76,10 @@ impl TestnetDeployer {
                     payment_token_address: options.evm_payment_token_address.clone(),
                     rpc_url: options.evm_rpc_url.clone(),
                 },
+                expires_at: get_environment_expiry_time(created_at, options.lifetime),
                 funding_wallet_address: None,
                 network_id: Some(options.network_id),
+                owner: get_environment_owner(options.owner.clone()),
                 region: options.region.clone(),
                 rewards_address: Some(options.rewards_address.clone()),
             },
@@ -77,6 +87,7 @@ impl TestnetDeployer {
         .await?;
 
         self.create_or_update_infra(&InfraRunOptions {
+            allow_destructive_changes: options.allow_destructive_changes,
             client_image_id: None,
             client_vm_count: Some(0),
             client_vm_size: None,
@@ -10,25 +10,34 @@ use crate::{
         provisioning::{AnsibleProvisioner, ProvisionOptions},
         AnsibleRunner,
     },
+    dry_run,
     error::{Error, Result},
-    get_environment_details,
-    infra::ClientsInfraRunOptions,
+    get_environment_details, get_environment_expiry_time, get_environment_owner,
+    infra::{apply_infra_changes, ClientsInfraRunOptions},
     inventory::ClientsDeploymentInventory,
+    object_store::{get_object_store, Bucket, ObjectStore},
     print_duration,
-    s3::S3Repository,
     ssh::SshClient,
     terraform::TerraformRunner,
     write_environment_details, BinaryOption, CloudProvider, DeploymentType, EnvironmentDetails,
     EnvironmentType, EvmDetails,
 };
 use alloy::primitives::U256;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
-use std::{path::PathBuf, time::Instant};
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 const ANSIBLE_DEFAULT_FORKS: usize = 50;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct ClientsDeployOptions {
+    /// Only applies to this run, so it is not saved with the rest of the options.
+    #[serde(skip)]
+    pub allow_destructive_changes: bool,
     pub binary_option: BinaryOption,
     pub chunk_size: Option<u64>,
     pub chunk_tracker_data_addresses: Vec<String>,
@@ -45,9 +54,13 @@ pub struct ClientsDeployOptions {
     pub file_address: Option<String>,
     pub expected_hash: Option<String>,
     pub expected_size: Option<u64>,
+    /// The resolved secret is not saved with the rest of the options.
+    #[serde(skip)]
     pub funding_wallet_secret_key: Option<String>,
     pub initial_gas: Option<U256>,
     pub initial_tokens: Option<U256>,
+    /// The environment can be reaped once this has elapsed since it was deployed.
+    pub lifetime: Option<Duration>,
     pub max_archived_log_files: u16,
     pub max_log_files: u16,
     pub max_uploads: Option<u32>,
@@ -55,6 +68,7 @@ pub struct ClientsDeployOptions {
     pub network_id: Option<u8>,
     pub network_contacts_url: Option<String>,
     pub output_inventory_dir_path: PathBuf,
+    pub owner: Option<String>,
     pub peer: Option<String>,
     pub performance_verifier_batch_size: Option<u16>,
     pub random_verifier_batch_size: Option<u16>,
@@ -80,6 +94,8 @@ pub struct ClientsDeployOptions {
     pub upload_size: Option<u16>,
     pub upload_interval: u16,
     pub upload_batch_size: Option<u16>,
+    /// The resolved secrets are not saved with the rest of the options.
+    #[serde(skip)]
     pub wallet_secret_keys: Option<Vec<String>>,
 }
 
@@ -228,15 +244,20 @@ impl ClientsDeployBuilder {
         )?;
 
         let ssh_client = SshClient::new(ssh_secret_key_path);
-        let ansible_provisioner =
-            AnsibleProvisioner::new(ansible_runner, provider, ssh_client.clone());
+        let object_store = get_object_store()?;
+        let ansible_provisioner = AnsibleProvisioner::new(
+            ansible_runner,
+            provider,
+            object_store.clone(),
+            ssh_client.clone(),
+        );
 
         let client_deployer = ClientsDeployer::new(
             ansible_provisioner,
             provider,
             self.deployment_type.clone(),
             &self.environment_name,
-            S3Repository {},
+            object_store,
             ssh_client,
             terraform_runner,
             working_directory_path,
@@ -255,7 +276,7 @@ pub struct ClientsDeployer {
     pub environment_name: String,
     pub inventory_file_path: PathBuf,
     pub region: String,
-    pub s3_repository: S3Repository,
+    pub object_store: Arc<dyn ObjectStore>,
     pub ssh_client: SshClient,
     pub terraform_runner: TerraformRunner,
     pub working_directory_path: PathBuf,
@@ -268,7 +289,7 @@ impl ClientsDeployer {
         cloud_provider: CloudProvider,
         deployment_type: EnvironmentType,
         environment_name: &str,
-        s3_repository: S3Repository,
+        object_store: Arc<dyn ObjectStore>,
         ssh_client: SshClient,
         terraform_runner: TerraformRunner,
         working_directory_path: PathBuf,
@@ -289,7 +310,7 @@ impl ClientsDeployer {
             environment_name: environment_name.to_string(),
             inventory_file_path,
             region,
-            s3_repository,
+            object_store,
             ssh_client,
             terraform_runner,
             working_directory_path,
@@ -302,10 +323,13 @@ impl ClientsDeployer {
         self.terraform_runner.workspace_select(&options.name)?;
 
         let args = options.build_terraform_args()?;
-
-        println!("Running terraform apply...");
-        self.terraform_runner
-            .apply(args, Some(options.tfvars_filenames.clone()))?;
+        apply_infra_changes(
+            &self.terraform_runner,
+            &options.name,
+            args,
+            Some(options.tfvars_filenames.clone()),
+            options.allow_destructive_changes,
+        )?;
         print_duration(start.elapsed());
         Ok(())
     }
@@ -346,6 +370,7 @@ impl ClientsDeployer {
         println!("Initializing infrastructure...");
 
         let infra_options = ClientsInfraRunOptions {
+            allow_destructive_changes: options.allow_destructive_changes,
             client_image_id: None,
             client_vm_count: options.client_vm_count,
             client_vm_size: options.client_vm_size.clone(),
@@ -356,10 +381,12 @@ impl ClientsDeployer {
 
         self.create_or_update_infra(&infra_options)?;
 
+        let created_at = Utc::now();
         write_environment_details(
-            &self.s3_repository,
+            &self.object_store,
             &options.name,
             &EnvironmentDetails {
+                created_at: Some(created_at.to_rfc3339()),
                 deployment_type: DeploymentType::Client,
                 environment_type: options.environment_type.clone(),
                 evm_details: EvmDetails {
@@ -368,8 +395,10 @@ impl ClientsDeployer {
                     payment_token_address: options.evm_details.payment_token_address.clone(),
                     rpc_url: options.evm_details.rpc_url.clone(),
                 },
+                expires_at: get_environment_expiry_time(created_at, options.lifetime),
                 funding_wallet_address: None,
                 network_id: options.network_id,
+                owner: get_environment_owner(options.owner.clone()),
                 region: self.region.clone(),
                 rewards_address: None,
             },
@@ -488,6 +517,7 @@ impl ClientsDeployer {
         println!("Initializing infrastructure...");
 
         let infra_options = ClientsInfraRunOptions {
+            allow_destructive_changes: options.allow_destructive_changes,
             client_image_id: None,
             client_vm_count: options.client_vm_count,
             client_vm_size: options.client_vm_size.clone(),
@@ -498,10 +528,12 @@ impl ClientsDeployer {
 
         self.create_or_update_infra(&infra_options)?;
 
+        let created_at = Utc::now();
         write_environment_details(
-            &self.s3_repository,
+            &self.object_store,
             &options.name,
             &EnvironmentDetails {
+                created_at: Some(created_at.to_rfc3339()),
                 deployment_type: DeploymentType::Client,
                 environment_type: options.environment_type.clone(),
                 evm_details: EvmDetails {
@@ -510,8 +542,10 @@ impl ClientsDeployer {
                     payment_token_address: options.evm_details.payment_token_address.clone(),
                     rpc_url: options.evm_details.rpc_url.clone(),
                 },
+                expires_at: get_environment_expiry_time(created_at, options.lifetime),
                 funding_wallet_address: None,
                 network_id: options.network_id,
+                owner: get_environment_owner(options.owner.clone()),
                 region: self.region.clone(),
                 rewards_address: None,
             },
@@ -565,6 +599,7 @@ impl ClientsDeployer {
         println!("Initializing infrastructure...");
 
         let infra_options = ClientsInfraRunOptions {
+            allow_destructive_changes: options.allow_destructive_changes,
             client_image_id: None,
             client_vm_count: options.client_vm_count,
             client_vm_size: options.client_vm_size.clone(),
@@ -575,10 +610,12 @@ impl ClientsDeployer {
 
         self.create_or_update_infra(&infra_options)?;
 
+        let created_at = Utc::now();
         write_environment_details(
-            &self.s3_repository,
+            &self.object_store,
             &options.name,
             &EnvironmentDetails {
+                created_at: Some(created_at.to_rfc3339()),
                 deployment_type: DeploymentType::Client,
                 environment_type: options.environment_type.clone(),
                 evm_details: EvmDetails {
@@ -587,8 +624,10 @@ impl ClientsDeployer {
                     payment_token_address: options.evm_details.payment_token_address.clone(),
                     rpc_url: options.evm_details.rpc_url.clone(),
                 },
+                expires_at: get_environment_expiry_time(created_at, options.lifetime),
                 funding_wallet_address: None,
                 network_id: options.network_id,
+                owner: get_environment_owner(options.owner.clone()),
                 region: self.region.clone(),
                 rewards_address: None,
             },
@@ -660,24 +699,39 @@ impl ClientsDeployer {
         Ok(())
     }
 
-    pub async fn clean(&self) -> Result<()> {
+    pub async fn clean(&self, drain_funds: bool) -> Result<()> {
         let environment_details =
-            get_environment_details(&self.environment_name, &self.s3_repository).await?;
-        crate::funding::drain_funds(&self.ansible_provisioner, &environment_details).await?;
+            get_environment_details(&self.environment_name, &self.object_store).await?;
+        if drain_funds {
+            crate::funding::drain_funds(&self.ansible_provisioner, &environment_details).await?;
+        } else {
+            println!(
+                "Funds will not be drained from {}, because it was not requested",
+                self.environment_name
+            );
+        }
 
         self.destroy_infra(&environment_details).await?;
 
-        cleanup_environment_inventory(
-            &self.environment_name,
-            &self
-                .working_directory_path
-                .join("ansible")
-                .join("inventory"),
-            None,
-        )?;
+        if dry_run::is_enabled() {
+            println!(
+                "Dry run: skipping deletion of the Ansible inventory for {}",
+                self.environment_name
+            );
+        } else {
+            cleanup_environment_inventory(
+                &self.environment_name,
+                &self
+                    .working_directory_path
+                    .join("ansible")
+                    .join("inventory"),
+                None,
+            )?;
+            println!("Deleted Ansible inventory for {}", self.environment_name);
+        }
 
-        self.s3_repository
-            .delete_object("sn-environment-type", &self.environment_name)
+        self.object_store
+            .delete_object(Bucket::EnvironmentDetails, &self.environment_name)
             .await?;
         Ok(())
     }
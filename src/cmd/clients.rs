@@ -12,6 +12,8 @@ use sn_testnet_deploy::{
     ansible::{extra_vars::ExtraVarsDocBuilder, inventory::AnsibleInventoryType, AnsiblePlaybook},
     clients::{ClientsDeployBuilder, ClientsDeployOptions},
     inventory::DeploymentInventoryService,
+    policy::{DestructivePreview, EnvironmentPolicy},
+    secrets::SecretReference,
     upscale::UpscaleOptions,
     EvmDetails, TestnetDeployBuilder,
 };
@@ -21,6 +23,12 @@ use sn_testnet_deploy::{
 pub enum ClientsCommands {
     /// Clean a deployed client environment.
     Clean {
+        /// Drain the funds from the environment before it is destroyed.
+        ///
+        /// Funds are always drained from Development and Staging environments, but for a
+        /// Production environment, this must be requested.
+        #[clap(long)]
+        drain_funds: bool,
         /// The name of the environment.
         #[arg(short = 'n', long)]
         name: String,
@@ -30,6 +38,12 @@ pub enum ClientsCommands {
     },
     /// Deploy a new client environment.
     Deploy {
+        /// Apply infrastructure changes that would destroy or replace existing resources, without
+        /// asking for confirmation.
+        ///
+        /// Without this, those changes are refused when there is no terminal to confirm them on.
+        #[arg(long)]
+        allow_destructive_changes: bool,
         /// Set to run Ansible with more verbose output.
         #[arg(long)]
         ansible_verbose: bool,
@@ -134,8 +148,11 @@ pub enum ClientsCommands {
         /// The secret key for the wallet that will fund all the ANT instances.
         ///
         /// This argument only applies when Arbitrum or Sepolia networks are used.
-        #[clap(long)]
-        funding_wallet_secret_key: Option<String>,
+        ///
+        /// Rather than the key itself, supply a reference to it, with 'env:NAME', 'file:PATH',
+        /// 'vault:PATH' for a file encrypted with ansible-vault, or 'keyring:[SERVICE/]ACCOUNT'.
+        #[clap(long, value_parser = parse_secret_reference)]
+        funding_wallet_secret_key: Option<SecretReference>,
         /// The amount of gas to initially transfer to each ANT instance, in U256
         ///
         /// 1 ETH = 1_000_000_000_000_000_000. Defaults to 0.1 ETH
@@ -146,6 +163,12 @@ pub enum ClientsCommands {
         /// 1 Token = 1_000_000_000_000_000_000. Defaults to 100 token.
         #[arg(long)]
         initial_tokens: Option<U256>,
+        /// How long the environment should live for, as a number of hours or days, e.g., '12h' or
+        /// '7d'.
+        ///
+        /// Once it has expired, the environment can be removed by the 'environments reap' command.
+        #[clap(long, value_parser = parse_lifetime)]
+        lifetime: Option<Duration>,
         /// Maximum number of uploads to perform before stopping.
         ///
         /// If not specified, uploaders will continue uploading indefinitely.
@@ -168,6 +191,11 @@ pub enum ClientsCommands {
         /// The networks contacts URL from an existing network.
         #[arg(long)]
         network_contacts_url: Option<String>,
+        /// The owner of the environment, who will be notified when it expires.
+        ///
+        /// If not supplied, the current user is the owner.
+        #[clap(long)]
+        owner: Option<String>,
         /// A peer from an existing network that the Ant client can connect to.
         ///
         /// Should be in the form of a multiaddr.
@@ -233,11 +261,20 @@ pub enum ClientsCommands {
         /// Can be specified multiple times, once for each ANT instance.
         /// If provided, the number of keys must match the total number of uploaders (VM count * uploaders per VM).
         /// When using this option, the deployer will not fund the wallets.
-        #[clap(long, value_name = "SECRET_KEY", number_of_values = 1)]
-        wallet_secret_key: Vec<String>,
+        ///
+        /// Each key can be a reference, with 'env:NAME', 'file:PATH', 'vault:PATH' or
+        /// 'keyring:[SERVICE/]ACCOUNT'.
+        #[clap(long, value_name = "SECRET_KEY", value_parser = parse_secret_reference, number_of_values = 1)]
+        wallet_secret_key: Vec<SecretReference>,
     },
     /// Deploy chunk tracker services on client VMs.
     DeployChunkTrackers {
+        /// Apply infrastructure changes that would destroy or replace existing resources, without
+        /// asking for confirmation.
+        ///
+        /// Without this, those changes are refused when there is no terminal to confirm them on.
+        #[arg(long)]
+        allow_destructive_changes: bool,
         /// Set to run Ansible with more verbose output.
         #[arg(long)]
         ansible_verbose: bool,
@@ -324,6 +361,12 @@ pub enum ClientsCommands {
         /// The default value from ansible.cfg is 50.
         #[clap(long)]
         forks: Option<usize>,
+        /// How long the environment should live for, as a number of hours or days, e.g., '12h' or
+        /// '7d'.
+        ///
+        /// Once it has expired, the environment can be removed by the 'environments reap' command.
+        #[clap(long, value_parser = parse_lifetime)]
+        lifetime: Option<Duration>,
         /// The name of the environment
         #[arg(short = 'n', long)]
         name: String,
@@ -341,6 +384,11 @@ pub enum ClientsCommands {
         /// The networks contacts URL from an existing network.
         #[arg(long)]
         network_contacts_url: Option<String>,
+        /// The owner of the environment, who will be notified when it expires.
+        ///
+        /// If not supplied, the current user is the owner.
+        #[clap(long)]
+        owner: Option<String>,
         /// A peer from an existing network that the Ant client can connect to.
         ///
         /// Should be in the form of a multiaddr.
@@ -379,6 +427,12 @@ pub enum ClientsCommands {
     },
     /// Deploy data retrieval service on client VMs.
     DeployDataRetrieval {
+        /// Apply infrastructure changes that would destroy or replace existing resources, without
+        /// asking for confirmation.
+        ///
+        /// Without this, those changes are refused when there is no terminal to confirm them on.
+        #[arg(long)]
+        allow_destructive_changes: bool,
         /// Set to run Ansible with more verbose output.
         #[arg(long)]
         ansible_verbose: bool,
@@ -459,6 +513,12 @@ pub enum ClientsCommands {
         /// The default value from ansible.cfg is 50.
         #[clap(long)]
         forks: Option<usize>,
+        /// How long the environment should live for, as a number of hours or days, e.g., '12h' or
+        /// '7d'.
+        ///
+        /// Once it has expired, the environment can be removed by the 'environments reap' command.
+        #[clap(long, value_parser = parse_lifetime)]
+        lifetime: Option<Duration>,
         /// The name of the environment
         #[arg(short = 'n', long)]
         name: String,
@@ -476,6 +536,11 @@ pub enum ClientsCommands {
         /// The networks contacts URL from an existing network.
         #[arg(long)]
         network_contacts_url: Option<String>,
+        /// The owner of the environment, who will be notified when it expires.
+        ///
+        /// If not supplied, the current user is the owner.
+        #[clap(long)]
+        owner: Option<String>,
         /// The cloud provider to deploy to.
         ///
         /// Valid values are "aws" or "digital-ocean".
@@ -514,6 +579,12 @@ pub enum ClientsCommands {
     },
     /// Deploy service(s) for repairing individual file addresses on client VMs
     DeployRepairFiles {
+        /// Apply infrastructure changes that would destroy or replace existing resources, without
+        /// asking for confirmation.
+        ///
+        /// Without this, those changes are refused when there is no terminal to confirm them on.
+        #[arg(long)]
+        allow_destructive_changes: bool,
         /// Set to run Ansible with more verbose output.
         #[arg(long)]
         ansible_verbose: bool,
@@ -576,9 +647,20 @@ pub enum ClientsCommands {
         /// The default value from ansible.cfg is 50.
         #[clap(long)]
         forks: Option<usize>,
+        /// How long the environment should live for, as a number of hours or days, e.g., '12h' or
+        /// '7d'.
+        ///
+        /// Once it has expired, the environment can be removed by the 'environments reap' command.
+        #[clap(long, value_parser = parse_lifetime)]
+        lifetime: Option<Duration>,
         /// The name of the environment
         #[arg(short = 'n', long)]
         name: String,
+        /// The owner of the environment, who will be notified when it expires.
+        ///
+        /// If not supplied, the current user is the owner.
+        #[clap(long)]
+        owner: Option<String>,
         /// The cloud provider to deploy to.
         ///
         /// Valid values are "aws" or "digital-ocean".
@@ -616,11 +698,20 @@ pub enum ClientsCommands {
         start_repair_service: bool,
         /// The secret key for the wallet that will fund chunks that get uploaded for repaired
         /// addresses.
-        #[clap(long)]
-        wallet_secret_key: String,
+        ///
+        /// This can be a reference to the key, with 'env:NAME', 'file:PATH', 'vault:PATH' or
+        /// 'keyring:[SERVICE/]ACCOUNT'.
+        #[clap(long, value_parser = parse_secret_reference)]
+        wallet_secret_key: SecretReference,
     },
     /// Deploy a scan repairing service on client VMs
     DeployScanRepair {
+        /// Apply infrastructure changes that would destroy or replace existing resources, without
+        /// asking for confirmation.
+        ///
+        /// Without this, those changes are refused when there is no terminal to confirm them on.
+        #[arg(long)]
+        allow_destructive_changes: bool,
         /// Set to run Ansible with more verbose output.
         #[arg(long)]
         ansible_verbose: bool,
@@ -683,9 +774,20 @@ pub enum ClientsCommands {
         /// The default value from ansible.cfg is 50.
         #[clap(long)]
         forks: Option<usize>,
+        /// How long the environment should live for, as a number of hours or days, e.g., '12h' or
+        /// '7d'.
+        ///
+        /// Once it has expired, the environment can be removed by the 'environments reap' command.
+        #[clap(long, value_parser = parse_lifetime)]
+        lifetime: Option<Duration>,
         /// The name of the environment
         #[arg(short = 'n', long)]
         name: String,
+        /// The owner of the environment, who will be notified when it expires.
+        ///
+        /// If not supplied, the current user is the owner.
+        #[clap(long)]
+        owner: Option<String>,
         /// The cloud provider to deploy to.
         ///
         /// Valid values are "aws" or "digital-ocean".
@@ -724,11 +826,20 @@ pub enum ClientsCommands {
         start_service: bool,
         /// The secret key for the wallet that will fund chunks that get uploaded for repaired
         /// addresses.
-        #[clap(long)]
-        wallet_secret_key: String,
+        ///
+        /// This can be a reference to the key, with 'env:NAME', 'file:PATH', 'vault:PATH' or
+        /// 'keyring:[SERVICE/]ACCOUNT'.
+        #[clap(long, value_parser = parse_secret_reference)]
+        wallet_secret_key: SecretReference,
     },
     /// Deploy a new static downloader environment.
     DeployStaticDownloaders {
+        /// Apply infrastructure changes that would destroy or replace existing resources, without
+        /// asking for confirmation.
+        ///
+        /// Without this, those changes are refused when there is no terminal to confirm them on.
+        #[arg(long)]
+        allow_destructive_changes: bool,
         /// Set to run Ansible with more verbose output.
         #[arg(long)]
         ansible_verbose: bool,
@@ -819,6 +930,12 @@ pub enum ClientsCommands {
         /// The default value from ansible.cfg is 50.
         #[clap(long)]
         forks: Option<usize>,
+        /// How long the environment should live for, as a number of hours or days, e.g., '12h' or
+        /// '7d'.
+        ///
+        /// Once it has expired, the environment can be removed by the 'environments reap' command.
+        #[clap(long, value_parser = parse_lifetime)]
+        lifetime: Option<Duration>,
         /// The name of the environment
         #[arg(short = 'n', long)]
         name: String,
@@ -836,6 +953,11 @@ pub enum ClientsCommands {
         /// The networks contacts URL from an existing network.
         #[arg(long)]
         network_contacts_url: Option<String>,
+        /// The owner of the environment, who will be notified when it expires.
+        ///
+        /// If not supplied, the current user is the owner.
+        #[clap(long)]
+        owner: Option<String>,
         /// A peer from an existing network that the Ant client can connect to.
         ///
         /// Should be in the form of a multiaddr.
@@ -889,6 +1011,12 @@ pub enum ClientsCommands {
     },
     /// Deploy a new static uploader environment.
     DeployStaticUploader {
+        /// Apply infrastructure changes that would destroy or replace existing resources, without
+        /// asking for confirmation.
+        ///
+        /// Without this, those changes are refused when there is no terminal to confirm them on.
+        #[arg(long)]
+        allow_destructive_changes: bool,
         /// Supply a version number for the ant binary.
         ///
         /// There should be no 'v' prefix.
@@ -961,6 +1089,12 @@ pub enum ClientsCommands {
         /// This argument only applies if the EVM network type is 'custom'.
         #[arg(long)]
         evm_rpc_url: Option<String>,
+        /// How long the environment should live for, as a number of hours or days, e.g., '12h' or
+        /// '7d'.
+        ///
+        /// Once it has expired, the environment can be removed by the 'environments reap' command.
+        #[clap(long, value_parser = parse_lifetime)]
+        lifetime: Option<Duration>,
         /// The name of the environment
         #[arg(short = 'n', long)]
         name: String,
@@ -978,6 +1112,11 @@ pub enum ClientsCommands {
         /// The networks contacts URL from an existing network.
         #[arg(long)]
         network_contacts_url: Option<String>,
+        /// The owner of the environment, who will be notified when it expires.
+        ///
+        /// If not supplied, the current user is the owner.
+        #[clap(long)]
+        owner: Option<String>,
         /// A peer from an existing network that the Ant client can connect to.
         ///
         /// Should be in the form of a multiaddr.
@@ -1008,8 +1147,11 @@ pub enum ClientsCommands {
         #[clap(long)]
         upload_batch_size: Option<u16>,
         /// The secret key for the wallet with the funds for uploading.
-        #[arg(long, verbatim_doc_comment)]
-        wallet_secret_key: String,
+        ///
+        /// This can be a reference to the key, with 'env:NAME', 'file:PATH', 'vault:PATH' or
+        /// 'keyring:[SERVICE/]ACCOUNT'.
+        #[arg(long, verbatim_doc_comment, value_parser = parse_secret_reference)]
+        wallet_secret_key: SecretReference,
     },
     /// Enable downloaders on all client VMs in an environment.
     EnableDownloaders {
@@ -1101,6 +1243,12 @@ pub enum ClientsCommands {
     },
     /// Upscale clients for an existing environment.
     Upscale {
+        /// Apply infrastructure changes that would destroy or replace existing resources, without
+        /// asking for confirmation.
+        ///
+        /// Without this, those changes are refused when there is no terminal to confirm them on.
+        #[arg(long)]
+        allow_destructive_changes: bool,
         /// Supply a version number for the autonomi binary to be used for new Client VMs.
         ///
         /// There should be no 'v' prefix.
@@ -1129,8 +1277,11 @@ pub enum ClientsCommands {
         /// The secret key for the wallet that will fund all the ANT instances.
         ///
         /// This argument only applies when Arbitrum or Sepolia networks are used.
-        #[clap(long)]
-        funding_wallet_secret_key: Option<String>,
+        ///
+        /// Rather than the key itself, supply a reference to it, with 'env:NAME', 'file:PATH',
+        /// 'vault:PATH' for a file encrypted with ansible-vault, or 'keyring:[SERVICE/]ACCOUNT'.
+        #[clap(long, value_parser = parse_secret_reference)]
+        funding_wallet_secret_key: Option<SecretReference>,
         /// The amount of gas tokens to transfer to each ANT instance.
         /// Must be a decimal value between 0 and 1, e.g. "0.1"
         #[clap(long)]
@@ -1158,6 +1309,48 @@ pub enum ClientsCommands {
     },
 }
 
+impl ClientsCommands {
+    /// The name of the environment and the command, for the commands that change the
+    /// environment, which must hold its lock.
+    pub fn get_lock_details(&self) -> Option<(String, &'static str)> {
+        let (name, command) = match self {
+            ClientsCommands::Clean { name, .. } => (name, "clients clean"),
+            ClientsCommands::Deploy { name, .. } => (name, "clients deploy"),
+            ClientsCommands::DeployChunkTrackers { name, .. } => {
+                (name, "clients deploy-chunk-trackers")
+            }
+            ClientsCommands::DeployDataRetrieval { name, .. } => {
+                (name, "clients deploy-data-retrieval")
+            }
+            ClientsCommands::DeployRepairFiles { name, .. } => {
+                (name, "clients deploy-repair-files")
+            }
+            ClientsCommands::DeployScanRepair { name, .. } => (name, "clients deploy-scan-repair"),
+            ClientsCommands::DeployStaticDownloaders { name, .. } => {
+                (name, "clients deploy-static-downloaders")
+            }
+            ClientsCommands::DeployStaticUploader { name, .. } => {
+                (name, "clients deploy-static-uploader")
+            }
+            ClientsCommands::EnableDownloaders { name, .. } => (name, "clients enable-downloaders"),
+            ClientsCommands::FetchScanRepairResults { .. } => return None,
+            ClientsCommands::StartChunkTrackers { name, .. } => {
+                (name, "clients start-chunk-trackers")
+            }
+            ClientsCommands::StartDownloaders { name, .. } => (name, "clients start-downloaders"),
+            ClientsCommands::StartUploaders { name, .. } => (name, "clients start-uploaders"),
+            ClientsCommands::StopChunkTrackers { name, .. } => {
+                (name, "clients stop-chunk-trackers")
+            }
+            ClientsCommands::StopDownloaders { name, .. } => (name, "clients stop-downloaders"),
+            ClientsCommands::StopUploaders { name, .. } => (name, "clients stop-uploaders"),
+            ClientsCommands::Upgrade { name, .. } => (name, "clients upgrade"),
+            ClientsCommands::Upscale { name, .. } => (name, "clients upscale"),
+        };
+        Some((name.clone(), command))
+    }
+}
+
 pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
     match cmd {
         ClientsCommands::EnableDownloaders { name, provider } => {
@@ -1199,17 +1392,32 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             println!("Files saved to: scan-repair-results/{}/", name);
             Ok(())
         }
-        ClientsCommands::Clean { name, provider } => {
+        ClientsCommands::Clean {
+            drain_funds,
+            name,
+            provider,
+        } => {
             println!("Cleaning Client environment '{name}'...");
             let client_deployer = ClientsDeployBuilder::default()
                 .environment_name(&name)
                 .provider(provider)
                 .build()?;
-            client_deployer.clean().await?;
+
+            let policy = EnvironmentPolicy::get(&name, &client_deployer.object_store).await?;
+            policy.enforce(&name, "clients clean", || {
+                client_deployer.terraform_runner.init()?;
+                let resources = client_deployer.terraform_runner.show(&name)?;
+                Ok(DestructivePreview::from_terraform_state(&resources))
+            })?;
+
+            client_deployer
+                .clean(policy.should_drain_funds(drain_funds))
+                .await?;
             println!("Client environment '{name}' cleaned");
             Ok(())
         }
         ClientsCommands::Deploy {
+            allow_destructive_changes,
             ansible_verbose,
             ant_version,
             branch,
@@ -1232,10 +1440,12 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             funding_wallet_secret_key,
             initial_gas,
             initial_tokens,
+            lifetime,
             max_uploads,
             name,
             network_id,
             network_contacts_url,
+            owner,
             peer,
             provider,
             region,
@@ -1303,6 +1513,13 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                     total_uploaders,
                 ));
             }
+            let funding_wallet_secret_key = funding_wallet_secret_key
+                .map(|sk| sk.resolve())
+                .transpose()?;
+            let wallet_secret_key = wallet_secret_key
+                .iter()
+                .map(|sk| sk.resolve())
+                .collect::<Result<Vec<_>, _>>()?;
 
             let binary_option = get_binary_option(
                 branch,
@@ -1344,6 +1561,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             };
 
             let options = ClientsDeployOptions {
+                allow_destructive_changes,
                 binary_option,
                 chunk_size,
                 chunk_tracker_data_addresses,
@@ -1363,6 +1581,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                 funding_wallet_secret_key,
                 initial_gas,
                 initial_tokens,
+                lifetime,
                 max_archived_log_files: 1,
                 max_log_files: 1,
                 max_uploads,
@@ -1370,6 +1589,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                 network_contacts_url,
                 network_id: Some(network_id),
                 output_inventory_dir_path: client_deployer.working_directory_path.join("inventory"),
+                owner,
                 peer,
                 performance_verifier_batch_size: None,
                 random_verifier_batch_size: None,
@@ -1408,6 +1628,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             Ok(())
         }
         ClientsCommands::DeployChunkTrackers {
+            allow_destructive_changes,
             ansible_verbose,
             ant_version,
             branch,
@@ -1424,9 +1645,11 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             evm_payment_token_address,
             evm_rpc_url,
             forks,
+            lifetime,
             name,
             network_id,
             network_contacts_url,
+            owner,
             peer,
             provider,
             region,
@@ -1502,6 +1725,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             };
 
             let options = ClientsDeployOptions {
+                allow_destructive_changes,
                 binary_option,
                 chunk_size,
                 chunk_tracker_data_addresses,
@@ -1521,6 +1745,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                 funding_wallet_secret_key: None,
                 initial_gas: None,
                 initial_tokens: None,
+                lifetime,
                 max_archived_log_files: 1,
                 max_log_files: 1,
                 max_uploads: None,
@@ -1528,6 +1753,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                 network_contacts_url,
                 network_id: Some(network_id),
                 output_inventory_dir_path: client_deployer.working_directory_path.join("inventory"),
+                owner,
                 peer,
                 performance_verifier_batch_size: None,
                 random_verifier_batch_size: None,
@@ -1562,6 +1788,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             Ok(())
         }
         ClientsCommands::DeployDataRetrieval {
+            allow_destructive_changes,
             ansible_verbose,
             ant_version,
             branch,
@@ -1576,9 +1803,11 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             evm_payment_token_address,
             evm_rpc_url,
             forks,
+            lifetime,
             name,
             network_id,
             network_contacts_url,
+            owner,
             provider,
             region,
             repo_owner,
@@ -1654,6 +1883,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             };
 
             let options = ClientsDeployOptions {
+                allow_destructive_changes,
                 binary_option,
                 chunk_size,
                 chunk_tracker_data_addresses: vec![],
@@ -1673,6 +1903,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                 funding_wallet_secret_key: None,
                 initial_gas: None,
                 initial_tokens: None,
+                lifetime,
                 max_archived_log_files: 1,
                 max_log_files: 1,
                 max_uploads: None,
@@ -1680,6 +1911,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                 network_contacts_url,
                 network_id: Some(network_id),
                 output_inventory_dir_path: client_deployer.working_directory_path.join("inventory"),
+                owner,
                 peer: None,
                 performance_verifier_batch_size: None,
                 random_verifier_batch_size: None,
@@ -1714,6 +1946,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             Ok(())
         }
         ClientsCommands::DeployRepairFiles {
+            allow_destructive_changes,
             ansible_verbose,
             ant_version,
             branch,
@@ -1724,7 +1957,9 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             disable_metrics,
             environment_type,
             forks,
+            lifetime,
             name,
+            owner,
             provider,
             region,
             repo_owner,
@@ -1733,6 +1968,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             start_repair_service,
             wallet_secret_key,
         } => {
+            let wallet_secret_key = wallet_secret_key.resolve()?;
             if (branch.is_some() && repo_owner.is_none())
                 || (branch.is_none() && repo_owner.is_some())
             {
@@ -1786,6 +2022,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             };
 
             let options = ClientsDeployOptions {
+                allow_destructive_changes,
                 binary_option,
                 chunk_size,
                 chunk_tracker_data_addresses: Vec::new(),
@@ -1805,6 +2042,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                 funding_wallet_secret_key: None,
                 initial_gas: None,
                 initial_tokens: None,
+                lifetime,
                 max_archived_log_files: 1,
                 max_log_files: 1,
                 max_uploads: None,
@@ -1812,6 +2050,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                 network_contacts_url: None,
                 network_id: None,
                 output_inventory_dir_path: client_deployer.working_directory_path.join("inventory"),
+                owner,
                 peer: None,
                 performance_verifier_batch_size: None,
                 random_verifier_batch_size: None,
@@ -1849,6 +2088,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             Ok(())
         }
         ClientsCommands::DeployScanRepair {
+            allow_destructive_changes,
             ansible_verbose,
             ant_version,
             branch,
@@ -1859,7 +2099,9 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             disable_metrics,
             environment_type,
             forks,
+            lifetime,
             name,
+            owner,
             provider,
             region,
             repo_owner,
@@ -1869,6 +2111,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             start_service,
             wallet_secret_key,
         } => {
+            let wallet_secret_key = wallet_secret_key.resolve()?;
             if (branch.is_some() && repo_owner.is_none())
                 || (branch.is_none() && repo_owner.is_some())
             {
@@ -1922,6 +2165,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             };
 
             let options = ClientsDeployOptions {
+                allow_destructive_changes,
                 binary_option,
                 chunk_size,
                 chunk_tracker_data_addresses: Vec::new(),
@@ -1941,6 +2185,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                 funding_wallet_secret_key: None,
                 initial_gas: None,
                 initial_tokens: None,
+                lifetime,
                 max_archived_log_files: 1,
                 max_log_files: 1,
                 max_uploads: None,
@@ -1948,6 +2193,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                 network_contacts_url: None,
                 network_id: None,
                 output_inventory_dir_path: client_deployer.working_directory_path.join("inventory"),
+                owner,
                 peer: None,
                 performance_verifier_batch_size: None,
                 random_verifier_batch_size: None,
@@ -1985,6 +2231,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             Ok(())
         }
         ClientsCommands::DeployStaticDownloaders {
+            allow_destructive_changes,
             ansible_verbose,
             ant_version,
             branch,
@@ -2002,9 +2249,11 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             evm_rpc_url,
             file_address,
             forks,
+            lifetime,
             name,
             network_id,
             network_contacts_url,
+            owner,
             peer,
             performance_verifier_batch_size,
             provider,
@@ -2085,6 +2334,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             };
 
             let options = ClientsDeployOptions {
+                allow_destructive_changes,
                 binary_option,
                 chunk_size,
                 chunk_tracker_data_addresses: Vec::new(),
@@ -2105,6 +2355,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                 funding_wallet_secret_key: None,
                 initial_gas: None,
                 initial_tokens: None,
+                lifetime,
                 max_archived_log_files: 1,
                 max_log_files: 1,
                 max_uploads: None,
@@ -2112,6 +2363,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                 network_contacts_url,
                 network_id: Some(network_id),
                 output_inventory_dir_path: client_deployer.working_directory_path.join("inventory"),
+                owner,
                 peer,
                 performance_verifier_batch_size,
                 random_verifier_batch_size,
@@ -2146,6 +2398,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             Ok(())
         }
         ClientsCommands::DeployStaticUploader {
+            allow_destructive_changes,
             ant_version,
             branch,
             chunk_size,
@@ -2158,9 +2411,11 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             evm_network_type,
             evm_payment_token_address,
             evm_rpc_url,
+            lifetime,
             name,
             network_id,
             network_contacts_url,
+            owner,
             peer,
             provider,
             region,
@@ -2168,6 +2423,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             upload_batch_size,
             wallet_secret_key,
         } => {
+            let wallet_secret_key = wallet_secret_key.resolve()?;
             if (branch.is_some() && repo_owner.is_none())
                 || (branch.is_none() && repo_owner.is_some())
             {
@@ -2232,6 +2488,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             };
 
             let options = ClientsDeployOptions {
+                allow_destructive_changes,
                 binary_option,
                 chunk_size,
                 chunk_tracker_data_addresses: Vec::new(),
@@ -2251,6 +2508,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                 funding_wallet_secret_key: None,
                 initial_gas: None,
                 initial_tokens: None,
+                lifetime,
                 max_archived_log_files: 1,
                 max_log_files: 1,
                 max_uploads: None,
@@ -2258,6 +2516,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
                 network_contacts_url,
                 network_id: Some(network_id),
                 output_inventory_dir_path: client_deployer.working_directory_path.join("inventory"),
+                owner,
                 peer,
                 performance_verifier_batch_size: None,
                 random_verifier_batch_size: None,
@@ -2432,6 +2691,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             Ok(())
         }
         ClientsCommands::Upscale {
+            allow_destructive_changes,
             autonomi_version,
             desired_client_vm_count,
             desired_uploaders_count,
@@ -2446,6 +2706,9 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
             start_random_verifier,
             start_performance_verifier,
         } => {
+            let funding_wallet_secret_key = funding_wallet_secret_key
+                .map(|sk| sk.resolve())
+                .transpose()?;
             let gas_amount = if let Some(amount) = gas_amount {
                 let amount: f64 = amount.parse().map_err(|_| {
                     eyre!("Invalid gas amount format. Must be a decimal value, e.g. '0.1'")
@@ -2474,6 +2737,7 @@ pub async fn handle_clients_command(cmd: ClientsCommands) -> Result<()> {
 
             testnet_deployer
                 .upscale_clients(&UpscaleOptions {
+                    allow_destructive_changes,
                     ansible_verbose: false,
                     ant_version: Some(autonomi_version),
                     current_inventory: inventory,
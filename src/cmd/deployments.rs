@@ -9,14 +9,21 @@ use super::{get_binary_option, upload_options_to_s3, OptionsType};
 use alloy::primitives::U256;
 use color_eyre::{eyre::eyre, Help, Result};
 use sn_testnet_deploy::{
-    bootstrap::BootstrapOptions, calculate_size_per_attached_volume, deploy::DeployOptions,
-    error::Error, inventory::DeploymentInventoryService, upscale::UpscaleOptions, BinaryOption,
-    CloudProvider, EnvironmentType, EvmNetwork, LogFormat, TestnetDeployBuilder,
+    bootstrap::BootstrapOptions,
+    calculate_size_per_attached_volume,
+    deploy::DeployOptions,
+    error::Error,
+    inventory::DeploymentInventoryService,
+    policy::{DestructivePreview, EnvironmentPolicy},
+    secrets::SecretReference,
+    upscale::UpscaleOptions,
+    BinaryOption, CloudProvider, EnvironmentType, EvmNetwork, LogFormat, TestnetDeployBuilder,
 };
 use std::time::Duration;
 
 #[allow(clippy::too_many_arguments)]
 pub async fn handle_bootstrap(
+    allow_destructive_changes: bool,
     ansible_verbose: bool,
     antctl_version: Option<String>,
     antnode_features: Option<Vec<String>>,
@@ -34,6 +41,7 @@ pub async fn handle_bootstrap(
     full_cone_private_node_vm_count: Option<u16>,
     full_cone_private_node_volume_size: Option<u16>,
     interval: Duration,
+    lifetime: Option<Duration>,
     log_format: Option<LogFormat>,
     max_archived_log_files: u16,
     max_log_files: u16,
@@ -44,6 +52,7 @@ pub async fn handle_bootstrap(
     node_vm_count: Option<u16>,
     node_vm_size: Option<String>,
     node_volume_size: Option<u16>,
+    owner: Option<String>,
     peer: Option<String>,
     provider: CloudProvider,
     region: String,
@@ -144,6 +153,7 @@ pub async fn handle_bootstrap(
 
     testnet_deployer
         .bootstrap(&BootstrapOptions {
+            allow_destructive_changes,
             binary_option,
             chunk_size,
             enable_logging: true,
@@ -160,6 +170,7 @@ pub async fn handle_bootstrap(
                 ))
             }),
             interval,
+            lifetime,
             log_format,
             max_archived_log_files,
             max_log_files,
@@ -176,6 +187,7 @@ pub async fn handle_bootstrap(
                 .working_directory_path
                 .join("ansible")
                 .join("inventory"),
+            owner,
             peer,
             region,
             rewards_address,
@@ -202,8 +214,28 @@ pub async fn handle_bootstrap(
     Ok(())
 }
 
+pub async fn handle_clean(drain_funds: bool, name: String, provider: CloudProvider) -> Result<()> {
+    let testnet_deployer = TestnetDeployBuilder::default()
+        .environment_name(&name)
+        .provider(provider)
+        .build()?;
+
+    let policy = EnvironmentPolicy::get(&name, &testnet_deployer.object_store).await?;
+    policy.enforce(&name, "clean", || {
+        testnet_deployer.terraform_runner.init()?;
+        let resources = testnet_deployer.terraform_runner.show(&name)?;
+        Ok(DestructivePreview::from_terraform_state(&resources))
+    })?;
+
+    testnet_deployer
+        .clean(policy.should_drain_funds(drain_funds))
+        .await?;
+    Ok(())
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn handle_deploy(
+    allow_destructive_changes: bool,
     ansible_verbose: bool,
     ant_version: Option<String>,
     antctl_version: Option<String>,
@@ -229,11 +261,12 @@ pub async fn handle_deploy(
     full_cone_private_node_vm_count: Option<u16>,
     full_cone_private_node_volume_size: Option<u16>,
     forks: Option<usize>,
-    funding_wallet_secret_key: Option<String>,
+    funding_wallet_secret_key: Option<SecretReference>,
     genesis_node_volume_size: Option<u16>,
     initial_gas: Option<U256>,
     initial_tokens: Option<U256>,
     interval: std::time::Duration,
+    lifetime: Option<Duration>,
     log_format: Option<LogFormat>,
     max_archived_log_files: u16,
     max_log_files: u16,
@@ -247,6 +280,7 @@ pub async fn handle_deploy(
     node_vm_count: Option<u16>,
     node_vm_size: Option<String>,
     node_volume_size: Option<u16>,
+    owner: Option<String>,
     peer_cache_node_count: Option<u16>,
     peer_cache_node_vm_count: Option<u16>,
     peer_cache_node_vm_size: Option<String>,
@@ -300,6 +334,9 @@ pub async fn handle_deploy(
             "Wallet secret key is required for Arbitrum or Sepolia networks"
         ));
     }
+    let funding_wallet_secret_key = funding_wallet_secret_key
+        .map(|sk| sk.resolve())
+        .transpose()?;
 
     let binary_option = get_binary_option(
         branch,
@@ -361,6 +398,7 @@ pub async fn handle_deploy(
         port_restricted_cone_private_node_count.unwrap_or(0);
 
     let deploy_options = DeployOptions {
+        allow_destructive_changes,
         binary_option: binary_option.clone(),
         chunk_size,
         chunk_tracker_data_addresses,
@@ -391,6 +429,7 @@ pub async fn handle_deploy(
         initial_gas,
         initial_tokens,
         interval,
+        lifetime,
         log_format,
         max_archived_log_files,
         max_log_files,
@@ -408,6 +447,7 @@ pub async fn handle_deploy(
             .working_directory_path
             .join("ansible")
             .join("inventory"),
+        owner,
         peer_cache_node_count,
         peer_cache_node_vm_count,
         peer_cache_node_vm_size,
@@ -491,6 +531,7 @@ pub async fn handle_deploy(
 
 #[allow(clippy::too_many_arguments)]
 pub async fn handle_upscale(
+    allow_destructive_changes: bool,
     ansible_verbose: bool,
     ant_version: Option<String>,
     antctl_version: Option<String>,
@@ -509,7 +550,7 @@ pub async fn handle_upscale(
     start_download_verifier: bool,
     start_performance_verifier: bool,
     start_random_verifier: bool,
-    funding_wallet_secret_key: Option<String>,
+    funding_wallet_secret_key: Option<SecretReference>,
     infra_only: bool,
     interval: Duration,
     max_archived_log_files: u16,
@@ -564,6 +605,9 @@ pub async fn handle_upscale(
             "The funding wallet secret key is required to upscale the Clients"
         ));
     }
+    let funding_wallet_secret_key = funding_wallet_secret_key
+        .map(|sk| sk.resolve())
+        .transpose()?;
     if let Some(branch) = branch {
         println!("The upscale will use the binaries built in the original deployment");
         inventory.binary_option = BinaryOption::BuildFromSource {
@@ -617,6 +661,7 @@ pub async fn handle_upscale(
 
     testnet_deployer
         .upscale(&UpscaleOptions {
+            allow_destructive_changes,
             ansible_verbose,
             ant_version,
             current_inventory: inventory,
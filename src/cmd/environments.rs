@@ -0,0 +1,114 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use super::{parse_lifetime, parse_provider};
+use clap::Subcommand;
+use color_eyre::Result;
+use sn_testnet_deploy::{
+    cost::{get_environment_spend, print_environment_spend, PricingTable},
+    dry_run,
+    environments::{
+        find_expired_environments, list_environments, notify_expired_environment_owners,
+        print_environment_list, print_expired_environments, reap_environments,
+    },
+    CloudProvider,
+};
+use std::time::Duration;
+
+#[derive(Subcommand, Debug)]
+pub enum EnvironmentsCommands {
+    /// Show the spend since creation for every environment that has droplets.
+    ///
+    /// The spend is based on how long each droplet and attached volume has existed, using the
+    /// locally cached droplet prices.
+    Cost {
+        /// Print the spend as JSON.
+        #[clap(long)]
+        json: bool,
+        /// Retrieve the droplet prices from Digital Ocean, rather than using the local cache.
+        #[clap(long)]
+        refresh_pricing: bool,
+    },
+    /// List the environments, with their details.
+    ///
+    /// The environments are gathered from the Terraform workspaces and the environment details in
+    /// S3. The VM and node counts and the binary versions are only available for environments
+    /// whose inventory is cached on this machine.
+    List {
+        /// Print the environments as JSON.
+        #[clap(long)]
+        json: bool,
+        /// The cloud provider the environments were deployed to.
+        #[clap(long, value_parser = parse_provider, verbatim_doc_comment, default_value_t = CloudProvider::DigitalOcean)]
+        provider: CloudProvider,
+    },
+    /// Remove the Development and Staging environments that have expired.
+    ///
+    /// The owners of the expired environments are notified on Slack. An environment is only
+    /// cleaned once the grace period since its expiry has elapsed, which gives its owner time to
+    /// extend it. With --dry-run, the expired environments are only reported.
+    Reap {
+        /// Also reap expired Production environments.
+        #[clap(long)]
+        force: bool,
+        /// The time to wait after an environment expires before it is cleaned, as a number of
+        /// hours or days, e.g., '12h' or '7d'.
+        #[clap(long, value_parser = parse_lifetime, default_value = "24h")]
+        grace_period: Duration,
+        /// The cloud provider the environments were deployed to.
+        #[clap(long, value_parser = parse_provider, verbatim_doc_comment, default_value_t = CloudProvider::DigitalOcean)]
+        provider: CloudProvider,
+    },
+}
+
+pub async fn handle_environments_command(cmd: EnvironmentsCommands) -> Result<()> {
+    match cmd {
+        EnvironmentsCommands::Cost {
+            json,
+            refresh_pricing,
+        } => {
+            let pricing = PricingTable::get(refresh_pricing).await?;
+            let spend = get_environment_spend(&pricing).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&spend)?);
+            } else {
+                print_environment_spend(&spend);
+            }
+            Ok(())
+        }
+        EnvironmentsCommands::List { json, provider } => {
+            let environments = list_environments(provider).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&environments)?);
+            } else {
+                print_environment_list(&environments);
+            }
+            Ok(())
+        }
+        EnvironmentsCommands::Reap {
+            force,
+            grace_period,
+            provider,
+        } => {
+            let expired = find_expired_environments(grace_period, force).await?;
+            print_expired_environments(&expired);
+            if dry_run::is_enabled() || expired.is_empty() {
+                return Ok(());
+            }
+
+            // A failure to notify shouldn't prevent the expired environments from being reaped.
+            if let Err(err) = notify_expired_environment_owners(&expired).await {
+                println!("Failed to notify the owners of the expired environments: {err}");
+            }
+            let to_reap = expired
+                .into_iter()
+                .filter(|environment| environment.past_grace_period)
+                .collect::<Vec<_>>();
+            reap_environments(&to_reap, provider).await?;
+            Ok(())
+        }
+    }
+}
@@ -11,20 +11,52 @@ use clap::Subcommand;
 use color_eyre::{eyre::eyre, Result};
 use evmlib::Network;
 use sn_testnet_deploy::{
-    funding::FundingOptions, get_anvil_node_data_hardcoded, get_environment_details,
-    inventory::DeploymentInventoryService, CloudProvider, EvmNetwork, TestnetDeployBuilder,
+    funding::{
+        balances::WalletOwner,
+        ledger::{FundingLedger, LedgerSummary},
+        FundingOptions,
+    },
+    get_anvil_node_data_hardcoded, get_environment_details,
+    inventory::DeploymentInventoryService,
+    object_store::get_object_store,
+    secrets::SecretReference,
+    CloudProvider, EvmDetails, EvmNetwork, TestnetDeployBuilder,
 };
 use std::str::FromStr;
 
 #[derive(Subcommand, Debug)]
 pub enum FundsCommand {
+    /// Show the token and gas balances of the funding wallet and every ANT uploader wallet.
+    ///
+    /// Wallets with a balance below either threshold are flagged.
+    Balances {
+        /// Flag wallets with less than this amount of gas.
+        ///
+        /// This is a decimal value, e.g., "0.01".
+        #[clap(long, value_parser = parse_token_amount, default_value = "0.001")]
+        gas_threshold: U256,
+        /// The name of the environment.
+        #[arg(short = 'n', long)]
+        name: String,
+        /// The cloud provider for the environment.
+        #[clap(long, value_parser = parse_provider, verbatim_doc_comment, default_value_t = CloudProvider::DigitalOcean)]
+        provider: CloudProvider,
+        /// Flag wallets with less than this amount of tokens.
+        ///
+        /// This is a decimal value, e.g., "0.5".
+        #[clap(long, value_parser = parse_token_amount, default_value = "1")]
+        token_threshold: U256,
+    },
     /// Deposit tokens and gas from the provided funding wallet secret key to all the ANT uploader.
     Deposit {
         /// The secret key for the wallet that will fund all the ANT uploader.
         ///
         /// This argument only applies when Arbitrum or Sepolia networks are used.
-        #[clap(long)]
-        funding_wallet_secret_key: Option<String>,
+        ///
+        /// Rather than the key itself, supply a reference to it, with 'env:NAME', 'file:PATH',
+        /// 'vault:PATH' for a file encrypted with ansible-vault, or 'keyring:[SERVICE/]ACCOUNT'.
+        #[clap(long, value_parser = parse_secret_reference)]
+        funding_wallet_secret_key: Option<SecretReference>,
         /// The number of gas to transfer, in U256
         ///
         /// 1 ETH = 1_000_000_000_000_000_000. Defaults to 0.1 ETH
@@ -56,10 +88,86 @@ pub enum FundsCommand {
         #[clap(long)]
         to_address: Option<String>,
     },
+    /// Show the totals of the tokens and gas deposited to and drained from the ANT instances.
+    ///
+    /// The totals come from the funding ledger, which records every deposit and drain
+    /// transaction for the environment.
+    Report {
+        /// The name of the environment.
+        #[arg(short = 'n', long)]
+        name: String,
+    },
 }
 
 pub async fn handle_funds_command(cmd: FundsCommand) -> Result<()> {
     match cmd {
+        FundsCommand::Balances {
+            gas_threshold,
+            name,
+            provider,
+            token_threshold,
+        } => {
+            let testnet_deployer = TestnetDeployBuilder::default()
+                .environment_name(&name)
+                .provider(provider)
+                .build()?;
+            let inventory_services = DeploymentInventoryService::from(&testnet_deployer);
+            inventory_services
+                .generate_or_retrieve_inventory(&name, true, None)
+                .await?;
+
+            let environment_details =
+                get_environment_details(&name, &inventory_services.object_store).await?;
+            let network =
+                get_evm_network(&environment_details.evm_details, "Querying wallet balances")?;
+            let funding_wallet_address = environment_details
+                .funding_wallet_address
+                .as_deref()
+                .map(Address::from_str)
+                .transpose()?;
+
+            let balances = testnet_deployer
+                .ansible_provisioner
+                .get_wallet_balances(funding_wallet_address, &network)
+                .await?;
+
+            println!(
+                "{:<28} {:>8} {:<42} {:>24} {:>16}",
+                "VM", "Uploader", "Address", "Tokens", "Gas"
+            );
+            let mut low_balance_count = 0;
+            for balance in balances.iter() {
+                let (vm_name, uploader) = match &balance.owner {
+                    WalletOwner::Funding => ("funding wallet".to_string(), "-".to_string()),
+                    WalletOwner::Uploader { vm_name, number } => {
+                        (vm_name.clone(), number.to_string())
+                    }
+                };
+                let is_low = balance.is_low(token_threshold, gas_threshold);
+                if is_low {
+                    low_balance_count += 1;
+                }
+                println!(
+                    "{:<28} {:>8} {:<42} {:>24} {:>16}{}",
+                    vm_name,
+                    uploader,
+                    balance.address.to_string(),
+                    format_amount(balance.token_balance),
+                    format_amount(balance.gas_balance),
+                    if is_low { "  LOW" } else { "" }
+                );
+            }
+            if low_balance_count > 0 {
+                println!(
+                    "{low_balance_count} wallets have less than {} tokens or {} gas",
+                    format_amount(token_threshold),
+                    format_amount(gas_threshold)
+                );
+            } else {
+                println!("All wallets are above the thresholds");
+            }
+            Ok(())
+        }
         FundsCommand::Deposit {
             funding_wallet_secret_key,
             gas_to_transfer,
@@ -67,6 +175,9 @@ pub async fn handle_funds_command(cmd: FundsCommand) -> Result<()> {
             provider,
             tokens_to_transfer,
         } => {
+            let funding_wallet_secret_key = funding_wallet_secret_key
+                .map(|sk| sk.resolve())
+                .transpose()?;
             let testnet_deployer = TestnetDeployBuilder::default()
                 .environment_name(&name)
                 .provider(provider)
@@ -77,7 +188,7 @@ pub async fn handle_funds_command(cmd: FundsCommand) -> Result<()> {
                 .await?;
 
             let environment_details =
-                get_environment_details(&name, &inventory_services.s3_repository).await?;
+                get_environment_details(&name, &inventory_services.object_store).await?;
 
             // For Anvil network, use the hardcoded deployer wallet key if not provided
             let funding_wallet_secret_key = if funding_wallet_secret_key.is_none()
@@ -124,7 +235,7 @@ pub async fn handle_funds_command(cmd: FundsCommand) -> Result<()> {
                 .await?;
 
             let environment_details =
-                get_environment_details(&name, &inventory_services.s3_repository).await?;
+                get_environment_details(&name, &inventory_services.object_store).await?;
 
             let to_address = if let Some(to_address) = to_address {
                 Address::from_str(&to_address)?
@@ -136,36 +247,10 @@ pub async fn handle_funds_command(cmd: FundsCommand) -> Result<()> {
                 ));
             };
 
-            let network = match environment_details.evm_details.network {
-                EvmNetwork::Anvil => {
-                    return Err(eyre!(
-                        "Draining funds from ANT instances is not supported for an Anvil network"
-                    ));
-                }
-                EvmNetwork::ArbitrumOne => Network::ArbitrumOne,
-                EvmNetwork::ArbitrumSepoliaTest => Network::ArbitrumSepoliaTest,
-                EvmNetwork::Custom => {
-                    if let (
-                        Some(emv_data_payments_address),
-                        Some(evm_payment_token_address),
-                        Some(evm_rpc_url),
-                    ) = (
-                        environment_details.evm_details.data_payments_address,
-                        environment_details.evm_details.payment_token_address,
-                        environment_details.evm_details.rpc_url,
-                    ) {
-                        Network::new_custom(
-                            &evm_rpc_url,
-                            &evm_payment_token_address,
-                            &emv_data_payments_address,
-                        )
-                    } else {
-                        return Err(eyre!(
-                            "Custom EVM details not found in the environment details"
-                        ));
-                    }
-                }
-            };
+            let network = get_evm_network(
+                &environment_details.evm_details,
+                "Draining funds from ANT instances",
+            )?;
 
             testnet_deployer
                 .ansible_provisioner
@@ -174,5 +259,130 @@ pub async fn handle_funds_command(cmd: FundsCommand) -> Result<()> {
 
             Ok(())
         }
+        FundsCommand::Report { name } => {
+            let ledger = FundingLedger::new(&name, get_object_store()?);
+            let entries = ledger.get_entries().await?;
+            if entries.is_empty() {
+                println!("No funding transactions have been recorded for {name}");
+                return Ok(());
+            }
+
+            let summary = LedgerSummary::from_entries(&entries);
+            println!("Funding report for {name}");
+            println!("{:<12} {:>28} {:>28}", "", "Tokens", "Gas");
+            for (label, tokens, gas) in [
+                ("Spent", summary.tokens_spent, summary.gas_spent),
+                ("Recovered", summary.tokens_recovered, summary.gas_recovered),
+                (
+                    "Outstanding",
+                    summary.tokens_outstanding(),
+                    summary.gas_outstanding(),
+                ),
+            ] {
+                println!(
+                    "{:<12} {:>28} {:>28}",
+                    label,
+                    format_amount(tokens),
+                    format_amount(gas)
+                );
+            }
+            println!(
+                "{} successful and {} failed transactions",
+                summary.successful_transactions, summary.failed_transactions
+            );
+
+            let failed_entries = entries
+                .iter()
+                .filter(|entry| !entry.succeeded)
+                .collect::<Vec<_>>();
+            if !failed_entries.is_empty() {
+                println!("Failed transactions:");
+                for entry in failed_entries {
+                    println!(
+                        "{} {} {} -> {} ({} tokens, {} gas): {}",
+                        entry.timestamp,
+                        entry.kind,
+                        entry.from,
+                        entry.to,
+                        format_amount(entry.get_token_amount()),
+                        format_amount(entry.get_gas_amount()),
+                        entry.error.as_deref().unwrap_or("unknown error")
+                    );
+                }
+            }
+            println!("The outstanding gas includes the gas used for uploads and transaction fees");
+            Ok(())
+        }
+    }
+}
+
+/// Get the EVM network used by an environment, for querying balances or making transfers.
+///
+/// The `action` describes what the network is needed for, in the error for an Anvil network.
+fn get_evm_network(evm_details: &EvmDetails, action: &str) -> Result<Network> {
+    let network = match evm_details.network {
+        EvmNetwork::Anvil => {
+            return Err(eyre!("{action} is not supported for an Anvil network"));
+        }
+        EvmNetwork::ArbitrumOne => Network::ArbitrumOne,
+        EvmNetwork::ArbitrumSepoliaTest => Network::ArbitrumSepoliaTest,
+        EvmNetwork::Custom => {
+            if let (
+                Some(emv_data_payments_address),
+                Some(evm_payment_token_address),
+                Some(evm_rpc_url),
+            ) = (
+                &evm_details.data_payments_address,
+                &evm_details.payment_token_address,
+                &evm_details.rpc_url,
+            ) {
+                Network::new_custom(
+                    evm_rpc_url,
+                    evm_payment_token_address,
+                    emv_data_payments_address,
+                )
+            } else {
+                return Err(eyre!(
+                    "Custom EVM details not found in the environment details"
+                ));
+            }
+        }
+    };
+    Ok(network)
+}
+
+/// Format an amount in wei as a decimal number of whole units, e.g., '100.5'.
+fn format_amount(amount: U256) -> String {
+    let unit = U256::from(1_000_000_000_000_000_000u64);
+    let whole = amount / unit;
+    let fraction = format!("{:0>18}", (amount % unit).to_string());
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{whole}.{fraction}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_amount_trims_trailing_zeros() {
+        assert_eq!(format_amount(U256::ZERO), "0");
+        assert_eq!(format_amount(U256::from(1_000_000_000_000_000_000u64)), "1");
+        assert_eq!(
+            format_amount(U256::from(100_500_000_000_000_000_000u128)),
+            "100.5"
+        );
+        assert_eq!(format_amount(U256::from(1)), "0.000000000000000001");
+    }
+
+    #[test]
+    fn test_format_amount_round_trips_through_parse_token_amount() {
+        for val in ["1", "0.001", "0.5", "100.25", "0.000000000000000001"] {
+            assert_eq!(format_amount(parse_token_amount(val).unwrap()), val);
+        }
     }
 }
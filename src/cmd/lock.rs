@@ -0,0 +1,80 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use clap::Subcommand;
+use color_eyre::{Report, Result};
+use sn_testnet_deploy::{
+    dry_run,
+    lock::{break_environment_lock, get_environment_lock, EnvironmentLockGuard},
+};
+use std::future::Future;
+
+#[derive(Subcommand, Debug)]
+pub enum LockCommands {
+    /// Remove the lock on an environment.
+    ///
+    /// Without the --force argument, only a stale lock, whose holder has stopped sending
+    /// heartbeats, will be removed.
+    Break {
+        /// Remove the lock even if its holder still appears to be running.
+        #[clap(long)]
+        force: bool,
+        /// The name of the environment.
+        #[arg(short = 'n', long)]
+        name: String,
+    },
+    /// Show who holds the lock on an environment.
+    Status {
+        /// The name of the environment.
+        #[arg(short = 'n', long)]
+        name: String,
+    },
+}
+
+pub async fn handle_lock_command(cmd: LockCommands) -> Result<()> {
+    match cmd {
+        LockCommands::Break { force, name } => {
+            break_environment_lock(&name, force).await?;
+            println!("The lock on {name} has been removed");
+            Ok(())
+        }
+        LockCommands::Status { name } => {
+            match get_environment_lock(&name).await? {
+                Some(lock) => lock.print(),
+                None => println!("The {name} environment is not locked"),
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Run a command that changes an environment while holding the lock on the environment.
+///
+/// The lock is released whether or not the command succeeds, and the result of the command is
+/// returned even if the release fails. During a dry run, the lock is not
+/// acquired, because nothing will be changed.
+pub async fn run_with_lock<T, E>(
+    name: &str,
+    command: &str,
+    future: impl Future<Output = std::result::Result<T, E>>,
+) -> Result<T>
+where
+    Report: From<E>,
+{
+    if dry_run::is_enabled() {
+        return Ok(future.await?);
+    }
+    let lock = EnvironmentLockGuard::acquire(name, command).await?;
+    let result = future.await;
+    // A failure to release the lock must not replace the result of the command. The lock will
+    // become stale once its heartbeats stop, so it can still be broken.
+    if let Err(err) = lock.release().await {
+        println!(
+            "Failed to release the lock on {name}: {err}. Use 'lock break --name {name}' to remove it."
+        );
+    }
+    Ok(result?)
+}
@@ -6,14 +6,61 @@
 
 use super::*;
 
+use chrono::{DateTime, NaiveDateTime, Utc};
 use clap::Subcommand;
-use color_eyre::Result;
+use color_eyre::{eyre::eyre, Result};
+use regex::Regex;
 use sn_testnet_deploy::{
-    inventory::DeploymentInventoryService, CloudProvider, TestnetDeployBuilder,
+    inventory::DeploymentInventoryService,
+    logs::{
+        analyze::{analyze_logs, AnalysisFormat},
+        archive::{upload_log_archives, LogArchiveFilter},
+        follow::LogFollowOptions,
+        index::{build_log_index, search_logs},
+        summarize::{print_summary, summarize_logs},
+        timeline::write_timeline,
+    },
+    CloudProvider, NodeType, TestnetDeployBuilder,
+};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    time::Duration,
 };
 
 #[derive(Subcommand, Debug)]
 pub enum LogCommands {
+    /// Analyse the JSON records in the logs that have been retrieved with the 'rsync' command.
+    ///
+    /// The records are counted per level, target module, node type and VM, within time buckets.
+    /// Only nodes deployed with '--log-format json' produce records that can be analysed.
+    Analyze {
+        /// The size of each time bucket, in seconds.
+        #[arg(long, default_value_t = 300)]
+        bucket_size: u64,
+        /// The output format.
+        ///
+        /// Valid values are "table", "csv" or "json".
+        #[arg(long, default_value = "table", value_parser = parse_analysis_format)]
+        format: AnalysisFormat,
+        /// Only include records logged at or after this time.
+        ///
+        /// The time should be in RFC 3339 format, or 'YYYY-MM-DD HH:MM:SS' in UTC.
+        #[arg(long, value_parser = parse_log_time)]
+        from: Option<DateTime<Utc>>,
+        /// The name of the environment for which logs have already been retrieved
+        #[arg(short = 'n', long)]
+        name: String,
+        /// Write the output to this file rather than stdout.
+        #[arg(short = 'o', long)]
+        output: Option<PathBuf>,
+        /// Only include records logged at or before this time.
+        ///
+        /// The time should be in RFC 3339 format, or 'YYYY-MM-DD HH:MM:SS' in UTC.
+        #[arg(long, value_parser = parse_log_time)]
+        to: Option<DateTime<Utc>>,
+    },
     /// Removes all the rotated log files from the the node VMs.
     Cleanup {
         /// The name of the environment
@@ -40,13 +87,81 @@ pub enum LogCommands {
         #[arg(short = 'r', long)]
         resources_only: bool,
     },
+    /// Keep a local mirror of the logs up to date by rsyncing them from all the VMs at an interval.
+    ///
+    /// Each pass only transfers the files that changed since the previous one. Hosts that fail are
+    /// skipped for an increasing number of passes. A summary is printed after each pass.
+    ///
+    /// This will write the logs to 'logs/<name>', relative to the current directory.
+    Follow {
+        /// Limit the bandwidth used to rsync from each VM, in KiB per second.
+        #[arg(long)]
+        bandwidth_limit: Option<u64>,
+        /// Do not sync the client logs.
+        #[arg(long, default_value = "false")]
+        disable_client_logs: bool,
+        /// The interval between each pass, e.g., '300', '30s', '5m' or '1h'.
+        ///
+        /// A value without a unit is in seconds.
+        #[arg(long, default_value = "5m", value_parser = parse_duration)]
+        every: Duration,
+        /// The maximum time a failing host will be skipped for, e.g., '300', '30s', '5m' or '1h'.
+        #[arg(long, default_value = "1h", value_parser = parse_duration)]
+        max_backoff: Duration,
+        /// Stop after this many passes. By default, the logs are followed until the process is
+        /// stopped.
+        #[arg(long)]
+        max_passes: Option<usize>,
+        /// The name of the environment
+        #[arg(short = 'n', long)]
+        name: String,
+        /// The cloud provider that was used.
+        #[clap(long, default_value_t = CloudProvider::DigitalOcean, value_parser = parse_provider, verbatim_doc_comment)]
+        provider: CloudProvider,
+        /// Optionally only sync the logs for the VMs that contain the following string.
+        #[arg(long)]
+        vm_filter: Option<String>,
+    },
     /// Retrieve the logs for a given environment from S3.
     ///
+    /// If the logs were uploaded as archives with the 'upload' command, the archives can be
+    /// filtered, so that only the relevant ones are retrieved. Otherwise, all the logs are
+    /// retrieved.
+    ///
     /// This will write the logs to 'logs/<name>', relative to the current directory.
     Get {
+        /// Only retrieve archives that contain entries logged at or after this time.
+        ///
+        /// The time should be in RFC 3339 format, or 'YYYY-MM-DD HH:MM:SS' in UTC.
+        #[arg(long, value_parser = parse_log_time)]
+        from: Option<DateTime<Utc>>,
         /// The name of the environment
         #[arg(short = 'n', long)]
         name: String,
+        /// Only retrieve archives for this type of node.
+        ///
+        /// Valid values are "peer-cache", "genesis", "generic", "full-cone-private",
+        /// "port-restricted-cone-private", "symmetric-private" and "upnp".
+        #[arg(long)]
+        node_type: Option<NodeType>,
+        /// Only retrieve archives that contain entries logged at or before this time.
+        ///
+        /// The time should be in RFC 3339 format, or 'YYYY-MM-DD HH:MM:SS' in UTC.
+        #[arg(long, value_parser = parse_log_time)]
+        to: Option<DateTime<Utc>>,
+        /// Only retrieve archives for VMs whose name contains this string.
+        #[arg(long)]
+        vm: Option<String>,
+    },
+    /// Build an index of the logs that have been retrieved with the 'rsync' command.
+    ///
+    /// The index records the time range covered by each log file, per VM and service, and is
+    /// written to 'logs/<name>/index.json'. Files that have not changed since the last time the
+    /// index was built are not read again.
+    Index {
+        /// The name of the environment for which logs have already been retrieved
+        #[arg(short = 'n', long)]
+        name: String,
     },
     /// Reassemble retrieved logs from their parts.
     ///
@@ -63,7 +178,10 @@ pub enum LogCommands {
     },
     /// Run a ripgrep query through all the logs from all the VMs and copy the results.
     ///
-    /// The results will be written to `logs/<name>/<vm>/rg-timestamp.log`
+    /// Private nodes are queried through their NAT gateway.
+    ///
+    /// The results will be written to `logs/<name>/<vm>/rg-timestamp.log`, and a report with the
+    /// number of matches per VM and service will be written to `logs/<name>/rg-timestamp.json`.
     Rg {
         /// The ripgrep arguments that are directly passed to ripgrep. The text to search for should be put inside
         /// single quotes. The dir to search for is set automatically, so do not provide one.
@@ -71,6 +189,12 @@ pub enum LogCommands {
         /// Example command: `cargo run --release -- logs rg --name <name> --args "'ValidSpendRecordPutFromNetwork' -z -a"`
         #[arg(short = 'a', long, allow_hyphen_values(true))]
         args: String,
+        /// Also run the query on the client VMs.
+        #[arg(long)]
+        include_clients: bool,
+        /// Print the report as JSON.
+        #[arg(long)]
+        json: bool,
         /// The name of the environment
         #[arg(short = 'n', long)]
         name: String,
@@ -102,10 +226,132 @@ pub enum LogCommands {
         #[arg(long)]
         vm_filter: Option<String>,
     },
+    /// Search the logs that have been retrieved with the 'rsync' command.
+    ///
+    /// The index is refreshed before searching, and only the files that overlap the time window
+    /// are scanned. Each match is printed with the VM and service it came from.
+    Search {
+        /// Only include entries logged at or after this time.
+        ///
+        /// The time should be in RFC 3339 format, or 'YYYY-MM-DD HH:MM:SS' in UTC.
+        #[arg(long, value_parser = parse_log_time)]
+        from: Option<DateTime<Utc>>,
+        /// Output the matches as JSON.
+        #[arg(long)]
+        json: bool,
+        /// The name of the environment for which logs have already been retrieved
+        #[arg(short = 'n', long)]
+        name: String,
+        /// Only search the logs from this type of node.
+        ///
+        /// Valid values are "peer-cache", "genesis", "generic", "full-cone-private",
+        /// "port-restricted-cone-private", "symmetric-private" and "upnp".
+        #[arg(long)]
+        node_type: Option<NodeType>,
+        /// The regular expression to search for.
+        #[arg(short = 'p', long)]
+        pattern: String,
+        /// Only include entries logged at or before this time.
+        ///
+        /// The time should be in RFC 3339 format, or 'YYYY-MM-DD HH:MM:SS' in UTC.
+        #[arg(long, value_parser = parse_log_time)]
+        to: Option<DateTime<Utc>>,
+    },
+    /// Summarise the errors and warnings in the logs that have been retrieved with the 'rsync'
+    /// command.
+    ///
+    /// Variable parts of each message, such as peer IDs, multiaddrs, record keys and numbers, are
+    /// replaced with placeholders, so that the messages can be clustered into templates. The most
+    /// frequent templates are reported with their counts, when they were first and last seen, and
+    /// the VMs they occurred on.
+    Summarize {
+        /// Only include entries logged at or after this time.
+        ///
+        /// The time should be in RFC 3339 format, or 'YYYY-MM-DD HH:MM:SS' in UTC.
+        #[arg(long, value_parser = parse_log_time)]
+        from: Option<DateTime<Utc>>,
+        /// Output all the templates as JSON.
+        #[arg(long)]
+        json: bool,
+        /// The name of the environment for which logs have already been retrieved
+        #[arg(short = 'n', long)]
+        name: String,
+        /// Only include entries logged at or before this time.
+        ///
+        /// The time should be in RFC 3339 format, or 'YYYY-MM-DD HH:MM:SS' in UTC.
+        #[arg(long, value_parser = parse_log_time)]
+        to: Option<DateTime<Utc>>,
+        /// The number of templates to report.
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+    },
+    /// Merge the logs that have been retrieved with the 'rsync' command into a single,
+    /// chronologically ordered timeline.
+    ///
+    /// Logs in both the default and JSON formats are supported. Each entry is prefixed with the VM
+    /// and service it came from.
+    Timeline {
+        /// Only include entries logged at or after this time.
+        ///
+        /// The time should be in RFC 3339 format, or 'YYYY-MM-DD HH:MM:SS' in UTC.
+        #[arg(long, value_parser = parse_log_time)]
+        from: Option<DateTime<Utc>>,
+        /// The name of the environment for which logs have already been retrieved
+        #[arg(short = 'n', long)]
+        name: String,
+        /// Write the timeline to this file rather than stdout.
+        #[arg(short = 'o', long)]
+        output: Option<PathBuf>,
+        /// Only include entries that mention this peer ID.
+        #[arg(long)]
+        peer_id: Option<String>,
+        /// Only include entries logged at or before this time.
+        ///
+        /// The time should be in RFC 3339 format, or 'YYYY-MM-DD HH:MM:SS' in UTC.
+        #[arg(long, value_parser = parse_log_time)]
+        to: Option<DateTime<Utc>>,
+    },
+    /// Compress the logs that have been retrieved with the 'rsync' command into an archive per VM,
+    /// then upload them to S3.
+    ///
+    /// A manifest describing the node type, services, time range and size of each archive is
+    /// uploaded with them, which allows the 'get' command to retrieve only the relevant archives.
+    Upload {
+        /// The name of the environment for which logs have already been retrieved
+        #[arg(short = 'n', long)]
+        name: String,
+    },
 }
 
 pub async fn handle_logs_command(log_cmd: LogCommands) -> Result<()> {
     match log_cmd {
+        LogCommands::Analyze {
+            bucket_size,
+            format,
+            from,
+            name,
+            output,
+            to,
+        } => {
+            let analysis = analyze_logs(&name, from, to, bucket_size)?;
+            if analysis.record_count == 0 {
+                eprintln!("No JSON log records were found. Was the environment deployed with '--log-format json'?");
+            }
+            match output {
+                Some(path) => {
+                    let mut writer = BufWriter::new(File::create(&path)?);
+                    analysis.write(format, &mut writer)?;
+                    writer.flush()?;
+                    eprintln!("Analysis written to {}", path.to_string_lossy());
+                }
+                None => {
+                    let mut writer = BufWriter::new(std::io::stdout().lock());
+                    analysis.write(format, &mut writer)?;
+                    writer.flush()?;
+                }
+            }
+            Ok(())
+        }
         LogCommands::Cleanup {
             name,
             provider,
@@ -138,8 +384,59 @@ pub async fn handle_logs_command(log_cmd: LogCommands) -> Result<()> {
             testnet_deployer.copy_logs(&name, resources_only)?;
             Ok(())
         }
-        LogCommands::Get { name } => {
-            sn_testnet_deploy::logs::get_logs(&name).await?;
+        LogCommands::Follow {
+            bandwidth_limit,
+            disable_client_logs,
+            every,
+            max_backoff,
+            max_passes,
+            name,
+            provider,
+            vm_filter,
+        } => {
+            let testnet_deployer = TestnetDeployBuilder::default()
+                .environment_name(&name)
+                .provider(provider)
+                .build()?;
+            testnet_deployer.init().await?;
+            let inventory_service = DeploymentInventoryService::from(&testnet_deployer);
+            inventory_service.setup_environment_inventory(&name)?;
+
+            testnet_deployer.follow_logs(
+                &name,
+                LogFollowOptions {
+                    bandwidth_limit,
+                    disable_client_logs,
+                    interval: every,
+                    max_backoff,
+                    max_passes,
+                    vm_filter,
+                },
+            )?;
+            Ok(())
+        }
+        LogCommands::Get {
+            from,
+            name,
+            node_type,
+            to,
+            vm,
+        } => {
+            sn_testnet_deploy::logs::get_logs(
+                &name,
+                LogArchiveFilter {
+                    vm,
+                    node_type,
+                    from,
+                    to,
+                },
+            )
+            .await?;
+            Ok(())
+        }
+        LogCommands::Index { name } => {
+            let index = build_log_index(&name)?;
+            index.print_report();
             Ok(())
         }
         LogCommands::Reassemble { name } => {
@@ -148,6 +445,8 @@ pub async fn handle_logs_command(log_cmd: LogCommands) -> Result<()> {
         }
         LogCommands::Rg {
             args,
+            include_clients,
+            json,
             name,
             provider,
         } => {
@@ -159,7 +458,12 @@ pub async fn handle_logs_command(log_cmd: LogCommands) -> Result<()> {
             let inventory_service = DeploymentInventoryService::from(&testnet_deployer);
             inventory_service.setup_environment_inventory(&name)?;
 
-            testnet_deployer.ripgrep_logs(&name, &args)?;
+            let report = testnet_deployer.ripgrep_logs(&name, &args, include_clients)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                report.print();
+            }
             Ok(())
         }
         LogCommands::Rm { name } => {
@@ -183,5 +487,115 @@ pub async fn handle_logs_command(log_cmd: LogCommands) -> Result<()> {
             testnet_deployer.rsync_logs(&name, vm_filter, disable_client_logs)?;
             Ok(())
         }
+        LogCommands::Search {
+            from,
+            json,
+            name,
+            node_type,
+            pattern,
+            to,
+        } => {
+            let pattern = Regex::new(&pattern)?;
+            let matches = search_logs(&name, &pattern, from, to, node_type)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&matches)?);
+            } else {
+                for m in matches.iter() {
+                    println!("{}/{}: {}", m.vm_name, m.service, m.line);
+                }
+                println!("Found {} matches", matches.len());
+            }
+            Ok(())
+        }
+        LogCommands::Summarize {
+            from,
+            json,
+            name,
+            to,
+            top,
+        } => {
+            let templates = summarize_logs(&name, from, to)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&templates)?);
+            } else {
+                print_summary(&templates, top);
+            }
+            Ok(())
+        }
+        LogCommands::Timeline {
+            from,
+            name,
+            output,
+            peer_id,
+            to,
+        } => {
+            let written = match output {
+                Some(path) => {
+                    let mut writer = BufWriter::new(File::create(&path)?);
+                    let written = write_timeline(&name, from, to, peer_id.as_deref(), &mut writer)?;
+                    writer.flush()?;
+                    eprintln!("Timeline written to {}", path.to_string_lossy());
+                    written
+                }
+                None => {
+                    let mut writer = BufWriter::new(std::io::stdout().lock());
+                    let written = write_timeline(&name, from, to, peer_id.as_deref(), &mut writer)?;
+                    writer.flush()?;
+                    written
+                }
+            };
+            eprintln!("{written} entries in the timeline");
+            Ok(())
+        }
+        LogCommands::Upload { name } => {
+            let manifest = upload_log_archives(&name).await?;
+            let total_size: u64 = manifest.archives.iter().map(|a| a.archive_size).sum();
+            println!(
+                "Uploaded {} archives ({total_size} bytes) for {name}",
+                manifest.archives.len()
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Parse a time from either RFC 3339 format or 'YYYY-MM-DD HH:MM:SS', which is assumed to be UTC.
+pub fn parse_log_time(val: &str) -> Result<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(val) {
+        return Ok(time.with_timezone(&Utc));
     }
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(val, format) {
+            return Ok(time.and_utc());
+        }
+    }
+    Err(eyre!(
+        "Could not parse '{val}' as a time. Use RFC 3339 or 'YYYY-MM-DD HH:MM:SS'."
+    ))
+}
+
+pub fn parse_analysis_format(val: &str) -> Result<AnalysisFormat> {
+    match val.to_lowercase().as_str() {
+        "csv" => Ok(AnalysisFormat::Csv),
+        "json" => Ok(AnalysisFormat::Json),
+        "table" => Ok(AnalysisFormat::Table),
+        _ => Err(eyre!(
+            "The only supported output formats are 'table', 'csv' or 'json'"
+        )),
+    }
+}
+
+/// Parse a duration with an optional 's', 'm' or 'h' unit. A value without a unit is in seconds.
+pub fn parse_duration(val: &str) -> Result<Duration> {
+    let val = val.trim();
+    let (number, multiplier) = match val.chars().last() {
+        Some('s') => (&val[..val.len() - 1], 1),
+        Some('m') => (&val[..val.len() - 1], 60),
+        Some('h') => (&val[..val.len() - 1], 60 * 60),
+        _ => (val, 1),
+    };
+    let number = number
+        .parse::<u64>()
+        .map_err(|_| eyre!("Could not parse '{val}' as a duration, e.g., '30s', '5m' or '1h'"))?;
+    Ok(Duration::from_secs(number * multiplier))
 }
@@ -10,14 +10,17 @@ use color_eyre::{
 };
 use sn_testnet_deploy::{
     ansible::{extra_vars::ExtraVarsDocBuilder, inventory::AnsibleInventoryType, AnsiblePlaybook},
-    get_environment_details,
+    dry_run, get_environment_details,
     infra::InfraRunOptions,
     inventory::{get_data_directory, DeploymentInventory, DeploymentInventoryService},
-    notify_slack, TestnetDeployBuilder,
+    notify_slack,
+    sweep::OrphanedResourceSweeper,
+    TestnetDeployBuilder,
 };
 
 #[allow(clippy::too_many_arguments)]
 pub async fn handle_extend_volume_size(
+    allow_destructive_changes: bool,
     ansible_verbose: bool,
     genesis_node_volume_size: Option<u16>,
     full_cone_private_node_volume_size: Option<u16>,
@@ -45,7 +48,7 @@ pub async fn handle_extend_volume_size(
     testnet_deployer.init().await?;
 
     let environment_details =
-        get_environment_details(&name, &testnet_deployer.s3_repository).await?;
+        get_environment_details(&name, &testnet_deployer.object_store).await?;
 
     let mut infra_run_options = InfraRunOptions::generate_existing(
         &name,
@@ -54,6 +57,7 @@ pub async fn handle_extend_volume_size(
         Some(&environment_details),
     )
     .await?;
+    infra_run_options.allow_destructive_changes = allow_destructive_changes;
     println!("Obtained infra run options from previous deployment {infra_run_options:?}");
     let mut node_types = Vec::new();
 
@@ -97,6 +101,31 @@ pub async fn handle_extend_volume_size(
     Ok(())
 }
 
+pub async fn handle_drift(
+    json: bool,
+    name: String,
+    provider: sn_testnet_deploy::CloudProvider,
+) -> Result<()> {
+    let testnet_deployer = TestnetDeployBuilder::default()
+        .environment_name(&name)
+        .provider(provider)
+        .build()?;
+    testnet_deployer.init().await?;
+    let inventory_service = DeploymentInventoryService::from(&testnet_deployer);
+    inventory_service.setup_environment_inventory(&name)?;
+
+    let report = testnet_deployer.detect_drift(&name).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        report.print_report();
+    }
+    if report.has_drift() {
+        return Err(eyre!("Drift was detected for the {name} environment"));
+    }
+    Ok(())
+}
+
 pub async fn handle_inventory(
     force_regeneration: bool,
     full: bool,
@@ -184,3 +213,27 @@ fn build_swapfile_extra_vars_doc(size: u16) -> Result<String> {
     extra_vars.add_variable("swapfile_size", &format!("{size}G"));
     Ok(extra_vars.build())
 }
+
+pub async fn handle_sweep(json: bool, provider: sn_testnet_deploy::CloudProvider) -> Result<()> {
+    let sweeper = OrphanedResourceSweeper::new(provider)?;
+    let report = sweeper.find_orphaned_resources().await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        report.print_report();
+    }
+    if dry_run::is_enabled() || report.is_empty() {
+        return Ok(());
+    }
+
+    let confirmed = inquire::Confirm::new("Do you want to delete these resources?")
+        .with_default(false)
+        .prompt()?;
+    if !confirmed {
+        println!("No resources were deleted");
+        return Ok(());
+    }
+    sweeper.sweep(&report).await?;
+    println!("Orphaned resources deleted");
+    Ok(())
+}
@@ -6,7 +6,9 @@
 
 pub mod clients;
 pub mod deployments;
+pub mod environments;
 pub mod funds;
+pub mod lock;
 pub mod logs;
 pub mod misc;
 pub mod network;
@@ -17,8 +19,9 @@ pub mod telegraf;
 pub mod upgrade;
 
 use crate::cmd::{
-    clients::ClientsCommands, funds::FundsCommand, logs::LogCommands, network::NetworkCommands,
-    nginx::NginxCommands, provision::ProvisionCommands, telegraf::TelegrafCommands,
+    clients::ClientsCommands, environments::EnvironmentsCommands, funds::FundsCommand,
+    lock::LockCommands, logs::LogCommands, network::NetworkCommands, nginx::NginxCommands,
+    provision::ProvisionCommands, telegraf::TelegrafCommands,
 };
 use alloy::primitives::U256;
 use ant_releases::{AntReleaseRepoActions, ReleaseType};
@@ -31,7 +34,8 @@ use log::debug;
 use semver::Version;
 use sn_testnet_deploy::{
     inventory::{DeploymentInventory, VirtualMachine},
-    s3::S3Repository,
+    object_store::{get_object_store, Bucket},
+    secrets::SecretReference,
     BinaryOption, CloudProvider, EnvironmentType, EvmNetwork, LogFormat, NodeType,
 };
 use std::time::Duration;
@@ -41,6 +45,12 @@ use std::time::Duration;
 pub enum Commands {
     /// Bootstrap a new network from an existing deployment.
     Bootstrap {
+        /// Apply infrastructure changes that would destroy or replace existing resources, without
+        /// asking for confirmation.
+        ///
+        /// Without this, those changes are refused when there is no terminal to confirm them on.
+        #[arg(long)]
+        allow_destructive_changes: bool,
         /// Set to run Ansible with more verbose output.
         #[arg(long)]
         ansible_verbose: bool,
@@ -143,6 +153,12 @@ pub enum Commands {
         /// The interval between starting each node in milliseconds.
         #[clap(long, value_parser = |t: &str| -> Result<Duration> { Ok(t.parse().map(Duration::from_millis)?)}, default_value = "2000")]
         interval: Duration,
+        /// How long the environment should live for, as a number of hours or days, e.g., '12h' or
+        /// '7d'.
+        ///
+        /// Once it has expired, the environment can be removed by the 'environments reap' command.
+        #[clap(long, value_parser = parse_lifetime)]
+        lifetime: Option<Duration>,
         /// Specify the logging format for the nodes.
         ///
         /// Valid values are "default" or "json".
@@ -202,6 +218,11 @@ pub enum Commands {
         /// argument.
         #[clap(long)]
         node_volume_size: Option<u16>,
+        /// The owner of the environment, who will be notified when it expires.
+        ///
+        /// If not supplied, the current user is the owner.
+        #[clap(long)]
+        owner: Option<String>,
         /// The peer from an existing network that we can bootstrap from.
         ///
         /// Either this or the `network-contacts-url` argument must be provided.
@@ -281,7 +302,16 @@ pub enum Commands {
         upnp_private_node_volume_size: Option<u16>,
     },
     /// Clean a deployed testnet environment.
+    ///
+    /// For a Production environment, the operator must type the name of the environment after a
+    /// preview of the resources that will be destroyed.
     Clean {
+        /// Drain the funds from the environment before it is destroyed.
+        ///
+        /// Funds are always drained from Development and Staging environments, but for a
+        /// Production environment, this must be requested.
+        #[clap(long)]
+        drain_funds: bool,
         /// The name of the environment.
         #[arg(short = 'n', long)]
         name: String,
@@ -309,6 +339,12 @@ pub enum Commands {
     },
     /// Deploy a new testnet environment using the latest version of the antnode binary.
     Deploy {
+        /// Apply infrastructure changes that would destroy or replace existing resources, without
+        /// asking for confirmation.
+        ///
+        /// Without this, those changes are refused when there is no terminal to confirm them on.
+        #[arg(long)]
+        allow_destructive_changes: bool,
         /// Set to run Ansible with more verbose output.
         #[arg(long)]
         ansible_verbose: bool,
@@ -451,8 +487,11 @@ pub enum Commands {
         /// The secret key for the wallet that will fund all the ANT instances.
         ///
         /// This argument only applies when Arbitrum or Sepolia networks are used.
-        #[clap(long)]
-        funding_wallet_secret_key: Option<String>,
+        ///
+        /// Rather than the key itself, supply a reference to it, with 'env:NAME', 'file:PATH',
+        /// 'vault:PATH' for a file encrypted with ansible-vault, or 'keyring:[SERVICE/]ACCOUNT'.
+        #[clap(long, value_parser = parse_secret_reference)]
+        funding_wallet_secret_key: Option<SecretReference>,
         /// The size of the volumes to attach to each genesis node VM.
         ///
         /// This argument will set the size of all the 7 attached volumes.
@@ -473,6 +512,12 @@ pub enum Commands {
         /// The interval between starting each node in milliseconds.
         #[clap(long, value_parser = |t: &str| -> Result<Duration> { Ok(t.parse().map(Duration::from_millis)?)}, default_value = "2000")]
         interval: Duration,
+        /// How long the environment should live for, as a number of hours or days, e.g., '12h' or
+        /// '7d'.
+        ///
+        /// Once it has expired, the environment can be removed by the 'environments reap' command.
+        #[clap(long, value_parser = parse_lifetime)]
+        lifetime: Option<Duration>,
         /// Specify the logging format for the nodes.
         ///
         /// Valid values are "default" or "json".
@@ -540,6 +585,11 @@ pub enum Commands {
         /// If it is not used, the value will be determined by the 'environment-type' argument.
         #[clap(long)]
         node_volume_size: Option<u16>,
+        /// The owner of the environment, who will be notified when it expires.
+        ///
+        /// If not supplied, the current user is the owner.
+        #[clap(long)]
+        owner: Option<String>,
         /// The number of antnode services to run on the peer-cache hosts.
         ///
         /// The number applies to each VM, so the total number of nodes will be the number of VMs
@@ -718,7 +768,32 @@ pub enum Commands {
         #[clap(long)]
         upnp_private_node_volume_size: Option<u16>,
     },
+    /// Compare the Terraform state, the VMs in the cloud provider and the Ansible inventory for an
+    /// environment.
+    ///
+    /// Reports VMs that only exist in some of the views, mismatched IP addresses and attached
+    /// volumes that are missing in the cloud.
+    Drift {
+        /// Print the report as JSON.
+        #[clap(long)]
+        json: bool,
+        /// The name of the environment.
+        #[arg(short = 'n', long)]
+        name: String,
+        /// The cloud provider for the environment.
+        #[clap(long, value_parser = parse_provider, verbatim_doc_comment, default_value_t = CloudProvider::DigitalOcean)]
+        provider: CloudProvider,
+    },
+    /// Manage the environments in the cloud provider.
+    #[clap(name = "environments", subcommand)]
+    Environments(EnvironmentsCommands),
     ExtendVolumeSize {
+        /// Apply infrastructure changes that would destroy or replace existing resources, without
+        /// asking for confirmation.
+        ///
+        /// Without this, those changes are refused when there is no terminal to confirm them on.
+        #[arg(long)]
+        allow_destructive_changes: bool,
         /// Set to run Ansible with more verbose output.
         #[arg(long)]
         ansible_verbose: bool,
@@ -791,6 +866,9 @@ pub enum Commands {
         #[clap(long, default_value_t = CloudProvider::DigitalOcean, value_parser = parse_provider, verbatim_doc_comment)]
         provider: CloudProvider,
     },
+    /// Manage the locks that prevent commands from changing an environment at the same time.
+    #[clap(name = "lock", subcommand)]
+    Lock(LockCommands),
     #[clap(name = "logs", subcommand)]
     Logs(LogCommands),
     #[clap(name = "network", subcommand)]
@@ -982,6 +1060,19 @@ pub enum Commands {
         #[clap(long)]
         service_name: Option<Vec<String>>,
     },
+    /// Find and delete resources that don't belong to a live environment.
+    ///
+    /// Aborted deployments and incomplete cleanups can leave behind droplets, volumes, Terraform
+    /// workspaces and environment details. The resources are listed and must be confirmed before
+    /// they are deleted. With --dry-run, they are only listed.
+    Sweep {
+        /// Print the report as JSON.
+        #[clap(long)]
+        json: bool,
+        /// The cloud provider to sweep.
+        #[clap(long, value_parser = parse_provider, verbatim_doc_comment, default_value_t = CloudProvider::DigitalOcean)]
+        provider: CloudProvider,
+    },
     /// Manage Telegraf services and configuration
     #[clap(name = "telegraf", subcommand)]
     Telegraf(TelegrafCommands),
@@ -1129,6 +1220,12 @@ pub enum Commands {
     },
     /// Upscale VMs and node services for an existing network.
     Upscale {
+        /// Apply infrastructure changes that would destroy or replace existing resources, without
+        /// asking for confirmation.
+        ///
+        /// Without this, those changes are refused when there is no terminal to confirm them on.
+        #[arg(long)]
+        allow_destructive_changes: bool,
         /// Supply a version number for the antnode binary.
         ///
         /// There should be no 'v' prefix.
@@ -1246,8 +1343,11 @@ pub enum Commands {
         /// The secret key for the wallet that will fund all the ANT instances.
         ///
         /// This argument only applies when Arbitrum or Sepolia networks are used.
-        #[clap(long)]
-        funding_wallet_secret_key: Option<String>,
+        ///
+        /// Rather than the key itself, supply a reference to it, with 'env:NAME', 'file:PATH',
+        /// 'vault:PATH' for a file encrypted with ansible-vault, or 'keyring:[SERVICE/]ACCOUNT'.
+        #[clap(long, value_parser = parse_secret_reference)]
+        funding_wallet_secret_key: Option<SecretReference>,
         /// Set to only use Terraform to upscale the VMs and not run Ansible.
         #[clap(long, default_value_t = false)]
         infra_only: bool,
@@ -1330,13 +1430,13 @@ pub async fn get_options_from_s3<T: serde::de::DeserializeOwned>(
     name: &str,
     options_type: OptionsType,
 ) -> Result<T> {
-    let s3_repo = S3Repository {};
+    let object_store = get_object_store()?;
     let temp_dir = tempfile::tempdir()?.into_path();
     let options_path = temp_dir.join(options_type.file_name(name));
 
-    s3_repo
+    object_store
         .download_object(
-            "ant-testnet-deploy-options",
+            Bucket::DeploymentOptions,
             &options_type.file_name(name),
             &options_path,
         )
@@ -1362,7 +1462,7 @@ pub async fn upload_options_to_s3<T: serde::Serialize>(
     options: &T,
     options_type: OptionsType,
 ) -> Result<()> {
-    let s3_repo = S3Repository {};
+    let object_store = get_object_store()?;
     let json = serde_json::to_string(options)
         .map_err(|e| eyre!("Failed to serialize {:?} options: {}", options_type, e))?;
 
@@ -1373,8 +1473,8 @@ pub async fn upload_options_to_s3<T: serde::Serialize>(
         .await
         .map_err(|e| eyre!("Failed to write {:?} options to file: {}", options_type, e))?;
 
-    s3_repo
-        .upload_file("ant-testnet-deploy-options", &file_path, false)
+    object_store
+        .upload_file(Bucket::DeploymentOptions, &file_path, false)
         .await
         .map_err(|e| eyre!("Failed to upload {:?} options to S3: {}", options_type, e))?;
     Ok(())
@@ -1550,6 +1650,21 @@ pub fn parse_evm_network(s: &str) -> Result<EvmNetwork, String> {
     }
 }
 
+/// Parse a number of hours or days, e.g., '12h' or '7d'.
+pub fn parse_lifetime(val: &str) -> Result<Duration> {
+    let (value, seconds_per_unit) = if let Some(hours) = val.strip_suffix('h') {
+        (hours, 60 * 60)
+    } else if let Some(days) = val.strip_suffix('d') {
+        (days, 24 * 60 * 60)
+    } else {
+        return Err(eyre!(
+            "The lifetime must be a number of hours or days, e.g., '12h' or '7d'"
+        ));
+    };
+    let value: u64 = value.parse()?;
+    Ok(Duration::from_secs(value * seconds_per_unit))
+}
+
 pub fn parse_provider(val: &str) -> Result<CloudProvider> {
     match val {
         "aws" => Ok(CloudProvider::Aws),
@@ -1560,7 +1675,80 @@ pub fn parse_provider(val: &str) -> Result<CloudProvider> {
     }
 }
 
+/// Parse a reference to a secret, such as 'env:FUNDING_WALLET_SECRET_KEY', or the secret itself.
+pub fn parse_secret_reference(val: &str) -> Result<SecretReference> {
+    Ok(val.parse()?)
+}
+
+/// Parse a decimal number of tokens, e.g., '0.5', into the amount in wei.
+pub fn parse_token_amount(val: &str) -> Result<U256> {
+    let (whole, fraction) = val.split_once('.').unwrap_or((val, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || fraction.len() > 18
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return Err(eyre!(
+            "The amount must be a decimal number with at most 18 decimal places, e.g., '0.5'"
+        ));
+    }
+    let digits = format!(
+        "{}{fraction:0<18}",
+        if whole.is_empty() { "0" } else { whole }
+    );
+    Ok(U256::from_str_radix(&digits, 10)?)
+}
+
 fn print_with_banner(s: &str) {
     let banner = "=".repeat(s.len());
     println!("{banner}\n{s}\n{banner}");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_token_amount_converts_decimal_tokens_to_wei() {
+        assert_eq!(
+            parse_token_amount("1").unwrap(),
+            U256::from(1_000_000_000_000_000_000u64)
+        );
+        assert_eq!(
+            parse_token_amount("0.001").unwrap(),
+            U256::from(1_000_000_000_000_000u64)
+        );
+        assert_eq!(
+            parse_token_amount(".5").unwrap(),
+            U256::from(500_000_000_000_000_000u64)
+        );
+        assert_eq!(
+            parse_token_amount("1.").unwrap(),
+            U256::from(1_000_000_000_000_000_000u64)
+        );
+        assert_eq!(
+            parse_token_amount("0.000000000000000001").unwrap(),
+            U256::from(1)
+        );
+    }
+
+    #[test]
+    fn test_parse_token_amount_rejects_invalid_amounts() {
+        for val in [
+            "",
+            ".",
+            "0.0000000000000000001",
+            "-1",
+            "1e18",
+            "1.2.3",
+            "one",
+        ] {
+            assert!(
+                parse_token_amount(val).is_err(),
+                "'{val}' should be rejected"
+            );
+        }
+    }
+}
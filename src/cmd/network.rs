@@ -18,14 +18,26 @@ use color_eyre::{
 use futures::StreamExt;
 use libp2p::PeerId;
 use rand::Rng;
+use serde::Serialize;
 use sn_testnet_deploy::{
     inventory::{get_data_directory, DeploymentInventory},
     CloudProvider,
 };
-use std::{collections::BTreeSet, net::SocketAddr, time::Duration};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::File,
+    io::Write,
+    net::SocketAddr,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
 use tonic::{transport::Channel, Request};
 
 const MAX_CONCURRENT_RPC_REQUESTS: usize = 10;
+const RESTART_VERIFICATION_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// If the restarted service is not seen to stop, a running status is only trusted after this long,
+/// so the status from before the restart isn't mistaken for the node coming back.
+const RESTART_SETTLE_TIME: Duration = Duration::from_secs(10);
 
 #[derive(Subcommand, Debug)]
 pub enum ChurnCommands {
@@ -49,6 +61,21 @@ pub enum ChurnCommands {
         /// Whether to retain the same PeerId on restart.
         #[clap(long, default_value_t = false)]
         retain_peer_id: bool,
+        /// The format of the churn timeline file.
+        ///
+        /// Valid values are "json" or "csv".
+        #[clap(long, default_value_t = TimelineFormat::Json, value_parser = parse_timeline_format)]
+        timeline_format: TimelineFormat,
+        /// The path of the file to which each restart will be recorded.
+        ///
+        /// If not provided, the timeline will be written to the data directory, with a name based
+        /// on the environment name and the time the churn started.
+        #[clap(long)]
+        timeline_path: Option<PathBuf>,
+        /// The maximum time, in seconds, to wait for a restarted node to report that it is running
+        /// again.
+        #[clap(long, value_parser = |t: &str| -> Result<Duration> { Ok(t.parse().map(Duration::from_secs)?)}, default_value = "60")]
+        verify_timeout: Duration,
     },
     /// Churn nodes at random intervals.
     RandomInterval {
@@ -71,15 +98,174 @@ pub enum ChurnCommands {
         /// Nodes are restarted at a rate of churn_count/time_frame with random delays between each restart.
         #[clap(long, value_parser = |t: &str| -> Result<Duration> { Ok(t.parse().map(Duration::from_secs)?)}, default_value = "600")]
         time_frame: Duration,
+        /// The format of the churn timeline file.
+        ///
+        /// Valid values are "json" or "csv".
+        #[clap(long, default_value_t = TimelineFormat::Json, value_parser = parse_timeline_format)]
+        timeline_format: TimelineFormat,
+        /// The path of the file to which each restart will be recorded.
+        ///
+        /// If not provided, the timeline will be written to the data directory, with a name based
+        /// on the environment name and the time the churn started.
+        #[clap(long)]
+        timeline_path: Option<PathBuf>,
+        /// The maximum time, in seconds, to wait for a restarted node to report that it is running
+        /// again.
+        #[clap(long, value_parser = |t: &str| -> Result<Duration> { Ok(t.parse().map(Duration::from_secs)?)}, default_value = "60")]
+        verify_timeout: Duration,
     },
 }
 
+#[derive(Clone, Copy, Debug)]
+pub enum TimelineFormat {
+    Csv,
+    Json,
+}
+
+impl std::fmt::Display for TimelineFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimelineFormat::Csv => write!(f, "csv"),
+            TimelineFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// A single node restart performed during a churn run.
+#[derive(Clone, Debug, Serialize)]
+pub struct ChurnEvent {
+    /// The wall-clock time at which the restart request was sent, in RFC 3339 format.
+    pub timestamp: String,
+    pub vm_name: String,
+    pub daemon_endpoint: SocketAddr,
+    pub service_number: u32,
+    pub old_peer_id: String,
+    /// The peer ID reported by the restarted service, if it came back.
+    pub new_peer_id: Option<String>,
+    /// Whether the service reported a running status within the verification timeout.
+    pub running_after_restart: bool,
+}
+
+/// Records every restart performed during a churn run.
+///
+/// The file is rewritten after each event, so the timeline is still usable if the churn is
+/// interrupted.
+pub struct ChurnTimeline {
+    events: Vec<ChurnEvent>,
+    format: TimelineFormat,
+    path: PathBuf,
+}
+
+impl ChurnTimeline {
+    pub fn new(name: &str, path: Option<PathBuf>, format: TimelineFormat) -> Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None => {
+                let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%S").to_string();
+                get_data_directory()?.join(format!("{name}-churn-{timestamp}.{format}"))
+            }
+        };
+        println!(
+            "Churn events will be recorded to {}",
+            path.to_string_lossy()
+        );
+        Ok(Self {
+            events: Vec::new(),
+            format,
+            path,
+        })
+    }
+
+    pub fn record(&mut self, event: ChurnEvent) -> Result<()> {
+        self.events.push(event);
+        self.write()
+    }
+
+    pub fn print_summary(&self) {
+        let failed = self
+            .events
+            .iter()
+            .filter(|event| !event.running_after_restart)
+            .collect::<Vec<_>>();
+        println!("==== Churn Summary ====");
+        println!("Total restarts: {}", self.events.len());
+        println!(
+            "Verified running: {}",
+            self.events.len().saturating_sub(failed.len())
+        );
+        if !failed.is_empty() {
+            println!("Not running after restart: {}", failed.len());
+            for event in failed {
+                println!(
+                    "  {} safenode-{}.service @ {} (old PeerId: {})",
+                    event.vm_name, event.service_number, event.daemon_endpoint, event.old_peer_id
+                );
+            }
+        }
+        println!("Timeline written to {}", self.path.to_string_lossy());
+    }
+
+    fn write(&self) -> Result<()> {
+        let mut file = File::create(&self.path)?;
+        match self.format {
+            TimelineFormat::Json => {
+                let json = serde_json::to_string_pretty(&self.events)?;
+                file.write_all(json.as_bytes())?;
+            }
+            TimelineFormat::Csv => {
+                writeln!(
+                    file,
+                    "timestamp,vm_name,daemon_endpoint,service_number,old_peer_id,new_peer_id,running_after_restart"
+                )?;
+                for event in self.events.iter() {
+                    writeln!(
+                        file,
+                        "{},{},{},{},{},{},{}",
+                        event.timestamp,
+                        event.vm_name,
+                        event.daemon_endpoint,
+                        event.service_number,
+                        event.old_peer_id,
+                        event.new_peer_id.as_deref().unwrap_or_default(),
+                        event.running_after_restart
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_timeline_format(val: &str) -> Result<TimelineFormat> {
+    match val.to_lowercase().as_str() {
+        "csv" => Ok(TimelineFormat::Csv),
+        "json" => Ok(TimelineFormat::Json),
+        _ => Err(eyre!(
+            "The only supported timeline formats are 'json' or 'csv'"
+        )),
+    }
+}
+
 // Administer or perform activities on a deployed network.
 #[derive(Subcommand, Debug)]
 pub enum NetworkCommands {
     /// Restart nodes in the testnet to simulate the churn of nodes.
     #[clap(name = "churn", subcommand)]
     ChurnCommands(ChurnCommands),
+    /// Query every node through its RPC endpoint and print a summary of the health of the network.
+    ///
+    /// The summary includes the connected peer counts, uptimes and versions of the nodes.
+    Health {
+        /// The number of nodes to query concurrently.
+        #[clap(long, short = 'c', default_value_t = 50)]
+        concurrent_requests: usize,
+        /// Print the result of each query as JSON rather than the summary.
+        #[clap(long)]
+        json: bool,
+        /// The name of the environment
+        #[arg(short = 'n', long)]
+        name: String,
+    },
     /// Modifies the log levels for all the antnode services through RPC requests.
     UpdateNodeLogLevel {
         /// The number of nodes to update concurrently.
@@ -114,6 +300,32 @@ pub async fn handle_update_node_log_level(
     Ok(())
 }
 
+pub async fn handle_network_health(
+    concurrent_requests: usize,
+    json: bool,
+    name: String,
+) -> Result<()> {
+    let inventory_path = get_data_directory()?.join(format!("{name}-inventory.json"));
+    if !inventory_path.exists() {
+        return Err(eyre!("There is no inventory for the {name} testnet")
+            .suggestion("Please run the inventory command to generate it"));
+    }
+
+    let inventory = DeploymentInventory::read(&inventory_path)?;
+    let rpc_client = sn_testnet_deploy::rpc_client::RpcClient::new(concurrent_requests);
+    let health = rpc_client
+        .get_network_health(&inventory.node_vm_list())
+        .await;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&health)?);
+    } else {
+        health.print_summary();
+    }
+
+    Ok(())
+}
+
 // Used internally for easier debugging
 struct DaemonRpcClient {
     addr: SocketAddr,
@@ -122,12 +334,17 @@ struct DaemonRpcClient {
 
 /// Perform fixed interval churn in the network by restarting nodes.
 /// This causes concurrent_churns nodes per vm to churn at a time.
+///
+/// Each restart is verified and recorded to the timeline.
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_fixed_interval_network_churn(
     inventory: DeploymentInventory,
     sleep_interval: Duration,
     concurrent_churns: usize,
     retain_peer_id: bool,
     max_churn_cycles: usize,
+    mut timeline: ChurnTimeline,
+    verify_timeout: Duration,
 ) -> Result<()> {
     let safenodemand_endpoints = inventory
         .node_vms
@@ -142,6 +359,7 @@ pub async fn handle_fixed_interval_network_churn(
                 .map(|endpoint| (inventory.peer_cache_node_count(), endpoint)),
         )
         .collect::<BTreeSet<_>>();
+    let vm_names = get_daemon_endpoint_vm_names(&inventory);
 
     let max_churn_cycles = std::cmp::max(max_churn_cycles, 1);
     println!("===== Configurations =====");
@@ -159,11 +377,16 @@ pub async fn handle_fixed_interval_network_churn(
             let mut concurrent_churns = 0;
             for (peer_id, node_service_number) in nodes_to_churn {
                 // we don't call restart concurrently as the daemon does not handle concurrent node registry reads/writes.
-                restart_node(peer_id, retain_peer_id, &mut daemon_client).await?;
-
-                println!(
-                    "safenode-{node_service_number:?}.service has been restarted. PeerId: {peer_id:?}"
-                );
+                let event = restart_and_verify_node(
+                    peer_id,
+                    node_service_number,
+                    retain_peer_id,
+                    &mut daemon_client,
+                    &vm_names,
+                    verify_timeout,
+                )
+                .await?;
+                timeline.record(event)?;
 
                 concurrent_churns += 1;
                 if concurrent_churns >= max_concurrent_churns {
@@ -176,15 +399,20 @@ pub async fn handle_fixed_interval_network_churn(
 
         n_cycles += 1;
     }
+
+    timeline.print_summary();
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_random_interval_network_churn(
     inventory: DeploymentInventory,
     time_frame: Duration,
     churn_count: usize,
     retain_peer_id: bool,
     max_churn_cycles: usize,
+    mut timeline: ChurnTimeline,
+    verify_timeout: Duration,
 ) -> Result<()> {
     if churn_count == 0 {
         bail!("Churn count cannot be 0");
@@ -201,6 +429,7 @@ pub async fn handle_random_interval_network_churn(
                 .filter_map(|node_vm| node_vm.safenodemand_endpoint),
         )
         .collect::<BTreeSet<_>>();
+    let vm_names = get_daemon_endpoint_vm_names(&inventory);
 
     let max_churn_cycles = std::cmp::max(max_churn_cycles, 1);
     let mut n_cycles = 0;
@@ -247,6 +476,7 @@ pub async fn handle_random_interval_network_churn(
             for ((daemon_endpoint, peer_id, node_service_number), interval) in
                 batch.iter().zip(intervals)
             {
+                let restart_started = Instant::now();
                 let sleep_time = Duration::from_secs(interval - previous_interval);
 
                 // reuse previous_daemon_rpc if endpoints match, which most probably they will all_running_nodes is not
@@ -256,10 +486,20 @@ pub async fn handle_random_interval_network_churn(
                     _ => get_safenode_manager_rpc_client(*daemon_endpoint).await?,
                 };
 
-                restart_node(*peer_id, retain_peer_id, &mut daemon_client).await?;
-                println!(
-                    "safenode-{node_service_number:?}.service @ {daemon_endpoint:?} has been restarted. PeerId: {peer_id:?}"
-                );
+                let event = restart_and_verify_node(
+                    *peer_id,
+                    *node_service_number,
+                    retain_peer_id,
+                    &mut daemon_client,
+                    &vm_names,
+                    verify_timeout,
+                )
+                .await?;
+                timeline.record(event)?;
+
+                // The time spent verifying the restart is deducted from the sleep, so the overall
+                // time frame is preserved.
+                let sleep_time = sleep_time.saturating_sub(restart_started.elapsed());
                 println!("Sleeping for {sleep_time:?} before restarting the next node.");
                 tokio::time::sleep(sleep_time).await;
 
@@ -271,6 +511,7 @@ pub async fn handle_random_interval_network_churn(
         n_cycles += 1;
     }
 
+    timeline.print_summary();
     Ok(())
 }
 
@@ -379,6 +620,153 @@ async fn get_running_node_list(daemon_client: &mut DaemonRpcClient) -> Result<Ve
     Ok(peers)
 }
 
+// Map each daemon endpoint to the name of the VM it is running on.
+fn get_daemon_endpoint_vm_names(inventory: &DeploymentInventory) -> HashMap<SocketAddr, String> {
+    inventory
+        .node_vms
+        .iter()
+        .chain(inventory.peer_cache_node_vms.iter())
+        .filter_map(|node_vm| {
+            node_vm
+                .safenodemand_endpoint
+                .map(|endpoint| (endpoint, node_vm.vm.name.clone()))
+        })
+        .collect()
+}
+
+// Restart a node, then wait for it to report a running status so the restart can be recorded.
+//
+// A failure to come back within the timeout is flagged on the event rather than treated as an
+// error, because the churn should carry on.
+async fn restart_and_verify_node(
+    peer_id: PeerId,
+    node_service_number: u32,
+    retain_peer_id: bool,
+    daemon_client: &mut DaemonRpcClient,
+    vm_names: &HashMap<SocketAddr, String>,
+    verify_timeout: Duration,
+) -> Result<ChurnEvent> {
+    let existing_numbers = get_node_service_numbers(daemon_client).await?;
+    let timestamp = chrono::Utc::now().to_rfc3339();
+    restart_node(peer_id, retain_peer_id, daemon_client).await?;
+    let vm_name = vm_names
+        .get(&daemon_client.addr)
+        .cloned()
+        .unwrap_or_else(|| daemon_client.addr.ip().to_string());
+    println!(
+        "safenode-{node_service_number}.service @ {vm_name} has been restarted. PeerId: {peer_id:?}"
+    );
+
+    let new_peer_id = wait_for_restarted_node(
+        daemon_client,
+        peer_id,
+        node_service_number,
+        retain_peer_id,
+        &existing_numbers,
+        verify_timeout,
+    )
+    .await?;
+    match &new_peer_id {
+        Some(new_peer_id) => println!("Verified running with PeerId: {new_peer_id:?}"),
+        None => println!(
+            "safenode-{node_service_number}.service @ {vm_name} did not report a running status within {verify_timeout:?}"
+        ),
+    }
+
+    Ok(ChurnEvent {
+        timestamp,
+        vm_name,
+        daemon_endpoint: daemon_client.addr,
+        service_number: node_service_number,
+        old_peer_id: peer_id.to_string(),
+        running_after_restart: new_peer_id.is_some(),
+        new_peer_id: new_peer_id.map(|peer_id| peer_id.to_string()),
+    })
+}
+
+// Return the service numbers of all the nodes known to the daemon, regardless of status.
+async fn get_node_service_numbers(daemon_client: &mut DaemonRpcClient) -> Result<BTreeSet<u32>> {
+    let response = daemon_client
+        .rpc
+        .get_status(Request::new(GetStatusRequest {}))
+        .await
+        .map_err(|err| {
+            eyre!(
+                "Failed to get status from {:?} with err: {err:?}",
+                daemon_client.addr
+            )
+        })?;
+    Ok(response
+        .get_ref()
+        .nodes
+        .iter()
+        .map(|node| node.number)
+        .collect())
+}
+
+// Poll the daemon until the restarted service reports a running status, returning its PeerId.
+//
+// When the PeerId is not retained, the daemon replaces the service with a new one, so any running
+// service that did not exist before the restart is also accepted, but only with a new PeerId.
+//
+// The status is only trusted once the service has been seen to leave the running state, or after
+// the settle time, because the first poll can still see the node from before the restart.
+async fn wait_for_restarted_node(
+    daemon_client: &mut DaemonRpcClient,
+    old_peer_id: PeerId,
+    node_service_number: u32,
+    retain_peer_id: bool,
+    existing_numbers: &BTreeSet<u32>,
+    timeout: Duration,
+) -> Result<Option<PeerId>> {
+    let started = Instant::now();
+    let mut has_stopped = false;
+    loop {
+        match daemon_client
+            .rpc
+            .get_status(Request::new(GetStatusRequest {}))
+            .await
+        {
+            Ok(response) => {
+                let nodes = &response.get_ref().nodes;
+                if !nodes.iter().any(|node| {
+                    node.number == node_service_number
+                        && node.status == ServiceStatus::Running as i32
+                }) {
+                    has_stopped = true;
+                }
+
+                if has_stopped || started.elapsed() >= RESTART_SETTLE_TIME {
+                    for node in nodes.iter().filter(|node| {
+                        node.status == ServiceStatus::Running as i32
+                            && (node.number == node_service_number
+                                || !existing_numbers.contains(&node.number))
+                    }) {
+                        let Some(peer_id) = node.peer_id.as_ref() else {
+                            continue;
+                        };
+                        let new_peer_id = PeerId::from_bytes(peer_id)?;
+                        if retain_peer_id || new_peer_id != old_peer_id {
+                            return Ok(Some(new_peer_id));
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                log::debug!(
+                    "Failed to get status from {:?} while verifying restart: {err:?}",
+                    daemon_client.addr
+                );
+            }
+        }
+
+        if started.elapsed() >= timeout {
+            return Ok(None);
+        }
+        tokio::time::sleep(RESTART_VERIFICATION_POLL_INTERVAL).await;
+    }
+}
+
 // Restart a remote safenode service by sending a RPC to the safenode manager daemon.
 async fn restart_node(
     peer_id: PeerId,
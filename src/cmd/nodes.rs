@@ -16,6 +16,7 @@ use sn_testnet_deploy::{
         AnsiblePlaybook,
     },
     inventory::DeploymentInventoryService,
+    policy::{DestructivePreview, EnvironmentPolicy},
     CloudProvider, EvmNetwork, NodeType, TestnetDeployBuilder,
 };
 use std::{str::FromStr, time::Duration};
@@ -122,6 +123,16 @@ pub async fn handle_reset_command(
         None
     };
 
+    let policy = EnvironmentPolicy::get(&name, &testnet_deployer.object_store).await?;
+    policy.enforce(&name, "reset", || {
+        Ok(DestructivePreview::from_node_vms(
+            "The nodes on these VMs will be removed and recreated",
+            &inventory,
+            node_type.as_ref(),
+            custom_inventory.as_ref(),
+        ))
+    })?;
+
     testnet_deployer.reset(node_type, custom_inventory)?;
 
     Ok(())
@@ -166,6 +177,16 @@ pub async fn handle_stop_command(
         None
     };
 
+    let policy = EnvironmentPolicy::get(&name, &testnet_deployer.object_store).await?;
+    policy.enforce(&name, "stop", || {
+        Ok(DestructivePreview::from_node_vms(
+            "The nodes on these VMs will be stopped",
+            &inventory,
+            node_type.as_ref(),
+            custom_inventory.as_ref(),
+        ))
+    })?;
+
     testnet_deployer.stop(interval, node_type, custom_inventory, delay, service_names)?;
 
     Ok(())
@@ -304,6 +325,20 @@ pub async fn handle_reset_to_n_nodes_command(
         .build()?;
     testnet_deployer.init().await?;
 
+    let custom_vms = custom_inventory
+        .as_ref()
+        .map(|custom_inventory| get_custom_inventory(&inventory, custom_inventory))
+        .transpose()?;
+    let policy = EnvironmentPolicy::get(&name, &testnet_deployer.object_store).await?;
+    policy.enforce(&name, "reset-to-n-nodes", || {
+        Ok(DestructivePreview::from_node_vms(
+            &format!("The nodes on these VMs will be reset to {node_count} nodes"),
+            &inventory,
+            node_type.as_ref(),
+            custom_vms.as_ref(),
+        ))
+    })?;
+
     let antnode_version = get_version_from_option(version, &ReleaseType::AntNode).await?;
     let mut extra_vars = ExtraVarsDocBuilder::default();
     extra_vars.add_variable("environment_name", &name);
@@ -315,9 +350,8 @@ pub async fn handle_reset_to_n_nodes_command(
 
     let ansible_runner = &testnet_deployer.ansible_provisioner.ansible_runner;
 
-    if let Some(custom_inventory) = custom_inventory {
+    if let Some(custom_vms) = custom_vms {
         println!("Running the playbook with a custom inventory");
-        let custom_vms = get_custom_inventory(&inventory, &custom_inventory)?;
         generate_custom_environment_inventory(
             &custom_vms,
             &name,
@@ -4,7 +4,7 @@
 // This SAFE Network Software is licensed under the BSD-3-Clause license.
 // Please see the LICENSE file for more details.
 
-use super::{get_options_from_s3, OptionsType};
+use super::{get_options_from_s3, parse_secret_reference, OptionsType};
 use clap::{arg, Subcommand};
 use color_eyre::Result;
 use sn_testnet_deploy::{
@@ -13,6 +13,7 @@ use sn_testnet_deploy::{
     error::Error,
     get_bootstrap_cache_url, get_genesis_multiaddr,
     inventory::DeploymentInventoryService,
+    secrets::SecretReference,
     CloudProvider, NodeType, TestnetDeployBuilder,
 };
 
@@ -21,6 +22,15 @@ pub enum ProvisionCommands {
     /// Provision clients for an environment
     #[clap(name = "clients")]
     Clients {
+        /// The secret key for the wallet that will fund all the ANT instances.
+        ///
+        /// The key is not saved with the deployment options, so it must be supplied again to
+        /// fund the uploaders on Arbitrum or Sepolia networks.
+        ///
+        /// Rather than the key itself, supply a reference to it, with 'env:NAME', 'file:PATH',
+        /// 'vault:PATH' for a file encrypted with ansible-vault, or 'keyring:[SERVICE/]ACCOUNT'.
+        #[clap(long, value_parser = parse_secret_reference)]
+        funding_wallet_secret_key: Option<SecretReference>,
         /// The name of the environment
         #[arg(short = 'n', long)]
         name: String,
@@ -235,8 +245,14 @@ pub async fn handle_provision_upnp_nodes(name: String, disable_nodes: bool) -> R
     handle_provision_nodes(name, NodeType::Upnp, disable_nodes).await
 }
 
-pub async fn handle_provision_clients(name: String) -> Result<()> {
-    let (_, provision_options, provisioner, ssh_client) = init_provision(&name).await?;
+pub async fn handle_provision_clients(
+    name: String,
+    funding_wallet_secret_key: Option<SecretReference>,
+) -> Result<()> {
+    let (_, mut provision_options, provisioner, ssh_client) = init_provision(&name).await?;
+    provision_options.funding_wallet_secret_key = funding_wallet_secret_key
+        .map(|sk| sk.resolve())
+        .transpose()?;
     let (genesis_multiaddr, genesis_ip) =
         get_genesis_multiaddr(&provisioner.ansible_runner, &ssh_client)?
             .ok_or_else(|| Error::GenesisListenAddress)?;
@@ -0,0 +1,620 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crate::{
+    digital_ocean::{DigitalOceanClient, ENVIRONMENT_TAG_PREFIX},
+    error::{Error, Result},
+    infra::{build_terraform_args, InfraRunOptions},
+    inventory::get_data_directory,
+    logs::line::parse_rfc3339,
+    TestnetDeployer,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+const PRICING_CACHE_FILE_NAME: &str = "digital_ocean_pricing.json";
+const PRICING_CACHE_MAX_AGE_DAYS: i64 = 7;
+/// Digital Ocean bills by the hour, up to a cap of 672 hours per month.
+const HOURS_PER_MONTH: f64 = 672.0;
+/// Block storage is not included in the sizes API.
+const VOLUME_PRICE_PER_GB_MONTHLY: f64 = 0.10;
+
+// Each group of VMs is described by the Terraform variables for its count and its droplet size,
+// and optionally the size of its attached volumes.
+const VM_GROUPS: [(&str, &str, &str, Option<&str>); 12] = [
+    (
+        "Peer Cache nodes",
+        "peer_cache_node_vm_count",
+        "peer_cache_droplet_size",
+        Some("peer_cache_node_volume_size"),
+    ),
+    (
+        "Genesis node",
+        "genesis_vm_count",
+        "peer_cache_droplet_size",
+        Some("genesis_node_volume_size"),
+    ),
+    (
+        "Nodes",
+        "node_vm_count",
+        "node_droplet_size",
+        Some("node_volume_size"),
+    ),
+    (
+        "Full Cone NAT gateways",
+        "full_cone_private_node_vm_count",
+        "full_cone_droplet_size",
+        None,
+    ),
+    (
+        "Full Cone private nodes",
+        "full_cone_private_node_vm_count",
+        "full_cone_droplet_size",
+        Some("full_cone_private_node_volume_size"),
+    ),
+    (
+        "Port Restricted Cone NAT gateways",
+        "port_restricted_cone_node_vm_count",
+        "port_restricted_cone_nat_gateway_droplet_size",
+        None,
+    ),
+    (
+        "Port Restricted Cone private nodes",
+        "port_restricted_cone_node_vm_count",
+        "port_restricted_cone_droplet_size",
+        Some("port_restricted_private_node_volume_size"),
+    ),
+    (
+        "Symmetric NAT gateways",
+        "symmetric_private_node_vm_count",
+        "symmetric_nat_gateway_droplet_size",
+        None,
+    ),
+    (
+        "Symmetric private nodes",
+        "symmetric_private_node_vm_count",
+        "node_droplet_size",
+        Some("symmetric_private_node_volume_size"),
+    ),
+    (
+        "UPnP private nodes",
+        "upnp_private_node_vm_count",
+        "upnp_droplet_size",
+        Some("upnp_private_node_volume_size"),
+    ),
+    (
+        "Clients",
+        "ant_client_vm_count",
+        "ant_client_droplet_size",
+        Some("ant_client_volume_size"),
+    ),
+    (
+        "EVM nodes",
+        "evm_node_vm_count",
+        "evm_node_droplet_size",
+        None,
+    ),
+];
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SizePrice {
+    pub price_hourly: f64,
+    pub price_monthly: f64,
+}
+
+/// The prices of the droplet sizes, in US dollars, cached from the Digital Ocean sizes API.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PricingTable {
+    pub fetched_at: String,
+    pub sizes: BTreeMap<String, SizePrice>,
+}
+
+impl PricingTable {
+    /// Get the pricing table from the local cache, or fetch it from the API if the cache doesn't
+    /// exist, is more than a week old, or a refresh is requested.
+    pub async fn get(refresh: bool) -> Result<PricingTable> {
+        let cache_path = get_pricing_cache_path()?;
+        if !refresh && cache_path.exists() {
+            let table: PricingTable = serde_json::from_reader(File::open(&cache_path)?)?;
+            let is_fresh = parse_rfc3339(&table.fetched_at).is_some_and(|fetched_at| {
+                Utc::now() - fetched_at < chrono::Duration::days(PRICING_CACHE_MAX_AGE_DAYS)
+            });
+            if is_fresh {
+                return Ok(table);
+            }
+        }
+
+        eprintln!("Retrieving droplet prices from Digital Ocean...");
+        let sizes = DigitalOceanClient::from_env()?
+            .list_sizes()
+            .await?
+            .into_iter()
+            .map(|size| {
+                (
+                    size.slug,
+                    SizePrice {
+                        price_hourly: size.price_hourly,
+                        price_monthly: size.price_monthly,
+                    },
+                )
+            })
+            .collect();
+        let table = PricingTable {
+            fetched_at: Utc::now().to_rfc3339(),
+            sizes,
+        };
+        serde_json::to_writer_pretty(File::create(&cache_path)?, &table)?;
+        Ok(table)
+    }
+
+    fn get_size_price(&self, slug: &str) -> Result<&SizePrice> {
+        self.sizes
+            .get(slug)
+            .ok_or_else(|| Error::DropletSizePriceNotFound(slug.to_string()))
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CostItem {
+    pub description: String,
+    pub count: u64,
+    /// The droplet size, or the size of each volume in GB.
+    pub size: String,
+    pub hourly: f64,
+    pub monthly: f64,
+}
+
+/// An estimate of what the infrastructure for an environment will cost while it is running.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CostEstimate {
+    pub items: Vec<CostItem>,
+    pub hourly: f64,
+    pub monthly: f64,
+}
+
+impl CostEstimate {
+    fn add_item(&mut self, item: CostItem) {
+        self.hourly += item.hourly;
+        self.monthly += item.monthly;
+        self.items.push(item);
+    }
+
+    pub fn print_report(&self) {
+        println!("==== Estimated Cost ====");
+        for item in self.items.iter() {
+            println!(
+                "{:<36} {:>4} x {:<16} ${:>9.2}/hour ${:>10.2}/month",
+                item.description, item.count, item.size, item.hourly, item.monthly
+            );
+        }
+        println!(
+            "{:<63} ${:>9.2}/hour ${:>10.2}/month",
+            "Total", self.hourly, self.monthly
+        );
+    }
+}
+
+/// The spend for an environment since each of its droplets and volumes were created.
+#[derive(Clone, Debug, Serialize)]
+pub struct EnvironmentSpend {
+    pub name: String,
+    pub droplet_count: usize,
+    pub volume_count: usize,
+    /// The time the oldest droplet was created, in RFC 3339 format.
+    pub created_at: Option<String>,
+    pub hourly: f64,
+    pub accumulated: f64,
+}
+
+impl TestnetDeployer {
+    /// Estimate the cost of the infrastructure that would be created with the given options.
+    ///
+    /// Any value that isn't set in the options is resolved in the same way Terraform would
+    /// resolve it: from the tfvars files, then from the default in the variables file.
+    pub fn estimate_infra_cost(
+        &self,
+        options: &InfraRunOptions,
+        pricing: &PricingTable,
+    ) -> Result<CostEstimate> {
+        let variables = resolve_variables(&self.terraform_runner.working_directory_path, options)?;
+
+        let get_number = |name: &str| {
+            variables
+                .get(name)
+                .and_then(|value| value.parse::<u64>().ok())
+                .unwrap_or_default()
+        };
+        let volumes_per_vm = get_number("volumes_per_node");
+
+        let mut estimate = CostEstimate::default();
+        if variables.get("use_custom_bin").map(|v| v.as_str()) == Some("true") {
+            if let Some(size) = variables.get("build_machine_size") {
+                let price = pricing.get_size_price(size)?;
+                estimate.add_item(CostItem {
+                    description: "Build VM".to_string(),
+                    count: 1,
+                    size: size.clone(),
+                    hourly: price.price_hourly,
+                    monthly: price.price_monthly,
+                });
+            }
+        }
+        let mut volume_gb = 0;
+        for (description, count_variable, size_variable, volume_size_variable) in VM_GROUPS {
+            let count = get_number(count_variable);
+            if count == 0 {
+                continue;
+            }
+            let Some(size) = variables.get(size_variable) else {
+                continue;
+            };
+            let price = pricing.get_size_price(size)?;
+            estimate.add_item(CostItem {
+                description: description.to_string(),
+                count,
+                size: size.clone(),
+                hourly: price.price_hourly * count as f64,
+                monthly: price.price_monthly * count as f64,
+            });
+            if let Some(volume_size_variable) = volume_size_variable {
+                volume_gb += count * volumes_per_vm * get_number(volume_size_variable);
+            }
+        }
+        if volume_gb > 0 {
+            let monthly = volume_gb as f64 * VOLUME_PRICE_PER_GB_MONTHLY;
+            estimate.add_item(CostItem {
+                description: "Attached volumes".to_string(),
+                count: volume_gb,
+                size: "1GB".to_string(),
+                hourly: monthly / HOURS_PER_MONTH,
+                monthly,
+            });
+        }
+        Ok(estimate)
+    }
+
+    /// Print the cost estimate for the infrastructure.
+    ///
+    /// The estimate is only informational, so a failure to produce it doesn't prevent the
+    /// deployment.
+    pub async fn print_infra_cost_estimate(&self, options: &InfraRunOptions) {
+        let estimate = match PricingTable::get(false).await {
+            Ok(pricing) => self.estimate_infra_cost(options, &pricing),
+            Err(err) => Err(err),
+        };
+        match estimate {
+            Ok(estimate) => estimate.print_report(),
+            Err(err) => println!("Could not estimate the cost of the infrastructure: {err}"),
+        }
+    }
+}
+
+/// Calculate the spend for every environment that has droplets, based on how long each of its
+/// droplets and volumes have existed.
+///
+/// This is an approximation, because Digital Ocean caps the hourly charges for a resource at its
+/// monthly price.
+pub async fn get_environment_spend(pricing: &PricingTable) -> Result<Vec<EnvironmentSpend>> {
+    let client = DigitalOceanClient::from_env()?;
+    let droplets = client.list_droplets(true).await?;
+    let volumes = client.list_volumes().await?;
+    let now = Utc::now();
+    let hours_since = |created_at: &str| {
+        parse_rfc3339(created_at)
+            .map(|created_at| (now - created_at).num_seconds().max(0) as f64 / 3600.0)
+            .unwrap_or_default()
+    };
+
+    let mut spend: BTreeMap<String, EnvironmentSpend> = BTreeMap::new();
+    let mut droplet_environments = HashMap::new();
+    for droplet in droplets.iter() {
+        let Some(name) = droplet
+            .tags
+            .iter()
+            .find_map(|tag| tag.strip_prefix(ENVIRONMENT_TAG_PREFIX))
+        else {
+            continue;
+        };
+        droplet_environments.insert(droplet.id, name.to_string());
+        let price = pricing.get_size_price(&droplet.size_slug)?;
+        let environment = spend
+            .entry(name.to_string())
+            .or_insert_with(|| EnvironmentSpend {
+                name: name.to_string(),
+                droplet_count: 0,
+                volume_count: 0,
+                created_at: None,
+                hourly: 0.0,
+                accumulated: 0.0,
+            });
+        environment.droplet_count += 1;
+        environment.hourly += price.price_hourly;
+        environment.accumulated += price.price_hourly * hours_since(&droplet.created_at);
+        // The timestamps are all RFC 3339 in UTC, so they order correctly as strings.
+        if environment
+            .created_at
+            .as_ref()
+            .is_none_or(|created_at| droplet.created_at < *created_at)
+        {
+            environment.created_at = Some(droplet.created_at.clone());
+        }
+    }
+
+    // Volumes have no tags, so they are attributed to the environment of their droplet.
+    let volume_hourly_per_gb = VOLUME_PRICE_PER_GB_MONTHLY / HOURS_PER_MONTH;
+    for volume in volumes.iter() {
+        let Some(name) = volume
+            .droplet_ids
+            .iter()
+            .find_map(|id| droplet_environments.get(id))
+        else {
+            continue;
+        };
+        if let Some(environment) = spend.get_mut(name) {
+            let hourly = volume.size_gigabytes as f64 * volume_hourly_per_gb;
+            environment.volume_count += 1;
+            environment.hourly += hourly;
+            environment.accumulated += hourly * hours_since(&volume.created_at);
+        }
+    }
+
+    Ok(spend.into_values().collect())
+}
+
+pub fn print_environment_spend(spend: &[EnvironmentSpend]) {
+    println!("==== Environment Spend ====");
+    println!(
+        "{:<24} {:<26} {:>8} {:>8} {:>12} {:>12}",
+        "Environment", "Created", "Droplets", "Volumes", "Per hour", "Accumulated"
+    );
+    for environment in spend.iter() {
+        println!(
+            "{:<24} {:<26} {:>8} {:>8} {:>12} {:>12}",
+            environment.name,
+            environment.created_at.as_deref().unwrap_or("-"),
+            environment.droplet_count,
+            environment.volume_count,
+            format!("${:.2}", environment.hourly),
+            format!("${:.2}", environment.accumulated)
+        );
+    }
+    let hourly: f64 = spend.iter().map(|e| e.hourly).sum();
+    let accumulated: f64 = spend.iter().map(|e| e.accumulated).sum();
+    println!(
+        "{:<78} {:>12} {:>12}",
+        "Total",
+        format!("${hourly:.2}"),
+        format!("${accumulated:.2}")
+    );
+}
+
+fn get_pricing_cache_path() -> Result<PathBuf> {
+    Ok(get_data_directory()
+        .map_err(|_| Error::CouldNotRetrieveDataDirectory)?
+        .join(PRICING_CACHE_FILE_NAME))
+}
+
+// Only single line values are supported, which covers every number and string variable that is
+// used for the estimate.
+fn parse_assignment(line: &str) -> Option<(String, String)> {
+    let line = line.split('#').next()?.trim();
+    let (key, value) = line.split_once('=')?;
+    let value = value.trim().trim_matches('"');
+    if value.is_empty() || value.starts_with('[') || value.starts_with('{') {
+        return None;
+    }
+    Some((key.trim().to_string(), value.to_string()))
+}
+
+/// Resolve the Terraform variables, with the arguments from the options taking precedence over
+/// the tfvars files, which take precedence over the defaults.
+fn resolve_variables(
+    working_directory_path: &Path,
+    options: &InfraRunOptions,
+) -> Result<HashMap<String, String>> {
+    let mut variables = read_variable_defaults(&working_directory_path.join("variables.tf"))?;
+    for filename in options.tfvars_filenames.iter().flatten() {
+        variables.extend(read_tfvars(&working_directory_path.join(filename))?);
+    }
+    variables.extend(build_terraform_args(options)?);
+    Ok(variables)
+}
+
+fn read_tfvars(path: &Path) -> Result<HashMap<String, String>> {
+    let contents = std::fs::read_to_string(path)?;
+    Ok(contents.lines().filter_map(parse_assignment).collect())
+}
+
+fn read_variable_defaults(path: &Path) -> Result<HashMap<String, String>> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut defaults = HashMap::new();
+    let mut current_variable = None;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if let Some(rest) = trimmed.strip_prefix("variable ") {
+            current_variable = Some(
+                rest.trim_end_matches('{')
+                    .trim()
+                    .trim_matches('"')
+                    .to_string(),
+            );
+            continue;
+        }
+        if let (Some(variable), Some((key, value))) =
+            (current_variable.as_ref(), parse_assignment(trimmed))
+        {
+            if key == "default" {
+                defaults.insert(variable.clone(), value);
+            }
+        }
+    }
+    Ok(defaults)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VARIABLES_TF: &str = r#"
+variable "node_vm_count" {
+  description = "The number of node VMs"
+  default     = 10
+}
+
+variable "node_droplet_size" {
+  default = "s-2vcpu-4gb"
+}
+
+variable "peer_cache_droplet_size" {
+  default = "s-8vcpu-16gb" # The peer cache nodes need more memory
+}
+
+variable "region" {
+  default = "lon1"
+}
+
+variable "droplet_ssh_keys" {
+  type    = list(number)
+  default = [37243057, 38313409]
+}
+
+variable "name" {
+  type = string
+}
+"#;
+
+    const TFVARS: &str = r#"
+# Overrides for the environment type
+node_vm_count = 20
+node_droplet_size = "s-4vcpu-8gb"
+region = "ams3"
+"#;
+
+    fn get_infra_run_options(tfvars_filenames: Option<Vec<String>>) -> InfraRunOptions {
+        InfraRunOptions {
+            allow_destructive_changes: false,
+            client_image_id: None,
+            client_vm_count: None,
+            client_vm_size: None,
+            enable_build_vm: false,
+            evm_node_count: None,
+            evm_node_vm_size: None,
+            evm_node_image_id: None,
+            full_cone_vm_size: None,
+            full_cone_private_node_vm_count: None,
+            full_cone_private_node_volume_size: None,
+            genesis_vm_count: None,
+            genesis_node_volume_size: None,
+            name: "alpha".to_string(),
+            nat_gateway_image_id: None,
+            node_image_id: None,
+            node_vm_count: None,
+            node_vm_size: None,
+            node_volume_size: None,
+            peer_cache_image_id: None,
+            peer_cache_node_vm_count: None,
+            peer_cache_node_vm_size: None,
+            peer_cache_node_volume_size: None,
+            port_restricted_cone_vm_size: None,
+            port_restricted_private_node_vm_count: None,
+            port_restricted_private_node_volume_size: None,
+            region: "nyc3".to_string(),
+            symmetric_nat_gateway_vm_size: None,
+            symmetric_private_node_vm_count: None,
+            symmetric_private_node_volume_size: None,
+            tfvars_filenames,
+            upnp_vm_size: None,
+            upnp_private_node_vm_count: None,
+            upnp_private_node_volume_size: None,
+        }
+    }
+
+    fn write_fixture() -> tempfile::TempDir {
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::write(temp_dir.path().join("variables.tf"), VARIABLES_TF).unwrap();
+        std::fs::write(temp_dir.path().join("dev.tfvars"), TFVARS).unwrap();
+        temp_dir
+    }
+
+    #[test]
+    fn test_parse_assignment_reads_numbers_and_quoted_strings() {
+        assert_eq!(
+            parse_assignment("node_vm_count = 20"),
+            Some(("node_vm_count".to_string(), "20".to_string()))
+        );
+        assert_eq!(
+            parse_assignment("  node_droplet_size = \"s-4vcpu-8gb\"  "),
+            Some(("node_droplet_size".to_string(), "s-4vcpu-8gb".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_assignment_ignores_comments() {
+        assert_eq!(
+            parse_assignment("region = \"ams3\" # The closest region"),
+            Some(("region".to_string(), "ams3".to_string()))
+        );
+        assert_eq!(parse_assignment("# region = \"ams3\""), None);
+    }
+
+    #[test]
+    fn test_parse_assignment_skips_unsupported_values() {
+        assert_eq!(parse_assignment("droplet_ssh_keys = [37243057]"), None);
+        assert_eq!(parse_assignment("tags = {"), None);
+        assert_eq!(parse_assignment("name = \"\""), None);
+        assert_eq!(parse_assignment("variable \"name\" {"), None);
+    }
+
+    #[test]
+    fn test_read_variable_defaults_returns_the_default_for_each_variable() {
+        let temp_dir = write_fixture();
+        let defaults = read_variable_defaults(&temp_dir.path().join("variables.tf")).unwrap();
+
+        assert_eq!(defaults.len(), 4);
+        assert_eq!(defaults.get("node_vm_count").unwrap(), "10");
+        assert_eq!(defaults.get("node_droplet_size").unwrap(), "s-2vcpu-4gb");
+        assert_eq!(
+            defaults.get("peer_cache_droplet_size").unwrap(),
+            "s-8vcpu-16gb"
+        );
+        assert_eq!(defaults.get("region").unwrap(), "lon1");
+        assert!(!defaults.contains_key("droplet_ssh_keys"));
+        assert!(!defaults.contains_key("name"));
+    }
+
+    #[test]
+    fn test_resolve_variables_uses_the_defaults_without_tfvars() {
+        let temp_dir = write_fixture();
+        let variables = resolve_variables(temp_dir.path(), &get_infra_run_options(None)).unwrap();
+
+        assert_eq!(variables.get("node_vm_count").unwrap(), "10");
+        assert_eq!(variables.get("node_droplet_size").unwrap(), "s-2vcpu-4gb");
+    }
+
+    #[test]
+    fn test_resolve_variables_prefers_tfvars_over_defaults_and_args_over_tfvars() {
+        let temp_dir = write_fixture();
+        let mut options = get_infra_run_options(Some(vec!["dev.tfvars".to_string()]));
+        options.node_vm_count = Some(30);
+        let variables = resolve_variables(temp_dir.path(), &options).unwrap();
+
+        // Set in the options, the tfvars file and the defaults.
+        assert_eq!(variables.get("node_vm_count").unwrap(), "30");
+        assert_eq!(variables.get("region").unwrap(), "nyc3");
+        // Set in the tfvars file and the defaults.
+        assert_eq!(variables.get("node_droplet_size").unwrap(), "s-4vcpu-8gb");
+        // Only set in the defaults.
+        assert_eq!(
+            variables.get("peer_cache_droplet_size").unwrap(),
+            "s-8vcpu-16gb"
+        );
+    }
+}
@@ -8,12 +8,13 @@ use crate::{
     ansible::provisioning::{PrivateNodeProvisionInventory, ProvisionOptions},
     error::{Error, Result},
     funding::get_address_from_sk,
-    get_anvil_node_data_hardcoded, get_bootstrap_cache_url, get_genesis_multiaddr,
-    write_environment_details, BinaryOption, DeploymentInventory, DeploymentType,
-    EnvironmentDetails, EnvironmentType, EvmDetails, EvmNetwork, InfraRunOptions, LogFormat,
-    NodeType, TestnetDeployer,
+    get_anvil_node_data_hardcoded, get_bootstrap_cache_url, get_environment_expiry_time,
+    get_environment_owner, get_genesis_multiaddr, write_environment_details, BinaryOption,
+    DeploymentInventory, DeploymentType, EnvironmentDetails, EnvironmentType, EvmDetails,
+    EvmNetwork, InfraRunOptions, LogFormat, NodeType, TestnetDeployer,
 };
 use alloy::{hex::ToHexExt, primitives::U256};
+use chrono::Utc;
 use colored::Colorize;
 use log::error;
 use serde::{Deserialize, Serialize};
@@ -21,6 +22,9 @@ use std::{path::PathBuf, time::Duration};
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct DeployOptions {
+    /// Only applies to this run, so it is not saved with the rest of the options.
+    #[serde(skip)]
+    pub allow_destructive_changes: bool,
     pub binary_option: BinaryOption,
     pub chunk_size: Option<u64>,
     pub chunk_tracker_data_addresses: Vec<String>,
@@ -41,11 +45,15 @@ pub struct DeployOptions {
     pub full_cone_private_node_count: u16,
     pub full_cone_private_node_vm_count: Option<u16>,
     pub full_cone_private_node_volume_size: Option<u16>,
+    /// The resolved secret is not saved with the rest of the options.
+    #[serde(skip)]
     pub funding_wallet_secret_key: Option<String>,
     pub genesis_node_volume_size: Option<u16>,
     pub initial_gas: Option<U256>,
     pub initial_tokens: Option<U256>,
     pub interval: Duration,
+    /// The environment can be reaped once this has elapsed since it was deployed.
+    pub lifetime: Option<Duration>,
     pub log_format: Option<LogFormat>,
     pub max_archived_log_files: u16,
     pub max_log_files: u16,
@@ -59,6 +67,7 @@ pub struct DeployOptions {
     pub node_vm_size: Option<String>,
     pub node_volume_size: Option<u16>,
     pub output_inventory_dir_path: PathBuf,
+    pub owner: Option<String>,
     pub peer_cache_node_count: u16,
     pub peer_cache_node_vm_count: Option<u16>,
     pub peer_cache_node_vm_size: Option<String>,
@@ -93,9 +102,11 @@ impl TestnetDeployer {
         &self,
         options: &DeployOptions,
     ) -> Result<(ProvisionOptions, (String, String))> {
+        let created_at = Utc::now();
         let build_custom_binaries = options.binary_option.should_provision_build_machine();
 
-        self.create_or_update_infra(&InfraRunOptions {
+        let infra_run_options = InfraRunOptions {
+            allow_destructive_changes: options.allow_destructive_changes,
             client_image_id: None,
             client_vm_count: options.client_vm_count,
             client_vm_size: options.client_vm_size.clone(),
@@ -141,16 +152,19 @@ impl TestnetDeployer {
             upnp_vm_size: options.upnp_vm_size.clone(),
             upnp_private_node_vm_count: options.upnp_private_node_vm_count,
             upnp_private_node_volume_size: options.upnp_private_node_volume_size,
-        })
-        .map_err(|err| {
-            println!("Failed to create infra {err:?}");
-            err
-        })?;
+        };
+        self.print_infra_cost_estimate(&infra_run_options).await;
+        self.create_or_update_infra(&infra_run_options)
+            .map_err(|err| {
+                println!("Failed to create infra {err:?}");
+                err
+            })?;
 
         write_environment_details(
-            &self.s3_repository,
+            &self.object_store,
             &options.name,
             &EnvironmentDetails {
+                created_at: Some(created_at.to_rfc3339()),
                 deployment_type: DeploymentType::New,
                 environment_type: options.environment_type.clone(),
                 evm_details: EvmDetails {
@@ -159,8 +173,10 @@ impl TestnetDeployer {
                     payment_token_address: options.evm_payment_token_address.clone(),
                     rpc_url: options.evm_rpc_url.clone(),
                 },
+                expires_at: get_environment_expiry_time(created_at, options.lifetime),
                 funding_wallet_address: None,
                 network_id: Some(options.network_id),
+                owner: get_environment_owner(options.owner.clone()),
                 region: options.region.clone(),
                 rewards_address: Some(options.rewards_address.clone()),
             },
@@ -212,9 +228,10 @@ impl TestnetDeployer {
         };
 
         write_environment_details(
-            &self.s3_repository,
+            &self.object_store,
             &options.name,
             &EnvironmentDetails {
+                created_at: Some(created_at.to_rfc3339()),
                 deployment_type: DeploymentType::New,
                 environment_type: options.environment_type.clone(),
                 evm_details: EvmDetails {
@@ -223,8 +240,10 @@ impl TestnetDeployer {
                     payment_token_address: provision_options.evm_payment_token_address.clone(),
                     rpc_url: provision_options.evm_rpc_url.clone(),
                 },
+                expires_at: get_environment_expiry_time(created_at, options.lifetime),
                 funding_wallet_address,
                 network_id: Some(options.network_id),
+                owner: get_environment_owner(options.owner.clone()),
                 region: options.region.clone(),
                 rewards_address: Some(options.rewards_address.clone()),
             },
@@ -11,11 +11,38 @@ use std::{net::Ipv4Addr, str::FromStr};
 
 pub const DIGITAL_OCEAN_API_BASE_URL: &str = "https://api.digitalocean.com";
 pub const DIGITAL_OCEAN_API_PAGE_SIZE: usize = 200;
+/// The Terraform workspace that always exists and doesn't belong to any environment.
+pub const DEFAULT_WORKSPACE: &str = "default";
+/// The prefix of the droplet tag that names the environment the droplet belongs to.
+pub const ENVIRONMENT_TAG_PREFIX: &str = "environment:";
 
 pub struct Droplet {
     pub id: usize,
     pub name: String,
     pub ip_address: Ipv4Addr,
+    /// The time the droplet was created, in RFC 3339 format.
+    pub created_at: String,
+    pub size_slug: String,
+    pub tags: Vec<String>,
+    pub volume_ids: Vec<String>,
+}
+
+pub struct DropletSize {
+    pub slug: String,
+    /// The price in US dollars.
+    pub price_hourly: f64,
+    /// The price in US dollars.
+    pub price_monthly: f64,
+}
+
+pub struct Volume {
+    pub id: String,
+    pub name: String,
+    /// The time the volume was created, in RFC 3339 format.
+    pub created_at: String,
+    pub size_gigabytes: u64,
+    /// The IDs of the droplets the volume is attached to. It is empty for a detached volume.
+    pub droplet_ids: Vec<usize>,
 }
 
 pub struct DigitalOceanClient {
@@ -25,6 +52,17 @@ pub struct DigitalOceanClient {
 }
 
 impl DigitalOceanClient {
+    /// Create a client for the API, using the access token in the `DO_PAT` variable.
+    pub fn from_env() -> Result<Self> {
+        let access_token = std::env::var("DO_PAT")
+            .map_err(|_| Error::CloudProviderCredentialsNotSupplied("DO_PAT".to_string()))?;
+        Ok(Self {
+            base_url: DIGITAL_OCEAN_API_BASE_URL.to_string(),
+            access_token,
+            page_size: DIGITAL_OCEAN_API_PAGE_SIZE,
+        })
+    }
+
     pub async fn list_droplets(&self, skip_if_no_ip: bool) -> Result<Vec<Droplet>> {
         let client = Client::new();
         let mut has_next_page = true;
@@ -71,6 +109,16 @@ impl DigitalOceanClient {
                     .as_str()
                     .ok_or(Error::MalformedDigitalOceanApiRespose("name".to_string()))?
                     .to_string();
+                let created_at = droplet_json["created_at"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                let size_slug = droplet_json["size_slug"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string();
+                let tags = get_string_array(&droplet_json["tags"]);
+                let volume_ids = get_string_array(&droplet_json["volume_ids"]);
                 let ip_address_array = droplet_json["networks"]["v4"].as_array().ok_or(
                     Error::MalformedDigitalOceanApiRespose("droplets".to_string()),
                 )?;
@@ -96,6 +144,10 @@ impl DigitalOceanClient {
                             id: id as usize,
                             name,
                             ip_address,
+                            created_at,
+                            size_slug,
+                            tags,
+                            volume_ids,
                         });
                     }
                     Err(_) if skip_if_no_ip => continue,
@@ -123,6 +175,169 @@ impl DigitalOceanClient {
 
         Ok(droplets)
     }
+
+    pub async fn list_volumes(&self) -> Result<Vec<Volume>> {
+        let client = Client::new();
+        let mut has_next_page = true;
+        let mut page = 1;
+        let mut volumes = Vec::new();
+        while has_next_page {
+            let url = format!(
+                "{}/v2/volumes?page={}&per_page={}",
+                self.base_url, page, self.page_size
+            );
+            debug!("Executing volume list request with {url}");
+            let response = client
+                .get(url)
+                .header("Authorization", format!("Bearer {}", self.access_token))
+                .send()
+                .await?;
+            let response = check_response(response).await?;
+
+            let json: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+            let volume_array =
+                json["volumes"]
+                    .as_array()
+                    .ok_or(Error::MalformedDigitalOceanApiRespose(
+                        "volumes".to_string(),
+                    ))?;
+            for volume_json in volume_array {
+                let id = volume_json["id"]
+                    .as_str()
+                    .ok_or(Error::MalformedDigitalOceanApiRespose("id".to_string()))?
+                    .to_string();
+                let name = volume_json["name"]
+                    .as_str()
+                    .ok_or(Error::MalformedDigitalOceanApiRespose("name".to_string()))?
+                    .to_string();
+                let droplet_ids = volume_json["droplet_ids"]
+                    .as_array()
+                    .map(|ids| {
+                        ids.iter()
+                            .filter_map(|id| id.as_u64().map(|id| id as usize))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                volumes.push(Volume {
+                    id,
+                    name,
+                    created_at: volume_json["created_at"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                    size_gigabytes: volume_json["size_gigabytes"].as_u64().unwrap_or_default(),
+                    droplet_ids,
+                });
+            }
+
+            has_next_page = json["links"]["pages"]
+                .as_object()
+                .is_some_and(|pages| pages.contains_key("next"));
+            page += 1;
+        }
+
+        Ok(volumes)
+    }
+
+    /// List the droplet sizes, along with their prices.
+    pub async fn list_sizes(&self) -> Result<Vec<DropletSize>> {
+        let client = Client::new();
+        let mut has_next_page = true;
+        let mut page = 1;
+        let mut sizes = Vec::new();
+        while has_next_page {
+            let url = format!(
+                "{}/v2/sizes?page={}&per_page={}",
+                self.base_url, page, self.page_size
+            );
+            debug!("Executing size list request with {url}");
+            let response = client
+                .get(url)
+                .header("Authorization", format!("Bearer {}", self.access_token))
+                .send()
+                .await?;
+            let response = check_response(response).await?;
+
+            let json: serde_json::Value = serde_json::from_str(&response.text().await?)?;
+            let size_array = json["sizes"]
+                .as_array()
+                .ok_or(Error::MalformedDigitalOceanApiRespose("sizes".to_string()))?;
+            for size_json in size_array {
+                let slug = size_json["slug"]
+                    .as_str()
+                    .ok_or(Error::MalformedDigitalOceanApiRespose("slug".to_string()))?
+                    .to_string();
+                let price_hourly = size_json["price_hourly"].as_f64().ok_or(
+                    Error::MalformedDigitalOceanApiRespose("price_hourly".to_string()),
+                )?;
+                let price_monthly = size_json["price_monthly"].as_f64().ok_or(
+                    Error::MalformedDigitalOceanApiRespose("price_monthly".to_string()),
+                )?;
+                sizes.push(DropletSize {
+                    slug,
+                    price_hourly,
+                    price_monthly,
+                });
+            }
+
+            has_next_page = json["links"]["pages"]
+                .as_object()
+                .is_some_and(|pages| pages.contains_key("next"));
+            page += 1;
+        }
+
+        Ok(sizes)
+    }
+
+    pub async fn delete_droplet(&self, id: usize) -> Result<()> {
+        self.delete(&format!("{}/v2/droplets/{id}", self.base_url))
+            .await
+    }
+
+    pub async fn delete_volume(&self, id: &str) -> Result<()> {
+        self.delete(&format!("{}/v2/volumes/{id}", self.base_url))
+            .await
+    }
+
+    async fn delete(&self, url: &str) -> Result<()> {
+        debug!("Executing delete request with {url}");
+        let response = Client::new()
+            .delete(url)
+            .header("Authorization", format!("Bearer {}", self.access_token))
+            .send()
+            .await?;
+        check_response(response).await?;
+        Ok(())
+    }
+}
+
+async fn check_response(response: reqwest::Response) -> Result<reqwest::Response> {
+    if response.status().as_u16() == 401 {
+        debug!("Error response body: {}", response.text().await?);
+        return Err(Error::DigitalOceanUnauthorized);
+    } else if !response.status().is_success() {
+        let status_code = response.status().as_u16();
+        let response_body = response.text().await?;
+        debug!("Response status code: {status_code}");
+        debug!("Error response body: {response_body}");
+        return Err(Error::DigitalOceanUnexpectedResponse(
+            status_code,
+            response_body,
+        ));
+    }
+    Ok(response)
+}
+
+fn get_string_array(value: &serde_json::Value) -> Vec<String> {
+    value
+        .as_array()
+        .map(|values| {
+            values
+                .iter()
+                .filter_map(|value| value.as_str().map(|value| value.to_string()))
+                .collect()
+        })
+        .unwrap_or_default()
 }
 
 #[cfg(test)]
@@ -543,6 +758,79 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_list_volumes_with_single_page() -> Result<()> {
+        const MOCK_API_RESPONSE: &str = r#"
+        {
+          "volumes": [
+            {
+              "id": "506f78a4-e098-11e5-ad9f-000f53306ae1",
+              "region": {
+                "name": "London 1",
+                "slug": "lon1"
+              },
+              "droplet_ids": [
+                118019015
+              ],
+              "name": "testnet-node-1-volume-1",
+              "description": "",
+              "size_gigabytes": 90,
+              "created_at": "2024-11-05T13:57:25Z",
+              "filesystem_type": "",
+              "filesystem_label": "",
+              "tags": []
+            },
+            {
+              "id": "2d2967ff-491d-11e6-860c-000f53315870",
+              "region": {
+                "name": "London 1",
+                "slug": "lon1"
+              },
+              "droplet_ids": [],
+              "name": "testnet-node-2-volume-1",
+              "description": "",
+              "size_gigabytes": 90,
+              "created_at": "2024-11-05T13:57:25Z",
+              "filesystem_type": "",
+              "filesystem_label": "",
+              "tags": []
+            }
+          ],
+          "links": {},
+          "meta": {
+            "total": 2
+          }
+        }
+        "#;
+
+        let server = MockServer::start();
+        let list_volumes_mock = server.mock(|when, then| {
+            when.method(GET).path("/v2/volumes");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .body(MOCK_API_RESPONSE);
+        });
+
+        let client = DigitalOceanClient {
+            base_url: server.base_url(),
+            access_token: String::from("fake_token"),
+            page_size: DIGITAL_OCEAN_API_PAGE_SIZE,
+        };
+
+        let volumes = client.list_volumes().await?;
+
+        assert_eq!(2, volumes.len());
+        assert_eq!("506f78a4-e098-11e5-ad9f-000f53306ae1", volumes[0].id);
+        assert_eq!("testnet-node-1-volume-1", volumes[0].name);
+        assert_eq!(90, volumes[0].size_gigabytes);
+        assert_eq!(vec![118019015], volumes[0].droplet_ids);
+        assert!(volumes[1].droplet_ids.is_empty());
+
+        list_volumes_mock.assert();
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_list_droplets_with_paged_response() -> Result<()> {
         const MOCK_API_PAGE_1_RESPONSE: &str = r#"
@@ -0,0 +1,339 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crate::{
+    ansible::inventory::AnsibleInventoryType,
+    digital_ocean::{DigitalOceanClient, Droplet},
+    error::{Error, Result},
+    inventory::VirtualMachine,
+    terraform::TerraformResource,
+    CloudProvider, TestnetDeployer,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    net::IpAddr,
+};
+
+const DROPLET_RESOURCE_TYPE: &str = "digitalocean_droplet";
+const VOLUME_RESOURCE_TYPE: &str = "digitalocean_volume";
+const VOLUME_ATTACHMENT_RESOURCE_TYPE: &str = "digitalocean_volume_attachment";
+
+// The inventories that cover every VM in an environment. The static inventories are excluded
+// because they are generated from the others.
+const INVENTORY_TYPES: [AnsibleInventoryType; 13] = [
+    AnsibleInventoryType::Build,
+    AnsibleInventoryType::Clients,
+    AnsibleInventoryType::EvmNodes,
+    AnsibleInventoryType::FullConeNatGateway,
+    AnsibleInventoryType::FullConePrivateNodes,
+    AnsibleInventoryType::Genesis,
+    AnsibleInventoryType::Nodes,
+    AnsibleInventoryType::PeerCacheNodes,
+    AnsibleInventoryType::PortRestrictedConeNatGateway,
+    AnsibleInventoryType::PortRestrictedConePrivateNodes,
+    AnsibleInventoryType::SymmetricNatGateway,
+    AnsibleInventoryType::SymmetricPrivateNodes,
+    AnsibleInventoryType::Upnp,
+];
+
+/// The public IP address of a VM, as seen by each view. A view that doesn't know about the VM has
+/// no address for it.
+#[derive(Clone, Debug, Serialize)]
+pub struct IpMismatch {
+    pub vm_name: String,
+    pub terraform: Option<IpAddr>,
+    pub cloud: Option<IpAddr>,
+    pub inventory: Option<IpAddr>,
+}
+
+/// A volume that Terraform attached to a VM, but which is not attached to it in the cloud.
+#[derive(Clone, Debug, Serialize)]
+pub struct MissingVolume {
+    pub vm_name: String,
+    pub volume_id: String,
+    pub volume_name: Option<String>,
+}
+
+/// The differences between the Terraform state, the VMs in the cloud provider, and the Ansible
+/// inventory for an environment.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DriftReport {
+    pub environment_name: String,
+    /// VMs that exist in the cloud but not in the Terraform state.
+    pub cloud_only: Vec<String>,
+    /// VMs in the Terraform state that no longer exist in the cloud.
+    pub state_only: Vec<String>,
+    /// VMs that exist in the cloud but are not returned by any Ansible inventory, e.g., because
+    /// their tags are wrong.
+    pub missing_from_inventory: Vec<String>,
+    /// VMs returned by an Ansible inventory that don't exist in the cloud.
+    pub inventory_only: Vec<String>,
+    pub ip_mismatches: Vec<IpMismatch>,
+    pub missing_volumes: Vec<MissingVolume>,
+}
+
+impl DriftReport {
+    pub fn has_drift(&self) -> bool {
+        !self.cloud_only.is_empty()
+            || !self.state_only.is_empty()
+            || !self.missing_from_inventory.is_empty()
+            || !self.inventory_only.is_empty()
+            || !self.ip_mismatches.is_empty()
+            || !self.missing_volumes.is_empty()
+    }
+
+    pub fn print_report(&self) {
+        println!("==== Drift Report for {} ====", self.environment_name);
+        if !self.has_drift() {
+            println!("The Terraform state, cloud provider and Ansible inventory all agree");
+            return;
+        }
+
+        let print_vms = |heading: &str, vms: &[String]| {
+            if vms.is_empty() {
+                return;
+            }
+            println!("{heading} ({}):", vms.len());
+            for vm in vms {
+                println!("  {vm}");
+            }
+        };
+        print_vms("In the cloud but not in Terraform state", &self.cloud_only);
+        print_vms("In Terraform state but not in the cloud", &self.state_only);
+        print_vms(
+            "In the cloud but not in the Ansible inventory",
+            &self.missing_from_inventory,
+        );
+        print_vms(
+            "In the Ansible inventory but not in the cloud",
+            &self.inventory_only,
+        );
+
+        if !self.ip_mismatches.is_empty() {
+            println!("IP address mismatches ({}):", self.ip_mismatches.len());
+            let format_ip =
+                |ip: &Option<IpAddr>| ip.map(|ip| ip.to_string()).unwrap_or("-".to_string());
+            for mismatch in self.ip_mismatches.iter() {
+                println!(
+                    "  {}: terraform {}, cloud {}, inventory {}",
+                    mismatch.vm_name,
+                    format_ip(&mismatch.terraform),
+                    format_ip(&mismatch.cloud),
+                    format_ip(&mismatch.inventory)
+                );
+            }
+        }
+
+        if !self.missing_volumes.is_empty() {
+            println!(
+                "Attached volumes missing in the cloud ({}):",
+                self.missing_volumes.len()
+            );
+            for volume in self.missing_volumes.iter() {
+                println!(
+                    "  {}: {}",
+                    volume.vm_name,
+                    volume.volume_name.as_deref().unwrap_or(&volume.volume_id)
+                );
+            }
+        }
+    }
+}
+
+// A VM as it is recorded in the Terraform state.
+struct StateVm {
+    id: String,
+    public_ip: Option<IpAddr>,
+}
+
+impl TestnetDeployer {
+    /// Compare the Terraform state, the droplets in Digital Ocean and the Ansible inventory for an
+    /// environment.
+    ///
+    /// The Ansible inventory must have been set up for the environment before this is called.
+    pub async fn detect_drift(&self, name: &str) -> Result<DriftReport> {
+        if !matches!(self.cloud_provider, CloudProvider::DigitalOcean) {
+            return Err(Error::CloudProviderNotSupported(
+                self.cloud_provider.to_string(),
+            ));
+        }
+        let environments = self.terraform_runner.workspace_list()?;
+        if !environments.contains(&name.to_string()) {
+            return Err(Error::EnvironmentDoesNotExist(name.to_string()));
+        }
+
+        eprintln!("Retrieving Terraform state...");
+        let resources = self.terraform_runner.show(name)?;
+        let state_vms = get_state_vms(&resources);
+
+        eprintln!("Retrieving droplets...");
+        let client = DigitalOceanClient::from_env()?;
+        // Other environments can have names that begin with this one, e.g., 'alpha' and
+        // 'alpha-2', so their droplets must be excluded.
+        let other_prefixes = environments
+            .iter()
+            .filter(|env| env.as_str() != name && env.starts_with(&format!("{name}-")))
+            .map(|env| format!("{env}-"))
+            .collect::<Vec<_>>();
+        let droplets = client
+            .list_droplets(true)
+            .await?
+            .into_iter()
+            .filter(|droplet| {
+                droplet.name.starts_with(&format!("{name}-"))
+                    && !other_prefixes
+                        .iter()
+                        .any(|prefix| droplet.name.starts_with(prefix))
+            })
+            .map(|droplet| (droplet.name.clone(), droplet))
+            .collect::<BTreeMap<_, _>>();
+
+        eprintln!("Retrieving Ansible inventory...");
+        let mut inventory: HashMap<String, VirtualMachine> = HashMap::new();
+        for inventory_type in INVENTORY_TYPES {
+            let vms = self
+                .ansible_provisioner
+                .ansible_runner
+                .get_inventory(inventory_type, false)?;
+            inventory.extend(vms.into_iter().map(|vm| (vm.name.clone(), vm)));
+        }
+
+        let mut report = DriftReport {
+            environment_name: name.to_string(),
+            ..Default::default()
+        };
+        report.cloud_only = droplets
+            .keys()
+            .filter(|name| !state_vms.contains_key(*name))
+            .cloned()
+            .collect();
+        report.state_only = state_vms
+            .keys()
+            .filter(|name| !droplets.contains_key(*name))
+            .cloned()
+            .collect();
+        report.missing_from_inventory = droplets
+            .keys()
+            .filter(|name| !inventory.contains_key(*name))
+            .cloned()
+            .collect();
+        let mut inventory_only = inventory
+            .keys()
+            .filter(|name| !droplets.contains_key(*name))
+            .cloned()
+            .collect::<Vec<_>>();
+        inventory_only.sort();
+        report.inventory_only = inventory_only;
+
+        let all_vm_names = state_vms
+            .keys()
+            .chain(droplets.keys())
+            .chain(inventory.keys())
+            .collect::<BTreeSet<_>>();
+        for vm_name in all_vm_names {
+            let terraform = state_vms.get(vm_name).and_then(|vm| vm.public_ip);
+            let cloud = droplets
+                .get(vm_name)
+                .map(|droplet| IpAddr::V4(droplet.ip_address));
+            let inventory = inventory.get(vm_name).map(|vm| vm.public_ip_addr);
+            let ips = [terraform, cloud, inventory]
+                .into_iter()
+                .flatten()
+                .collect::<BTreeSet<_>>();
+            if ips.len() > 1 {
+                report.ip_mismatches.push(IpMismatch {
+                    vm_name: vm_name.clone(),
+                    terraform,
+                    cloud,
+                    inventory,
+                });
+            }
+        }
+
+        report.missing_volumes = get_missing_volumes(&resources, &state_vms, &droplets);
+        Ok(report)
+    }
+}
+
+fn get_string_value(resource: &TerraformResource, key: &str) -> Option<String> {
+    resource
+        .values
+        .get(key)
+        .and_then(|value| value.as_str())
+        .map(|value| value.to_string())
+}
+
+fn get_state_vms(resources: &[TerraformResource]) -> BTreeMap<String, StateVm> {
+    resources
+        .iter()
+        .filter(|resource| resource.resource_type == DROPLET_RESOURCE_TYPE)
+        .filter_map(|resource| {
+            let name = get_string_value(resource, "name")?;
+            let vm = StateVm {
+                id: get_string_value(resource, "id").unwrap_or_default(),
+                public_ip: get_string_value(resource, "ipv4_address")
+                    .and_then(|ip| ip.parse().ok()),
+            };
+            Some((name, vm))
+        })
+        .collect()
+}
+
+// Each volume attachment in the state should have a corresponding volume ID on the droplet.
+fn get_missing_volumes(
+    resources: &[TerraformResource],
+    state_vms: &BTreeMap<String, StateVm>,
+    droplets: &BTreeMap<String, Droplet>,
+) -> Vec<MissingVolume> {
+    let volume_names = resources
+        .iter()
+        .filter(|resource| resource.resource_type == VOLUME_RESOURCE_TYPE)
+        .filter_map(|resource| {
+            Some((
+                get_string_value(resource, "id")?,
+                get_string_value(resource, "name")?,
+            ))
+        })
+        .collect::<HashMap<_, _>>();
+    let vm_names_by_id = state_vms
+        .iter()
+        .map(|(name, vm)| (vm.id.clone(), name.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let mut missing_volumes = Vec::new();
+    for attachment in resources
+        .iter()
+        .filter(|resource| resource.resource_type == VOLUME_ATTACHMENT_RESOURCE_TYPE)
+    {
+        let Some(volume_id) = get_string_value(attachment, "volume_id") else {
+            continue;
+        };
+        // The droplet ID is a number in the attachment, but a string in the droplet resource.
+        let Some(droplet_id) = attachment.values.get("droplet_id").map(|id| match id {
+            serde_json::Value::String(id) => id.clone(),
+            id => id.to_string(),
+        }) else {
+            continue;
+        };
+        let Some(vm_name) = vm_names_by_id.get(&droplet_id) else {
+            continue;
+        };
+        // A droplet that is missing entirely is already reported.
+        let Some(droplet) = droplets.get(vm_name) else {
+            continue;
+        };
+        if !droplet.volume_ids.contains(&volume_id) {
+            missing_volumes.push(MissingVolume {
+                vm_name: vm_name.clone(),
+                volume_name: volume_names.get(&volume_id).cloned(),
+                volume_id,
+            });
+        }
+    }
+    missing_volumes.sort_by(|a, b| a.vm_name.cmp(&b.vm_name));
+    missing_volumes
+}
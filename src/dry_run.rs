@@ -0,0 +1,145 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crate::secrets;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+// The dry run applies to the whole process, because the runners and clients that would make the
+// changes are created in many places, rather than being passed down from the command.
+static DRY_RUN_ENABLED: AtomicBool = AtomicBool::new(false);
+static PLAN: Mutex<Vec<PlannedAction>> = Mutex::new(Vec::new());
+
+/// An action that would have changed an environment, which was recorded rather than run.
+///
+/// Actions that only read state, such as `terraform show` or retrieving the Ansible inventory,
+/// still run during a dry run, so that the plan reflects the current state of the environment.
+#[derive(Clone, Debug)]
+pub enum PlannedAction {
+    AnsiblePlaybook {
+        playbook: String,
+        inventory_type: String,
+        extra_vars: Option<String>,
+        command: String,
+    },
+    ObjectDelete {
+        url: String,
+    },
+    ObjectUpload {
+        source: String,
+        url: String,
+    },
+    Ssh {
+        host: String,
+        command: String,
+    },
+    /// The command includes the Terraform vars.
+    Terraform {
+        command: String,
+    },
+    /// The amounts are in the smallest unit of the token and gas.
+    Transfer {
+        from: String,
+        to: String,
+        token_amount: String,
+        gas_amount: String,
+    },
+}
+
+impl std::fmt::Display for PlannedAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlannedAction::AnsiblePlaybook {
+                playbook,
+                inventory_type,
+                extra_vars,
+                command,
+            } => {
+                write!(
+                    f,
+                    "[ansible] Run {playbook} on the {inventory_type} inventory"
+                )?;
+                if let Some(extra_vars) = extra_vars {
+                    write!(f, "\n   Extra vars: {extra_vars}")?;
+                }
+                write!(f, "\n   Command: {command}")
+            }
+            PlannedAction::ObjectDelete { url } => write!(f, "[object store] Delete {url}"),
+            PlannedAction::ObjectUpload { source, url } => {
+                write!(f, "[object store] Upload {source} to {url}")
+            }
+            PlannedAction::Ssh { host, command } => write!(f, "[ssh] {host}: {command}"),
+            PlannedAction::Terraform { command } => write!(f, "[terraform] {command}"),
+            PlannedAction::Transfer {
+                from,
+                to,
+                token_amount,
+                gas_amount,
+            } => write!(
+                f,
+                "[evm] Transfer {token_amount} tokens and {gas_amount} gas from {from} to {to}"
+            ),
+        }
+    }
+}
+
+pub fn enable() {
+    DRY_RUN_ENABLED.store(true, Ordering::SeqCst);
+}
+
+pub fn is_enabled() -> bool {
+    DRY_RUN_ENABLED.load(Ordering::SeqCst)
+}
+
+/// Add an action to the plan. The action is also printed, so it's clear where it would have run
+/// in relation to the rest of the output.
+pub fn record(action: PlannedAction) {
+    println!(
+        "Dry run: skipping {}",
+        action.to_string().lines().next().unwrap_or_default()
+    );
+    PLAN.lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push(action);
+}
+
+/// Get the actions that have been recorded, in the order they would have run.
+pub fn get_plan() -> Vec<PlannedAction> {
+    PLAN.lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+pub fn print_plan() {
+    let plan = get_plan();
+    println!("==== Dry Run Plan ====");
+    if plan.is_empty() {
+        println!("No changes would be made");
+        return;
+    }
+    for (i, action) in plan.iter().enumerate() {
+        println!("{}. {action}", i + 1);
+    }
+    println!("{} actions would be run", plan.len());
+}
+
+/// Format a command and its arguments as a single line that could be pasted into a shell.
+///
+/// Any registered secrets are redacted.
+pub fn format_command(binary: &str, args: &[String]) -> String {
+    let mut command = binary.to_string();
+    for arg in args {
+        command.push(' ');
+        if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || "\"'{}$*".contains(c)) {
+            command.push_str(&format!("'{}'", arg.replace('\'', r"'\''")));
+        } else {
+            command.push_str(arg);
+        }
+    }
+    secrets::redact(&command)
+}
@@ -0,0 +1,416 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crate::{
+    clients::ClientsDeployBuilder,
+    digital_ocean::{DigitalOceanClient, DEFAULT_WORKSPACE, ENVIRONMENT_TAG_PREFIX},
+    error::{Error, Result},
+    get_environment_details,
+    inventory::{get_data_directory, ClientsDeploymentInventory, DeploymentInventory},
+    lock::EnvironmentLockGuard,
+    object_store::{get_object_store, Bucket},
+    policy::EnvironmentPolicy,
+    post_slack_message,
+    terraform::get_environment_terraform_runners,
+    BinaryOption, CloudProvider, DeploymentType, EnvironmentType, TestnetDeployBuilder,
+};
+use chrono::Utc;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+    time::Duration,
+};
+
+/// An environment, described by whichever of its Terraform workspace, environment details and
+/// cached inventory are available.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct EnvironmentSummary {
+    pub name: String,
+    /// The Terraform directories that have a workspace for the environment, i.e., 'testnet' or
+    /// 'clients'.
+    pub terraform_directories: Vec<String>,
+    pub environment_type: Option<String>,
+    pub deployment_type: Option<String>,
+    pub region: Option<String>,
+    pub evm_network: Option<String>,
+    /// The VM and node counts are only available if the inventory is cached on this machine.
+    pub vm_count: Option<usize>,
+    pub node_count: Option<usize>,
+    pub binary_versions: Option<String>,
+    /// The time the environment was deployed, or for older environments, the time the oldest
+    /// droplet was created, in RFC 3339 format.
+    pub created_at: Option<String>,
+    pub owner: Option<String>,
+    pub expires_at: Option<String>,
+}
+
+/// List every environment that has a Terraform workspace or environment details.
+///
+/// Environments deployed by older versions have no creation time in their details, so it's taken
+/// from their droplets, which is only possible for environments on Digital Ocean.
+pub async fn list_environments(provider: CloudProvider) -> Result<Vec<EnvironmentSummary>> {
+    let mut workspaces: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (directory, runner) in get_environment_terraform_runners(provider)? {
+        eprintln!("Retrieving {directory} workspaces...");
+        runner.init()?;
+        for workspace in runner.workspace_list()? {
+            if workspace != DEFAULT_WORKSPACE {
+                workspaces
+                    .entry(workspace)
+                    .or_default()
+                    .push(directory.clone());
+            }
+        }
+    }
+
+    eprintln!("Retrieving environment details...");
+    let object_store = get_object_store()?;
+    let details_names = object_store
+        .list_object_keys(Bucket::EnvironmentDetails, "")
+        .await?
+        .into_iter()
+        .collect::<BTreeSet<_>>();
+    let mut environments = workspaces
+        .keys()
+        .chain(details_names.iter())
+        .map(|name| {
+            let summary = EnvironmentSummary {
+                name: name.clone(),
+                ..Default::default()
+            };
+            (name.clone(), summary)
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    for summary in environments.values_mut() {
+        if let Some(directories) = workspaces.get(&summary.name) {
+            summary.terraform_directories = directories.clone();
+        }
+        if details_names.contains(&summary.name) {
+            match get_environment_details(&summary.name, &object_store).await {
+                Ok(details) => {
+                    summary.environment_type = Some(details.environment_type.to_string());
+                    summary.deployment_type = Some(details.deployment_type.to_string());
+                    summary.region = Some(details.region.clone());
+                    summary.evm_network = Some(details.evm_details.network.to_string());
+                    summary.created_at = details.created_at;
+                    summary.owner = details.owner;
+                    summary.expires_at = details.expires_at;
+                }
+                Err(err) => {
+                    eprintln!(
+                        "Failed to retrieve environment details for {}: {err}",
+                        summary.name
+                    );
+                }
+            }
+        }
+        apply_cached_inventory(summary)?;
+    }
+
+    if matches!(provider, CloudProvider::DigitalOcean) {
+        eprintln!("Retrieving droplets...");
+        let client = DigitalOceanClient::from_env()?;
+        let mut droplet_created_at: BTreeMap<String, String> = BTreeMap::new();
+        for droplet in client.list_droplets(true).await? {
+            let Some(name) = droplet
+                .tags
+                .iter()
+                .find_map(|tag| tag.strip_prefix(ENVIRONMENT_TAG_PREFIX))
+            else {
+                continue;
+            };
+            // The timestamps are all RFC 3339 in UTC, so they order correctly as strings.
+            let created_at = droplet_created_at.entry(name.to_string()).or_default();
+            if created_at.is_empty() || droplet.created_at < *created_at {
+                *created_at = droplet.created_at.clone();
+            }
+        }
+        for (name, created_at) in droplet_created_at {
+            if let Some(summary) = environments.get_mut(&name) {
+                summary.created_at.get_or_insert(created_at);
+            }
+        }
+    }
+
+    Ok(environments.into_values().collect())
+}
+
+/// An environment whose expiry time has passed.
+#[derive(Clone, Debug, Serialize)]
+pub struct ExpiredEnvironment {
+    pub name: String,
+    pub deployment_type: DeploymentType,
+    pub environment_type: EnvironmentType,
+    pub owner: Option<String>,
+    /// The expiry time, in RFC 3339 format.
+    pub expires_at: String,
+    /// The time the environment will be reaped, which is the end of the grace period, in RFC
+    /// 3339 format.
+    pub reap_at: String,
+    /// The grace period has elapsed, so the environment will be cleaned.
+    pub past_grace_period: bool,
+}
+
+/// Find the Development and Staging environments whose expiry time has passed.
+///
+/// Production environments are only included if `include_production` is set. Environments
+/// without an expiry time never expire.
+pub async fn find_expired_environments(
+    grace_period: Duration,
+    include_production: bool,
+) -> Result<Vec<ExpiredEnvironment>> {
+    let grace_period = chrono::Duration::from_std(grace_period)
+        .map_err(|_| Error::InvalidGracePeriod(format!("{grace_period:?}")))?;
+    let now = Utc::now();
+
+    eprintln!("Retrieving environment details...");
+    let object_store = get_object_store()?;
+    let names = object_store
+        .list_object_keys(Bucket::EnvironmentDetails, "")
+        .await?;
+    let mut expired = Vec::new();
+    for name in names {
+        let details = match get_environment_details(&name, &object_store).await {
+            Ok(details) => details,
+            Err(err) => {
+                eprintln!("Failed to retrieve environment details for {name}: {err}");
+                continue;
+            }
+        };
+        let Some(expires_at) = details.get_expiry_time() else {
+            continue;
+        };
+        if expires_at > now {
+            continue;
+        }
+        if details.environment_type == EnvironmentType::Production && !include_production {
+            eprintln!("Skipping the expired production environment {name}");
+            continue;
+        }
+        let reap_at = expires_at + grace_period;
+        expired.push(ExpiredEnvironment {
+            name,
+            deployment_type: details.deployment_type,
+            environment_type: details.environment_type,
+            owner: details.owner,
+            expires_at: expires_at.to_rfc3339(),
+            reap_at: reap_at.to_rfc3339(),
+            past_grace_period: reap_at <= now,
+        });
+    }
+    Ok(expired)
+}
+
+/// Post a message to Slack that tells the owners which of their environments have expired, and
+/// when they will be, or are being, reaped.
+pub async fn notify_expired_environment_owners(expired: &[ExpiredEnvironment]) -> Result<()> {
+    let mut message = String::new();
+    message.push_str("*Expired Environments*\n");
+    for environment in expired.iter() {
+        let owner = environment.owner.as_deref().unwrap_or("unknown owner");
+        if environment.past_grace_period {
+            message.push_str(&format!(
+                "{} ({}, {owner}): expired at {} and is being removed\n",
+                environment.name, environment.environment_type, environment.expires_at
+            ));
+        } else {
+            message.push_str(&format!(
+                "{} ({}, {owner}): expired at {} and will be removed after {}\n",
+                environment.name,
+                environment.environment_type,
+                environment.expires_at,
+                environment.reap_at
+            ));
+        }
+    }
+    post_slack_message(&message).await
+}
+
+/// Clean each of the environments, using the same process as the `clean` command for its
+/// deployment type.
+///
+/// An environment that is locked by another command is not cleaned.
+///
+/// A failure to clean one environment doesn't prevent the others from being cleaned.
+pub async fn reap_environments(
+    expired: &[ExpiredEnvironment],
+    provider: CloudProvider,
+) -> Result<()> {
+    let mut failed = Vec::new();
+    for environment in expired.iter() {
+        println!("Reaping {}...", environment.name);
+        let lock = match EnvironmentLockGuard::acquire(&environment.name, "reap").await {
+            Ok(lock) => lock,
+            Err(err) => {
+                println!("Failed to reap {}: {err}", environment.name);
+                failed.push(environment.name.clone());
+                continue;
+            }
+        };
+        let result = match environment.deployment_type {
+            DeploymentType::Client => match ClientsDeployBuilder::default()
+                .environment_name(&environment.name)
+                .provider(provider)
+                .build()
+            {
+                Ok(deployer) => {
+                    let policy =
+                        EnvironmentPolicy::for_environment_type(&environment.environment_type);
+                    deployer.clean(policy.should_drain_funds(false)).await
+                }
+                Err(err) => Err(err),
+            },
+            DeploymentType::Bootstrap | DeploymentType::New => {
+                match TestnetDeployBuilder::default()
+                    .environment_name(&environment.name)
+                    .provider(provider)
+                    .build()
+                {
+                    Ok(deployer) => {
+                        let policy =
+                            EnvironmentPolicy::for_environment_type(&environment.environment_type);
+                        deployer.clean(policy.should_drain_funds(false)).await
+                    }
+                    Err(err) => Err(err),
+                }
+            }
+        };
+        if let Err(err) = lock.release().await {
+            println!("Failed to release the lock on {}: {err}", environment.name);
+        }
+        if let Err(err) = result {
+            println!("Failed to reap {}: {err}", environment.name);
+            failed.push(environment.name.clone());
+        }
+    }
+    if !failed.is_empty() {
+        return Err(Error::EnvironmentReapFailed(failed.join(", ")));
+    }
+    Ok(())
+}
+
+pub fn print_expired_environments(expired: &[ExpiredEnvironment]) {
+    println!("==== Expired Environments ====");
+    if expired.is_empty() {
+        println!("No environments have expired");
+        return;
+    }
+    for environment in expired.iter() {
+        println!(
+            "{} ({}, owner {}): expired at {}, {}",
+            environment.name,
+            environment.environment_type,
+            environment.owner.as_deref().unwrap_or("-"),
+            environment.expires_at,
+            if environment.past_grace_period {
+                "past the grace period".to_string()
+            } else {
+                format!("will be reaped after {}", environment.reap_at)
+            }
+        );
+    }
+}
+
+pub fn print_environment_list(environments: &[EnvironmentSummary]) {
+    println!("==== Environments ====");
+    if environments.is_empty() {
+        println!("No environments were found");
+        return;
+    }
+    println!(
+        "{:<24} {:<12} {:<10} {:<6} {:<18} {:>5} {:>6} {:<26} {:<16} Binaries",
+        "Environment",
+        "Type",
+        "Deployment",
+        "Region",
+        "EVM network",
+        "VMs",
+        "Nodes",
+        "Created",
+        "Owner"
+    );
+    let format_count = |count: Option<usize>| {
+        count
+            .map(|count| count.to_string())
+            .unwrap_or("-".to_string())
+    };
+    for environment in environments.iter() {
+        println!(
+            "{:<24} {:<12} {:<10} {:<6} {:<18} {:>5} {:>6} {:<26} {:<16} {}",
+            environment.name,
+            environment.environment_type.as_deref().unwrap_or("-"),
+            environment.deployment_type.as_deref().unwrap_or("-"),
+            environment.region.as_deref().unwrap_or("-"),
+            environment.evm_network.as_deref().unwrap_or("-"),
+            format_count(environment.vm_count),
+            format_count(environment.node_count),
+            environment.created_at.as_deref().unwrap_or("-"),
+            environment.owner.as_deref().unwrap_or("-"),
+            environment.binary_versions.as_deref().unwrap_or("-")
+        );
+    }
+}
+
+// A testnet inventory takes precedence over a clients inventory with the same name, because only
+// one of them would have been created by the environment's deployment type.
+fn apply_cached_inventory(summary: &mut EnvironmentSummary) -> Result<()> {
+    let data_dir = get_data_directory().map_err(|_| Error::CouldNotRetrieveDataDirectory)?;
+
+    let inventory_path = data_dir.join(format!("{}-inventory.json", summary.name));
+    if let Some(inventory) = read_cached_inventory::<DeploymentInventory>(&inventory_path) {
+        summary.vm_count = Some(inventory.vm_list().len());
+        summary.node_count = Some(
+            inventory
+                .node_vm_list()
+                .iter()
+                .map(|node_vm| node_vm.node_count)
+                .sum(),
+        );
+        summary.binary_versions = Some(get_binary_versions(&inventory.binary_option));
+        return Ok(());
+    }
+
+    let inventory_path = data_dir.join(format!("{}-clients-inventory.json", summary.name));
+    if let Some(inventory) = read_cached_inventory::<ClientsDeploymentInventory>(&inventory_path) {
+        summary.vm_count = Some(inventory.vm_list().len());
+        summary.node_count = Some(0);
+        summary.binary_versions = Some(get_binary_versions(&inventory.binary_option));
+    }
+    Ok(())
+}
+
+// An inventory that can't be parsed was written by an older version, so it's treated as missing.
+fn read_cached_inventory<T: serde::de::DeserializeOwned>(path: &Path) -> Option<T> {
+    let data = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+fn get_binary_versions(binary_option: &BinaryOption) -> String {
+    match binary_option {
+        BinaryOption::BuildFromSource {
+            branch, repo_owner, ..
+        } => format!("{repo_owner}/{branch}"),
+        BinaryOption::Versioned {
+            ant_version,
+            antctl_version,
+            antnode_version,
+        } => [
+            ("antnode", antnode_version),
+            ("antctl", antctl_version),
+            ("ant", ant_version),
+        ]
+        .iter()
+        .filter_map(|(binary, version)| {
+            version
+                .as_ref()
+                .map(|version| format!("{binary} {version}"))
+        })
+        .collect::<Vec<_>>()
+        .join(", "),
+    }
+}
@@ -4,7 +4,7 @@
 // This SAFE Network Software is licensed under the BSD-3-Clause license.
 // Please see the LICENSE file for more details.
 
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 
 use crate::{ansible::inventory::AnsibleInventoryType, NodeType};
 use evmlib::contract::network_token;
@@ -22,6 +22,14 @@ pub enum Error {
     AssetContentLengthUndetermined,
     #[error(transparent)]
     AwsS3Error(#[from] Box<aws_sdk_s3::Error>),
+    #[error(
+        "The checksum of the downloaded '{object_key}' is {actual}, but {expected} was expected"
+    )]
+    ChecksumMismatch {
+        object_key: String,
+        expected: String,
+        actual: String,
+    },
     #[error("The {0} environment variable must be set to use your cloud provider")]
     CloudProviderCredentialsNotSupplied(String),
     #[error("The {0} cloud provider is not supported yet")]
@@ -30,20 +38,37 @@ pub enum Error {
     CouldNotRetrieveDataDirectory,
     #[error("Failed to delete '{0}' from '{1}")]
     DeleteS3ObjectError(String, String),
+    #[error("The infrastructure changes for '{0}' would destroy or replace resources and were not confirmed")]
+    DestructiveInfraChangesNotConfirmed(String),
     #[error("Authorization failed for the Digital Ocean API")]
     DigitalOceanUnauthorized,
     #[error("Unexpected response: {0} -- {1}")]
     DigitalOceanUnexpectedResponse(u16, String),
     #[error("The public IP address was not obtainable from the API response")]
     DigitalOceanPublicIpAddressNotFound,
+    #[error("The price for the '{0}' droplet size could not be found")]
+    DropletSizePriceNotFound(String),
     #[error("The provided ansible inventory is empty or does not exists {0}")]
     EmptyInventory(AnsibleInventoryType),
     #[error("Could not retrieve environment details for '{0}'")]
     EnvironmentDetailsNotFound(String),
     #[error("The '{0}' environment does not exist")]
     EnvironmentDoesNotExist(String),
+    #[error("The '{name}' environment is locked by {owner} for the '{command}' command, which started at {started_at}")]
+    EnvironmentLocked {
+        name: String,
+        owner: String,
+        command: String,
+        started_at: String,
+    },
+    #[error("The name of the '{0}' environment was not confirmed")]
+    EnvironmentNameNotConfirmed(String),
     #[error("The environment name is required")]
     EnvironmentNameRequired,
+    #[error("The '{0}' environment is not locked")]
+    EnvironmentNotLocked(String),
+    #[error("Failed to reap these environments: {0}")]
+    EnvironmentReapFailed(String),
     #[error("Could not convert '{0}' to an EnvironmentType variant")]
     EnvironmentNameFromStringError(String),
     #[error("No EVM node found in the inventory")]
@@ -73,8 +98,12 @@ pub enum Error {
     InquireError(#[from] inquire::InquireError),
     #[error("'{0}' is not a valid binary to build")]
     InvalidBinaryName(String),
+    #[error("The grace period '{0}' is too long")]
+    InvalidGracePeriod(String),
     #[error("The node type '{0:?}' is not supported")]
     InvalidNodeType(NodeType),
+    #[error("'{0}' is not a valid secret reference. Use env:NAME, file:PATH, vault:PATH or keyring:[SERVICE/]ACCOUNT")]
+    InvalidSecretReference(String),
     #[error("The number of wallet secret keys ({0}) does not match the number of uploaders ({1})")]
     InvalidWalletCount(usize, usize),
     #[error(
@@ -119,6 +148,8 @@ pub enum Error {
     ListS3ObjectsError { prefix: String, error: String },
     #[error("Could not configure logging: {0}")]
     LoggingConfiguration(String),
+    #[error("The logs for '{0}' were not uploaded as archives, so they cannot be filtered")]
+    LogArchiveManifestNotFound(String),
     #[error("Logs for a '{0}' testnet already exist")]
     LogsForPreviousTestnetExist(String),
     #[error("Logs have not been retrieved for the '{0}' environment.")]
@@ -141,10 +172,24 @@ pub enum Error {
     NoClientError,
     #[error("This deployment does not have a faucet. It may be a bootstrap deployment.")]
     NoFaucetError,
+    #[error(
+        "There are no logs at 'logs/{0}' to upload. Use the 'logs rsync' command to retrieve them first."
+    )]
+    NoLogsToUpload(String),
     #[error("The node count for the provided custom vms are not equal")]
     NodeCountMismatch,
     #[error("Could not obtain a multiaddr from the node inventory")]
     NodeAddressNotFound,
+    #[error("The '{0}' object store is not supported. Use 's3' or 'local'.")]
+    ObjectStoreNotSupported(String),
+    #[error("The operator '{operator}' is not allowed to run destructive commands against the {environment_type} environment '{name}'")]
+    OperatorNotAllowed {
+        operator: String,
+        name: String,
+        environment_type: String,
+    },
+    #[error("Failed to delete {0} orphaned resources. See output for details.")]
+    OrphanedResourceDeletionFailed(usize),
     #[error("Failed to upload {0} to S3 bucket {1}")]
     PutS3ObjectError(String, String),
     #[error(transparent)]
@@ -157,6 +202,8 @@ pub enum Error {
     RepairWalletAddressNotProvided,
     #[error("Routed VM for IP {0} not found")]
     RoutedVmNotFound(IpAddr),
+    #[error("RPC request to {0} failed: {1}")]
+    RpcRequestFailed(SocketAddr, String),
     #[error("Safe client command failed: {0}")]
     SafeCmdError(String),
     #[error("Failed to download the safe or safenode binary")]
@@ -165,6 +212,8 @@ pub enum Error {
     S3ByteStreamError,
     #[error("The secret key was not found in the environment")]
     SecretKeyNotFound,
+    #[error("Could not retrieve the secret from '{0}': {1}")]
+    SecretReferenceNotResolved(String, String),
     #[error(transparent)]
     SerdeJson(#[from] serde_json::Error),
     #[error("An unexpected error occurred during the setup process")]
@@ -0,0 +1,86 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crate::{
+    error::{Error, Result},
+    is_binary_on_path, run_external_command,
+};
+use std::{path::PathBuf, sync::Arc};
+
+/// Runs the external tools, such as `terraform`, `ansible-playbook` and `ssh`, on behalf of the
+/// runners and clients.
+///
+/// The runners hold an executor rather than starting processes themselves, so that a fake can be
+/// supplied to test them without the tools or any infrastructure.
+pub trait CommandExecutor: Send + Sync {
+    /// Run a command to completion and return the lines from its stdout and stderr.
+    fn run(
+        &self,
+        binary_path: PathBuf,
+        working_directory_path: PathBuf,
+        args: Vec<String>,
+        suppress_stdout: bool,
+        suppress_stderr: bool,
+    ) -> Result<Vec<String>>;
+}
+
+/// Runs the commands as processes on the local machine.
+///
+/// A binary given by its name rather than a path, e.g., `ansible-inventory`, must be on the
+/// `PATH`, otherwise `Error::ToolBinaryNotFound` is returned.
+#[derive(Clone, Debug, Default)]
+pub struct SystemCommandExecutor;
+
+impl CommandExecutor for SystemCommandExecutor {
+    fn run(
+        &self,
+        binary_path: PathBuf,
+        working_directory_path: PathBuf,
+        args: Vec<String>,
+        suppress_stdout: bool,
+        suppress_stderr: bool,
+    ) -> Result<Vec<String>> {
+        if binary_path.components().count() == 1 {
+            let bin_name = binary_path.to_string_lossy().to_string();
+            if !is_binary_on_path(&bin_name) {
+                return Err(Error::ToolBinaryNotFound(bin_name));
+            }
+        }
+        run_external_command(
+            binary_path,
+            working_directory_path,
+            args,
+            suppress_stdout,
+            suppress_stderr,
+        )
+    }
+}
+
+pub fn get_system_command_executor() -> Arc<dyn CommandExecutor> {
+    Arc::new(SystemCommandExecutor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_returns_tool_binary_not_found_for_a_binary_not_on_the_path() {
+        let result = SystemCommandExecutor.run(
+            PathBuf::from("sn-testnet-deploy-missing-binary"),
+            std::env::temp_dir(),
+            vec!["--version".to_string()],
+            true,
+            true,
+        );
+        match result {
+            Err(Error::ToolBinaryNotFound(name)) => {
+                assert_eq!(name, "sn-testnet-deploy-missing-binary")
+            }
+            other => panic!("Expected ToolBinaryNotFound, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,110 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crate::{
+    ansible::provisioning::AnsibleProvisioner,
+    error::{Error, Result},
+    inventory::VirtualMachine,
+};
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
+use evmlib::{
+    common::U256,
+    wallet::{balance_of_gas_tokens, balance_of_tokens},
+    Network,
+};
+use futures::{stream, StreamExt, TryStreamExt};
+use log::debug;
+use std::collections::HashMap;
+
+/// The number of wallets whose balances are queried at the same time.
+const BALANCE_QUERY_CONCURRENCY: usize = 16;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WalletOwner {
+    Funding,
+    /// An uploader on a Client VM. The number matches the `ant_random_uploader_<number>` service.
+    Uploader {
+        vm_name: String,
+        number: usize,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct WalletBalance {
+    pub owner: WalletOwner,
+    pub address: Address,
+    pub token_balance: U256,
+    pub gas_balance: U256,
+}
+
+impl WalletBalance {
+    /// Returns whether either balance is below its threshold.
+    pub fn is_low(&self, token_threshold: U256, gas_threshold: U256) -> bool {
+        self.token_balance < token_threshold || self.gas_balance < gas_threshold
+    }
+}
+
+impl AnsibleProvisioner {
+    /// Get the balances of the uploader wallets on all the Client VMs, and the funding wallet, if
+    /// its address is provided.
+    ///
+    /// The funding wallet is first, followed by the uploaders, ordered by VM name.
+    pub async fn get_wallet_balances(
+        &self,
+        funding_wallet_address: Option<Address>,
+        network: &Network,
+    ) -> Result<Vec<WalletBalance>> {
+        let client_secret_keys = self.get_client_secret_keys()?;
+        let mut wallets = funding_wallet_address
+            .map(|address| (WalletOwner::Funding, address))
+            .into_iter()
+            .collect::<Vec<_>>();
+        wallets.extend(get_uploader_wallets(&client_secret_keys));
+        get_balances(wallets, network).await
+    }
+}
+
+fn get_uploader_wallets(
+    client_secret_keys: &HashMap<VirtualMachine, Vec<PrivateKeySigner>>,
+) -> Vec<(WalletOwner, Address)> {
+    let mut vms = client_secret_keys.iter().collect::<Vec<_>>();
+    vms.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+    vms.into_iter()
+        .flat_map(|(vm, keys)| {
+            keys.iter().enumerate().map(|(i, sk)| {
+                (
+                    WalletOwner::Uploader {
+                        vm_name: vm.name.clone(),
+                        number: i + 1,
+                    },
+                    sk.address(),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Query the token and gas balances of the wallets concurrently, keeping them in their order.
+pub async fn get_balances(
+    wallets: Vec<(WalletOwner, Address)>,
+    network: &Network,
+) -> Result<Vec<WalletBalance>> {
+    stream::iter(wallets)
+        .map(|(owner, address)| async move {
+            debug!("Querying balances for {address}");
+            let token_balance = balance_of_tokens(address, network).await?;
+            let gas_balance = balance_of_gas_tokens(address, network).await?;
+            Ok::<_, Error>(WalletBalance {
+                owner,
+                address,
+                token_balance,
+                gas_balance,
+            })
+        })
+        .buffered(BALANCE_QUERY_CONCURRENCY)
+        .try_collect()
+        .await
+}
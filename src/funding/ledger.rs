@@ -0,0 +1,346 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crate::{
+    error::{Error, Result},
+    inventory::get_data_directory,
+    object_store::{Bucket, ObjectStore},
+};
+use alloy::primitives::Address;
+use chrono::Utc;
+use evmlib::common::U256;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+fn get_ledger_prefix(name: &str) -> String {
+    format!("funding-ledgers/{name}/")
+}
+
+/// The ID begins with the time, so the parts of a ledger are listed in the order of the runs.
+fn generate_run_id() -> String {
+    format!(
+        "{}-{:08x}",
+        Utc::now().format("%Y%m%dT%H%M%SZ"),
+        rand::random::<u32>()
+    )
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferKind {
+    /// Funds sent from the funding wallet to an uploader wallet.
+    Deposit,
+    /// Funds returned from an uploader wallet to the funding wallet.
+    Drain,
+}
+
+impl std::fmt::Display for TransferKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransferKind::Deposit => write!(f, "deposit"),
+            TransferKind::Drain => write!(f, "drain"),
+        }
+    }
+}
+
+/// A single token or gas transaction made for an environment.
+///
+/// The amounts are decimal strings in wei, because they don't fit in any of the JSON number types.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    /// The time of the transaction, in RFC 3339 format.
+    pub timestamp: String,
+    pub environment_name: String,
+    pub kind: TransferKind,
+    pub from: String,
+    pub to: String,
+    /// The Client VM of the uploader wallet.
+    pub vm_name: String,
+    pub token_amount: String,
+    pub gas_amount: String,
+    pub transaction_hash: Option<String>,
+    pub succeeded: bool,
+    pub error: Option<String>,
+}
+
+impl LedgerEntry {
+    #[allow(clippy::too_many_arguments)]
+    pub fn from_transfer<T: ToString, E: std::fmt::Display>(
+        environment_name: &str,
+        kind: TransferKind,
+        vm_name: &str,
+        from: Address,
+        to: Address,
+        token_amount: U256,
+        gas_amount: U256,
+        result: &std::result::Result<T, E>,
+    ) -> Self {
+        Self {
+            timestamp: Utc::now().to_rfc3339(),
+            environment_name: environment_name.to_string(),
+            kind,
+            from: from.to_string(),
+            to: to.to_string(),
+            vm_name: vm_name.to_string(),
+            token_amount: token_amount.to_string(),
+            gas_amount: gas_amount.to_string(),
+            transaction_hash: result.as_ref().ok().map(|hash| hash.to_string()),
+            succeeded: result.is_ok(),
+            error: result.as_ref().err().map(|err| err.to_string()),
+        }
+    }
+
+    pub fn get_token_amount(&self) -> U256 {
+        U256::from_str(&self.token_amount).unwrap_or_default()
+    }
+
+    pub fn get_gas_amount(&self) -> U256 {
+        U256::from_str(&self.gas_amount).unwrap_or_default()
+    }
+}
+
+/// The record of every deposit and drain transaction for an environment.
+///
+/// Each run that makes transactions writes its own part of the ledger. Entries are appended to a
+/// local file for the run as each transaction completes, so nothing is lost if the process fails
+/// part way through, and the file is then uploaded as a separate object. No object is written by
+/// more than one run, so runs on different machines can't overwrite each other's entries. The
+/// ledger is read by merging all of its parts.
+#[derive(Clone)]
+pub struct FundingLedger {
+    pub environment_name: String,
+    object_store: Arc<dyn ObjectStore>,
+    run_id: String,
+    // Appends from concurrent transfers would otherwise interleave.
+    write_lock: Arc<Mutex<()>>,
+}
+
+impl FundingLedger {
+    pub fn new(environment_name: &str, object_store: Arc<dyn ObjectStore>) -> Self {
+        Self {
+            environment_name: environment_name.to_string(),
+            object_store,
+            run_id: generate_run_id(),
+            write_lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    fn get_local_dir_path(&self) -> Result<PathBuf> {
+        let ledger_dir_path = get_data_directory()
+            .map_err(|_| Error::CouldNotRetrieveDataDirectory)?
+            .join("funding-ledgers")
+            .join(&self.environment_name);
+        std::fs::create_dir_all(&ledger_dir_path)?;
+        Ok(ledger_dir_path)
+    }
+
+    /// The path of the local file for the entries recorded by this run.
+    pub fn get_local_path(&self) -> Result<PathBuf> {
+        Ok(self
+            .get_local_dir_path()?
+            .join(format!("{}.jsonl", self.run_id)))
+    }
+
+    /// Append an entry to the local file for this run.
+    pub fn record(&self, entry: &LedgerEntry) -> Result<()> {
+        let _guard = self
+            .write_lock
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.get_local_path()?)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    /// Upload the entries recorded by this run to the object store.
+    pub async fn sync(&self) -> Result<()> {
+        let path = self.get_local_path()?;
+        if !path.exists() {
+            return Ok(());
+        }
+        let contents = {
+            let _guard = self
+                .write_lock
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            std::fs::read(path)?
+        };
+        self.object_store
+            .put_object_contents(
+                Bucket::Testnet,
+                &format!(
+                    "{}{}.jsonl",
+                    get_ledger_prefix(&self.environment_name),
+                    self.run_id
+                ),
+                contents,
+            )
+            .await
+    }
+
+    /// Get the entries from every run, ordered by time.
+    ///
+    /// The local files are also read, so the entries from any run on this machine that failed to
+    /// upload are included.
+    pub async fn get_entries(&self) -> Result<Vec<LedgerEntry>> {
+        let mut entries = Vec::new();
+        for key in self
+            .object_store
+            .list_object_keys(Bucket::Testnet, &get_ledger_prefix(&self.environment_name))
+            .await?
+        {
+            if let Some(contents) = self
+                .object_store
+                .get_object_contents(Bucket::Testnet, &key)
+                .await?
+            {
+                merge_entries(
+                    &mut entries,
+                    parse_entries(&String::from_utf8_lossy(&contents))?,
+                );
+            }
+        }
+        for dir_entry in std::fs::read_dir(self.get_local_dir_path()?)? {
+            let path = dir_entry?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "jsonl")
+            {
+                merge_entries(
+                    &mut entries,
+                    parse_entries(&std::fs::read_to_string(path)?)?,
+                );
+            }
+        }
+        entries.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        Ok(entries)
+    }
+}
+
+/// An uploaded part of the ledger is also in the local file for its run, so the entries that are
+/// already present are skipped.
+fn merge_entries(entries: &mut Vec<LedgerEntry>, new_entries: Vec<LedgerEntry>) {
+    for entry in new_entries {
+        if !entries.contains(&entry) {
+            entries.push(entry);
+        }
+    }
+}
+
+fn parse_entries(contents: &str) -> Result<Vec<LedgerEntry>> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(Error::from))
+        .collect()
+}
+
+/// The totals of the transactions in a ledger.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LedgerSummary {
+    pub tokens_spent: U256,
+    pub gas_spent: U256,
+    pub tokens_recovered: U256,
+    pub gas_recovered: U256,
+    pub successful_transactions: usize,
+    pub failed_transactions: usize,
+}
+
+impl LedgerSummary {
+    pub fn from_entries(entries: &[LedgerEntry]) -> Self {
+        let mut summary = LedgerSummary::default();
+        for entry in entries {
+            if !entry.succeeded {
+                summary.failed_transactions += 1;
+                continue;
+            }
+            summary.successful_transactions += 1;
+            match entry.kind {
+                TransferKind::Deposit => {
+                    summary.tokens_spent += entry.get_token_amount();
+                    summary.gas_spent += entry.get_gas_amount();
+                }
+                TransferKind::Drain => {
+                    summary.tokens_recovered += entry.get_token_amount();
+                    summary.gas_recovered += entry.get_gas_amount();
+                }
+            }
+        }
+        summary
+    }
+
+    /// The tokens that were deposited and have not been drained back.
+    pub fn tokens_outstanding(&self) -> U256 {
+        self.tokens_spent.saturating_sub(self.tokens_recovered)
+    }
+
+    /// The gas that was deposited and has not been drained back, which includes the gas used
+    /// for uploads and transaction fees.
+    pub fn gas_outstanding(&self) -> U256 {
+        self.gas_spent.saturating_sub(self.gas_recovered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_entry(kind: TransferKind, token_amount: u64, gas_amount: u64) -> LedgerEntry {
+        LedgerEntry::from_transfer::<String, String>(
+            "alpha",
+            kind,
+            "alpha-client-1",
+            Address::ZERO,
+            Address::ZERO,
+            U256::from(token_amount),
+            U256::from(gas_amount),
+            &Ok("0x01".to_string()),
+        )
+    }
+
+    #[test]
+    fn test_summary_totals_only_include_successful_transactions() {
+        let mut failed_entry = get_entry(TransferKind::Deposit, 1000, 0);
+        failed_entry.succeeded = false;
+        failed_entry.transaction_hash = None;
+        failed_entry.error = Some("insufficient funds".to_string());
+        let entries = vec![
+            get_entry(TransferKind::Deposit, 100, 0),
+            get_entry(TransferKind::Deposit, 0, 10),
+            get_entry(TransferKind::Deposit, 100, 0),
+            failed_entry,
+            get_entry(TransferKind::Drain, 150, 0),
+            get_entry(TransferKind::Drain, 0, 4),
+        ];
+
+        let summary = LedgerSummary::from_entries(&entries);
+        assert_eq!(summary.tokens_spent, U256::from(200));
+        assert_eq!(summary.gas_spent, U256::from(10));
+        assert_eq!(summary.tokens_recovered, U256::from(150));
+        assert_eq!(summary.gas_recovered, U256::from(4));
+        assert_eq!(summary.tokens_outstanding(), U256::from(50));
+        assert_eq!(summary.gas_outstanding(), U256::from(6));
+        assert_eq!(summary.successful_transactions, 5);
+        assert_eq!(summary.failed_transactions, 1);
+    }
+
+    #[test]
+    fn test_entry_amounts_round_trip_through_json() {
+        let entry = get_entry(TransferKind::Deposit, u64::MAX, 100_000_000_000_000_000);
+        let line = serde_json::to_string(&entry).unwrap();
+        let parsed = parse_entries(&format!("{line}\n\n")).unwrap();
+        assert_eq!(parsed, vec![entry]);
+        assert_eq!(parsed[0].get_token_amount(), U256::from(u64::MAX));
+    }
+}
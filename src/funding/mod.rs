@@ -4,9 +4,13 @@
 // This SAFE Network Software is licensed under the BSD-3-Clause license.
 // Please see the LICENSE file for more details.
 
+pub mod balances;
+pub mod ledger;
+
 use crate::error::Result;
 use crate::{
     ansible::{inventory::AnsibleInventoryType, provisioning::AnsibleProvisioner},
+    dry_run::{self, PlannedAction},
     error::Error,
     inventory::VirtualMachine,
     EnvironmentDetails, EvmNetwork,
@@ -14,6 +18,7 @@ use crate::{
 use alloy::primitives::Address;
 use alloy::{network::EthereumWallet, signers::local::PrivateKeySigner};
 use evmlib::{common::U256, wallet::Wallet, Network};
+use ledger::{FundingLedger, LedgerEntry, TransferKind};
 use log::{debug, error, warn};
 use std::collections::HashMap;
 use std::str::FromStr;
@@ -22,6 +27,8 @@ use std::str::FromStr;
 const DEFAULT_TOKEN_AMOUNT: &str = "100_000_000_000_000_000_000";
 /// 0.1 ETH (1e17)
 const DEFAULT_GAS_AMOUNT: &str = "100_000_000_000_000_000";
+/// 0.001 ETH (1e13), which is left behind in a drained wallet to pay for the transfer.
+const DRAIN_GAS_RESERVE: &str = "10_000_000_000_000";
 
 pub struct FundingOptions {
     pub evm_network: EvmNetwork,
@@ -160,6 +167,28 @@ impl AnsibleProvisioner {
         println!("Draining all the local ANT wallets to {to_address:?}");
         let ant_secret_keys = self.get_client_secret_keys()?;
 
+        let ledger = self.get_funding_ledger();
+        let result = self
+            .drain_wallets(&ledger, &ant_secret_keys, to_address, evm_network)
+            .await;
+        if !dry_run::is_enabled() {
+            sync_ledger(&ledger).await;
+        }
+        result?;
+
+        println!("All funds drained to {to_address:?} successfully");
+        debug!("All funds drained to {to_address:?} successfully");
+
+        Ok(())
+    }
+
+    async fn drain_wallets(
+        &self,
+        ledger: &FundingLedger,
+        ant_secret_keys: &HashMap<VirtualMachine, Vec<PrivateKeySigner>>,
+        to_address: Address,
+        evm_network: Network,
+    ) -> Result<()> {
         for (vm, keys) in ant_secret_keys.iter() {
             debug!(
                 "Draining funds for Client vm: {} to {to_address:?}",
@@ -174,6 +203,20 @@ impl AnsibleProvisioner {
                 let from_wallet =
                     Wallet::new(evm_network.clone(), EthereumWallet::new(ant_sk.clone()));
 
+                if dry_run::is_enabled() {
+                    let token_balance = from_wallet.balance_of_tokens().await?;
+                    let gas_balance = from_wallet.balance_of_gas_tokens().await?;
+                    dry_run::record(PlannedAction::Transfer {
+                        from: from_wallet.address().to_string(),
+                        to: to_address.to_string(),
+                        token_amount: token_balance.to_string(),
+                        gas_amount: gas_balance
+                            .saturating_sub(U256::from_str(DRAIN_GAS_RESERVE).unwrap())
+                            .to_string(),
+                    });
+                    continue;
+                }
+
                 let token_balance = from_wallet.balance_of_tokens().await.inspect_err(|err| {
                     debug!(
                         "Failed to get token balance for {} with err: {err:?}",
@@ -196,14 +239,28 @@ impl AnsibleProvisioner {
                         from_wallet.address()
                     );
                 } else {
-                    from_wallet
+                    let result = from_wallet
                         .transfer_tokens(to_address, token_balance)
                         .await
                         .inspect_err(|err| {
                             debug!(
                                 "Failed to transfer {token_balance} tokens from {to_address} with err: {err:?}",
                             )
-                        })?;
+                        });
+                    record_transfer(
+                        ledger,
+                        &LedgerEntry::from_transfer(
+                            &ledger.environment_name,
+                            TransferKind::Drain,
+                            &vm.name,
+                            from_wallet.address(),
+                            to_address,
+                            token_balance,
+                            U256::ZERO,
+                            &result,
+                        ),
+                    );
+                    result?;
                     println!(
                         "Drained {token_balance} tokens from {} to {to_address:?}",
                         from_wallet.address()
@@ -236,14 +293,29 @@ impl AnsibleProvisioner {
                 if gas_balance.is_zero() {
                     debug!("No gas tokens to drain from wallet: {to_address}");
                 } else {
-                    from_wallet
-                    // 0.001 gas
-                        .transfer_gas_tokens(to_address, gas_balance - U256::from_str("10_000_000_000_000").unwrap()).await
+                    let gas_to_transfer = gas_balance - U256::from_str(DRAIN_GAS_RESERVE).unwrap();
+                    let result = from_wallet
+                        .transfer_gas_tokens(to_address, gas_to_transfer)
+                        .await
                         .inspect_err(|err| {
                             debug!(
                                 "Failed to transfer {gas_balance} gas from {to_address} with err: {err:?}",
                             )
-                        })?;
+                        });
+                    record_transfer(
+                        ledger,
+                        &LedgerEntry::from_transfer(
+                            &ledger.environment_name,
+                            TransferKind::Drain,
+                            &vm.name,
+                            from_wallet.address(),
+                            to_address,
+                            U256::ZERO,
+                            gas_to_transfer,
+                            &result,
+                        ),
+                    );
+                    result?;
                     println!(
                         "Drained {gas_balance} gas from {} to {to_address:?}",
                         from_wallet.address()
@@ -255,9 +327,6 @@ impl AnsibleProvisioner {
                 }
             }
         }
-        println!("All funds drained to {to_address:?} successfully");
-        debug!("All funds drained to {to_address:?} successfully");
-
         Ok(())
     }
 
@@ -442,14 +511,57 @@ impl AnsibleProvisioner {
             "Transferring {token_amount} tokens and {gas_amount} gas tokens to each ANT instance"
         );
 
+        let ledger = self.get_funding_ledger();
+        let result = self
+            .transfer_to_wallets(
+                &ledger,
+                &from_wallet,
+                all_secret_keys,
+                token_amount,
+                gas_amount,
+            )
+            .await;
+        // The ledger is synced even if a transfer failed, so the record includes the failure.
+        if !dry_run::is_enabled() {
+            sync_ledger(&ledger).await;
+        }
+        result?;
+        println!("All funds transferred successfully");
+        debug!("All funds transferred successfully");
+
+        Ok(())
+    }
+
+    pub fn get_funding_ledger(&self) -> FundingLedger {
+        FundingLedger::new(
+            &self.ansible_runner.environment_name,
+            self.object_store.clone(),
+        )
+    }
+
+    async fn transfer_to_wallets(
+        &self,
+        ledger: &FundingLedger,
+        from_wallet: &Wallet,
+        all_secret_keys: &HashMap<VirtualMachine, Vec<PrivateKeySigner>>,
+        token_amount: U256,
+        gas_amount: U256,
+    ) -> Result<()> {
         for (vm, vm_secret_keys) in all_secret_keys.iter() {
             println!("Transferring funds for Client vm: {}", vm.name);
             for sk in vm_secret_keys.iter() {
-                sk.address();
-
+                if dry_run::is_enabled() {
+                    dry_run::record(PlannedAction::Transfer {
+                        from: from_wallet.address().to_string(),
+                        to: sk.address().to_string(),
+                        token_amount: token_amount.to_string(),
+                        gas_amount: gas_amount.to_string(),
+                    });
+                    continue;
+                }
                 if !token_amount.is_zero() {
                     print!("Transferring {token_amount} tokens to {}...", sk.address());
-                    from_wallet
+                    let result = from_wallet
                         .transfer_tokens(sk.address(), token_amount)
                         .await
                         .inspect_err(|err| {
@@ -457,12 +569,26 @@ impl AnsibleProvisioner {
                                 "Failed to transfer {token_amount} tokens to {}: {err:?}",
                                 sk.address()
                             )
-                        })?;
+                        });
+                    record_transfer(
+                        ledger,
+                        &LedgerEntry::from_transfer(
+                            &ledger.environment_name,
+                            TransferKind::Deposit,
+                            &vm.name,
+                            from_wallet.address(),
+                            sk.address(),
+                            token_amount,
+                            U256::ZERO,
+                            &result,
+                        ),
+                    );
+                    result?;
                     println!("Transfer complete");
                 }
                 if !gas_amount.is_zero() {
                     print!("Transferring {gas_amount} gas to {}...", sk.address());
-                    from_wallet
+                    let result = from_wallet
                         .transfer_gas_tokens(sk.address(), gas_amount)
                         .await
                         .inspect_err(|err| {
@@ -470,14 +596,25 @@ impl AnsibleProvisioner {
                                 "Failed to transfer {gas_amount} gas to {}: {err:?}",
                                 sk.address()
                             )
-                        })?;
+                        });
+                    record_transfer(
+                        ledger,
+                        &LedgerEntry::from_transfer(
+                            &ledger.environment_name,
+                            TransferKind::Deposit,
+                            &vm.name,
+                            from_wallet.address(),
+                            sk.address(),
+                            U256::ZERO,
+                            gas_amount,
+                            &result,
+                        ),
+                    );
+                    result?;
                     println!("Transfer complete");
                 }
             }
         }
-        println!("All funds transferred successfully");
-        debug!("All funds transferred successfully");
-
         Ok(())
     }
 }
@@ -545,3 +682,31 @@ pub async fn drain_funds(
         Ok(())
     }
 }
+
+/// The ledger is only a record of the transfers, so a failure to write it must not stop funds
+/// from being moved, or hide the result of a transfer.
+fn record_transfer(ledger: &FundingLedger, entry: &LedgerEntry) {
+    if let Err(err) = ledger.record(entry) {
+        error!(
+            "Failed to record the {} from {} to {} in the funding ledger: {err}",
+            entry.kind, entry.from, entry.to
+        );
+        println!(
+            "Failed to record the {} from {} to {} in the funding ledger: {err}",
+            entry.kind, entry.from, entry.to
+        );
+    }
+}
+
+async fn sync_ledger(ledger: &FundingLedger) {
+    if let Err(err) = ledger.sync().await {
+        error!(
+            "Failed to upload the funding ledger for {}: {err}",
+            ledger.environment_name
+        );
+        println!(
+            "Failed to upload the funding ledger for {}: {err}",
+            ledger.environment_name
+        );
+    }
+}
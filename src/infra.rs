@@ -7,12 +7,14 @@
 use log::debug;
 
 use crate::{
+    dry_run,
     error::{Error, Result},
     print_duration,
-    terraform::{TerraformResource, TerraformRunner},
+    terraform::{TerraformPlan, TerraformResource, TerraformResourceChange, TerraformRunner},
     EnvironmentDetails, TestnetDeployer,
 };
-use std::time::Instant;
+use inquire::{Confirm, InquireError};
+use std::{collections::BTreeMap, path::Path, time::Instant};
 
 const BUILD_VM: &str = "build";
 const CLIENT: &str = "ant_client";
@@ -39,8 +41,12 @@ const UPNP_PRIVATE_NODE_ATTACHED_VOLUME: &str = "upnp_private_node_attached_volu
 const SIZE: &str = "size";
 const IMAGE: &str = "image";
 
+pub(crate) const PLAN_FILE_NAME: &str = "infra.tfplan";
+
 #[derive(Clone, Debug)]
 pub struct InfraRunOptions {
+    /// Apply changes that would destroy or replace existing resources without confirmation.
+    pub allow_destructive_changes: bool,
     /// Set to None for new deployments, as the value will be fetched from tfvars.
     pub client_image_id: Option<String>,
     pub client_vm_count: Option<u16>,
@@ -291,6 +297,7 @@ impl InfraRunOptions {
         };
 
         let options = Self {
+            allow_destructive_changes: false,
             client_image_id,
             client_vm_count: Some(client_vm_count),
             client_vm_size,
@@ -331,25 +338,229 @@ impl InfraRunOptions {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum PlannedChange {
+    Create,
+    Destroy,
+    Replace,
+    Resize,
+    Update,
+}
+
+/// A summary of a Terraform plan, described in terms of the VMs and volumes in an environment,
+/// rather than Terraform resources, e.g., "+3 node VMs, -1 peer cache VM, 2 volumes resized".
+#[derive(Clone, Debug, Default)]
+pub struct InfraPlanSummary {
+    changes: BTreeMap<(PlannedChange, String), usize>,
+    /// The addresses of the resources that the plan destroys or replaces.
+    pub destructive_addresses: Vec<String>,
+}
+
+impl InfraPlanSummary {
+    pub fn from_plan(plan: &TerraformPlan) -> Self {
+        let mut summary = InfraPlanSummary::default();
+        for resource_change in plan.resource_changes.iter() {
+            let change = &resource_change.change;
+            let planned_change = if change.is_replace() {
+                PlannedChange::Replace
+            } else if change.is_delete() {
+                PlannedChange::Destroy
+            } else if change.is_create() {
+                PlannedChange::Create
+            } else if change.is_update() {
+                let (before, after) = change.get_attribute_change(SIZE);
+                if before != after {
+                    PlannedChange::Resize
+                } else {
+                    PlannedChange::Update
+                }
+            } else {
+                continue;
+            };
+
+            if matches!(
+                planned_change,
+                PlannedChange::Destroy | PlannedChange::Replace
+            ) {
+                summary
+                    .destructive_addresses
+                    .push(resource_change.address.clone());
+            }
+            *summary
+                .changes
+                .entry((planned_change, get_resource_description(resource_change)))
+                .or_insert(0) += 1;
+        }
+        summary
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn has_destructive_changes(&self) -> bool {
+        !self.destructive_addresses.is_empty()
+    }
+}
+
+impl std::fmt::Display for InfraPlanSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_empty() {
+            return write!(f, "no changes");
+        }
+        let changes = self
+            .changes
+            .iter()
+            .map(|((planned_change, description), count)| {
+                let description = if *count == 1 {
+                    description.clone()
+                } else {
+                    format!("{description}s")
+                };
+                match planned_change {
+                    PlannedChange::Create => format!("+{count} {description}"),
+                    PlannedChange::Destroy => format!("-{count} {description}"),
+                    PlannedChange::Replace => format!("{count} {description} replaced"),
+                    PlannedChange::Resize => format!("{count} {description} resized"),
+                    PlannedChange::Update => format!("{count} {description} updated in-place"),
+                }
+            })
+            .collect::<Vec<_>>();
+        write!(f, "{}", changes.join(", "))
+    }
+}
+
+// The AWS resources are defined in modules, so the module name is used to identify them.
+fn get_resource_description(resource_change: &TerraformResourceChange) -> String {
+    let name = resource_change
+        .module_address
+        .as_deref()
+        .map(|module| module.trim_start_matches("module."))
+        .unwrap_or(&resource_change.resource_name);
+    if resource_change
+        .resource_type
+        .ends_with("_volume_attachment")
+    {
+        return "volume attachment".to_string();
+    }
+    if name.ends_with("_attached_volume") {
+        return "volume".to_string();
+    }
+    let description = match name {
+        BUILD_VM => "build VM",
+        CLIENT | "client_ec2_instance" => "client VM",
+        EVM_NODE => "EVM node VM",
+        FULL_CONE_NAT_GATEWAY => "full cone NAT gateway",
+        FULL_CONE_PRIVATE_NODE => "full cone private node VM",
+        GENESIS_NODE | "genesis_ec2_instance" => "genesis VM",
+        NODE | "node_ec2_instances" => "node VM",
+        PEER_CACHE_NODE => "peer cache VM",
+        PORT_RESTRICTED_CONE_NAT_GATEWAY => "port restricted cone NAT gateway",
+        PORT_RESTRICTED_PRIVATE_NODE | "port_restricted_cone_private_node" => {
+            "port restricted cone private node VM"
+        }
+        SYMMETRIC_NAT_GATEWAY => "symmetric NAT gateway",
+        SYMMETRIC_PRIVATE_NODE => "symmetric private node VM",
+        UPNP_PRIVATE_NODE => "UPnP private node VM",
+        _ => return format!("{} resource", resource_change.resource_type),
+    };
+    description.to_string()
+}
+
 impl TestnetDeployer {
     /// Create or update the infrastructure for a deployment.
+    ///
+    /// The plan is saved and summarised before it is applied. If it would destroy or replace any
+    /// existing resources, the changes must be confirmed, otherwise nothing is applied.
     pub fn create_or_update_infra(&self, options: &InfraRunOptions) -> Result<()> {
         let start = Instant::now();
         println!("Selecting {} workspace...", options.name);
         self.terraform_runner.workspace_select(&options.name)?;
 
         let args = build_terraform_args(options)?;
-
-        println!("Running terraform apply...");
-        self.terraform_runner
-            .apply(args, options.tfvars_filenames.clone())?;
+        apply_infra_changes(
+            &self.terraform_runner,
+            &options.name,
+            args,
+            options.tfvars_filenames.clone(),
+            options.allow_destructive_changes,
+        )?;
         print_duration(start.elapsed());
         Ok(())
     }
 }
 
+/// Save the plan to the given path and print a summary of it.
+pub(crate) fn plan_infra_changes(
+    terraform_runner: &TerraformRunner,
+    args: Vec<(String, String)>,
+    tfvars_filenames: Option<Vec<String>>,
+    plan_path: &Path,
+) -> Result<InfraPlanSummary> {
+    terraform_runner.plan_to_file(args, tfvars_filenames, plan_path)?;
+    let plan = terraform_runner.show_plan(plan_path)?;
+    let summary = InfraPlanSummary::from_plan(&plan);
+    println!("Planned infrastructure changes: {summary}");
+    Ok(summary)
+}
+
+/// Plan the changes, confirm them if they would destroy or replace any existing resources, then
+/// apply exactly what was planned.
+pub(crate) fn apply_infra_changes(
+    terraform_runner: &TerraformRunner,
+    name: &str,
+    args: Vec<(String, String)>,
+    tfvars_filenames: Option<Vec<String>>,
+    allow_destructive_changes: bool,
+) -> Result<()> {
+    let plan_dir = tempfile::tempdir()?;
+    let plan_path = plan_dir.path().join(PLAN_FILE_NAME);
+    println!("Running terraform plan...");
+    let summary = plan_infra_changes(terraform_runner, args, tfvars_filenames, &plan_path)?;
+    if summary.has_destructive_changes() {
+        if allow_destructive_changes {
+            println!("Destructive changes to {name} were allowed without confirmation");
+        } else if dry_run::is_enabled() {
+            // The apply is only recorded, so there is nothing to confirm.
+            print_destructive_changes(name, &summary);
+        } else {
+            confirm_destructive_changes(name, &summary)?;
+        }
+    }
+
+    println!("Running terraform apply...");
+    terraform_runner.apply_plan(&plan_path)?;
+    Ok(())
+}
+
+fn print_destructive_changes(name: &str, summary: &InfraPlanSummary) {
+    println!("The plan for {name} would destroy or replace these resources:");
+    for address in summary.destructive_addresses.iter() {
+        println!("  {address}");
+    }
+}
+
+fn confirm_destructive_changes(name: &str, summary: &InfraPlanSummary) -> Result<()> {
+    print_destructive_changes(name, summary);
+    let confirmed = match Confirm::new("Do you want to apply these changes?")
+        .with_default(false)
+        .prompt()
+    {
+        Ok(confirmed) => confirmed,
+        // There is nobody to confirm the changes when running non-interactively.
+        Err(InquireError::NotTTY) => false,
+        Err(err) => return Err(err.into()),
+    };
+    if !confirmed {
+        return Err(Error::DestructiveInfraChangesNotConfirmed(name.to_string()));
+    }
+    Ok(())
+}
+
 #[derive(Clone, Debug)]
 pub struct ClientsInfraRunOptions {
+    /// Apply changes that would destroy or replace existing resources without confirmation.
+    pub allow_destructive_changes: bool,
     pub client_image_id: Option<String>,
     pub client_vm_count: Option<u16>,
     pub client_vm_size: Option<String>,
@@ -392,6 +603,7 @@ impl ClientsInfraRunOptions {
         let enable_build_vm = build_vm_count > 0;
 
         let options = Self {
+            allow_destructive_changes: false,
             client_image_id,
             client_vm_count: Some(client_vm_count),
             client_vm_size,
@@ -17,7 +17,7 @@ use crate::{
     },
     clients::ClientsDeployer,
     get_bootstrap_cache_url, get_environment_details, get_genesis_multiaddr,
-    s3::S3Repository,
+    object_store::{Bucket, ObjectStore},
     ssh::SshClient,
     terraform::TerraformRunner,
     BinaryOption, CloudProvider, DeploymentType, EnvironmentDetails, EnvironmentType, Error,
@@ -37,11 +37,11 @@ use std::{
     io::Write,
     net::{IpAddr, SocketAddr},
     path::PathBuf,
+    sync::Arc,
 };
 
 const DEFAULT_CONTACTS_COUNT: usize = 100;
 const UNAVAILABLE_NODE: &str = "-";
-const TESTNET_BUCKET_NAME: &str = "sn-testnet";
 
 pub struct DeploymentInventoryService {
     pub ansible_runner: AnsibleRunner,
@@ -51,7 +51,7 @@ pub struct DeploymentInventoryService {
     pub ansible_provisioner: AnsibleProvisioner,
     pub cloud_provider: CloudProvider,
     pub inventory_file_path: PathBuf,
-    pub s3_repository: S3Repository,
+    pub object_store: Arc<dyn ObjectStore>,
     pub ssh_client: SshClient,
     pub terraform_runner: TerraformRunner,
     pub working_directory_path: PathBuf,
@@ -72,7 +72,7 @@ impl From<&TestnetDeployer> for DeploymentInventoryService {
                 .join("ansible")
                 .join("inventory")
                 .join(format!("dev_inventory_{provider}.yml")),
-            s3_repository: item.s3_repository.clone(),
+            object_store: item.object_store.clone(),
             ssh_client: item.ssh_client.clone(),
             terraform_runner: item.terraform_runner.clone(),
             working_directory_path: item.working_directory_path.clone(),
@@ -95,7 +95,7 @@ impl From<&ClientsDeployer> for DeploymentInventoryService {
                 .join("ansible")
                 .join("inventory")
                 .join(format!("dev_inventory_{provider}.yml")),
-            s3_repository: item.s3_repository.clone(),
+            object_store: item.object_store.clone(),
             ssh_client: item.ssh_client.clone(),
             terraform_runner: item.terraform_runner.clone(),
             working_directory_path: item.working_directory_path.clone(),
@@ -156,7 +156,7 @@ impl DeploymentInventoryService {
             &output_inventory_dir_path,
         )?;
 
-        let environment_details = match get_environment_details(name, &self.s3_repository).await {
+        let environment_details = match get_environment_details(name, &self.object_store).await {
             Ok(details) => details,
             Err(Error::EnvironmentDetailsNotFound(_)) => {
                 println!("Environment details not found: treating this as a new deployment");
@@ -653,8 +653,8 @@ impl DeploymentInventoryService {
             }
         }
 
-        self.s3_repository
-            .upload_file(TESTNET_BUCKET_NAME, &temp_file_path, true)
+        self.object_store
+            .upload_file(Bucket::Testnet, &temp_file_path, true)
             .await?;
 
         Ok(())
@@ -746,7 +746,7 @@ impl DeploymentInventoryService {
             &output_inventory_dir_path,
         )?;
 
-        let environment_details = match get_environment_details(name, &self.s3_repository).await {
+        let environment_details = match get_environment_details(name, &self.object_store).await {
             Ok(details) => details,
             Err(Error::EnvironmentDetailsNotFound(_)) => {
                 println!("Environment details not found: treating this as a new deployment");
@@ -7,19 +7,29 @@
 pub mod ansible;
 pub mod bootstrap;
 pub mod clients;
+pub mod cost;
 pub mod deploy;
 pub mod digital_ocean;
+pub mod drift;
+pub mod dry_run;
+pub mod environments;
 pub mod error;
+pub mod executor;
 pub mod funding;
 pub mod infra;
 pub mod inventory;
+pub mod lock;
 pub mod logs;
+pub mod object_store;
+pub mod policy;
 pub mod reserved_ip;
 pub mod rpc_client;
 pub mod s3;
 pub mod safe;
+pub mod secrets;
 pub mod setup;
 pub mod ssh;
+pub mod sweep;
 pub mod terraform;
 pub mod upscale;
 
@@ -33,16 +43,19 @@ use crate::{
         AnsibleRunner,
     },
     error::{Error, Result},
+    executor::CommandExecutor,
     inventory::{DeploymentInventory, VirtualMachine},
+    logs::line::parse_rfc3339,
+    object_store::{get_object_store, Bucket, ObjectStore},
     rpc_client::RpcClient,
-    s3::S3Repository,
     ssh::SshClient,
     terraform::TerraformRunner,
 };
 use ant_service_management::ServiceStatus;
+use chrono::{DateTime, Utc};
 use flate2::read::GzDecoder;
 use indicatif::{ProgressBar, ProgressStyle};
-use infra::{build_terraform_args, InfraRunOptions};
+use infra::{build_terraform_args, InfraRunOptions, PLAN_FILE_NAME};
 use log::{debug, trace};
 use semver::Version;
 use serde::{Deserialize, Serialize};
@@ -54,6 +67,7 @@ use std::{
     path::{Path, PathBuf},
     process::{Command, Stdio},
     str::FromStr,
+    sync::Arc,
     time::Duration,
 };
 use tar::Archive;
@@ -216,15 +230,32 @@ pub struct EvmDetails {
 
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct EnvironmentDetails {
+    /// The time the environment was deployed, in RFC 3339 format.
+    ///
+    /// This and the owner and expiry time are not present for environments deployed by older
+    /// versions.
+    #[serde(default)]
+    pub created_at: Option<String>,
     pub deployment_type: DeploymentType,
     pub environment_type: EnvironmentType,
     pub evm_details: EvmDetails,
+    /// The time after which the environment can be reaped, in RFC 3339 format.
+    #[serde(default)]
+    pub expires_at: Option<String>,
     pub funding_wallet_address: Option<String>,
     pub network_id: Option<u8>,
+    #[serde(default)]
+    pub owner: Option<String>,
     pub region: String,
     pub rewards_address: Option<String>,
 }
 
+impl EnvironmentDetails {
+    pub fn get_expiry_time(&self) -> Option<DateTime<Utc>> {
+        self.expires_at.as_deref().and_then(parse_rfc3339)
+    }
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub enum EnvironmentType {
     #[default]
@@ -308,7 +339,6 @@ impl FromStr for EnvironmentType {
 ///
 /// There are several binaries involved in the deployment:
 /// * safenode
-/// * safenode_rpc_client
 /// * faucet
 /// * safe
 ///
@@ -483,8 +513,10 @@ impl UpgradeOptions {
 pub struct TestnetDeployBuilder {
     ansible_forks: Option<usize>,
     ansible_verbose_mode: bool,
+    command_executor: Option<Arc<dyn CommandExecutor>>,
     deployment_type: EnvironmentType,
     environment_name: String,
+    object_store: Option<Arc<dyn ObjectStore>>,
     provider: Option<CloudProvider>,
     region: Option<String>,
     ssh_secret_key_path: Option<PathBuf>,
@@ -509,6 +541,13 @@ impl TestnetDeployBuilder {
         self
     }
 
+    /// Use a different executor for the Terraform, Ansible and SSH commands, e.g., a fake one for
+    /// testing.
+    pub fn command_executor(&mut self, command_executor: Arc<dyn CommandExecutor>) -> &mut Self {
+        self.command_executor = Some(command_executor);
+        self
+    }
+
     pub fn deployment_type(&mut self, deployment_type: EnvironmentType) -> &mut Self {
         self.deployment_type = deployment_type;
         self
@@ -519,6 +558,12 @@ impl TestnetDeployBuilder {
         self
     }
 
+    /// Use a different object store from the one configured by the environment.
+    pub fn object_store(&mut self, object_store: Arc<dyn ObjectStore>) -> &mut Self {
+        self.object_store = Some(object_store);
+        self
+    }
+
     pub fn provider(&mut self, provider: CloudProvider) -> &mut Self {
         self.provider = Some(provider);
         self
@@ -603,7 +648,7 @@ impl TestnetDeployBuilder {
             None => "lon1".to_string(),
         };
 
-        let terraform_runner = TerraformRunner::new(
+        let mut terraform_runner = TerraformRunner::new(
             terraform_binary_path.to_path_buf(),
             working_directory_path
                 .join("terraform")
@@ -612,7 +657,7 @@ impl TestnetDeployBuilder {
             provider,
             &state_bucket_name,
         )?;
-        let ansible_runner = AnsibleRunner::new(
+        let mut ansible_runner = AnsibleRunner::new(
             self.ansible_forks.unwrap_or(ANSIBLE_DEFAULT_FORKS),
             self.ansible_verbose_mode,
             &self.environment_name,
@@ -621,13 +666,23 @@ impl TestnetDeployBuilder {
             vault_password_path,
             working_directory_path.join("ansible"),
         )?;
-        let ssh_client = SshClient::new(ssh_secret_key_path);
-        let ansible_provisioner =
-            AnsibleProvisioner::new(ansible_runner, provider, ssh_client.clone());
-        let rpc_client = RpcClient::new(
-            PathBuf::from("/usr/local/bin/safenode_rpc_client"),
-            working_directory_path.clone(),
+        let mut ssh_client = SshClient::new(ssh_secret_key_path);
+        if let Some(command_executor) = &self.command_executor {
+            terraform_runner.executor = command_executor.clone();
+            ansible_runner.executor = command_executor.clone();
+            ssh_client.executor = command_executor.clone();
+        }
+        let object_store = match &self.object_store {
+            Some(object_store) => object_store.clone(),
+            None => get_object_store()?,
+        };
+        let ansible_provisioner = AnsibleProvisioner::new(
+            ansible_runner,
+            provider,
+            object_store.clone(),
+            ssh_client.clone(),
         );
+        let rpc_client = RpcClient::default();
 
         // Remove any `safe` binary from a previous deployment. Otherwise you can end up with
         // mismatched binaries.
@@ -642,7 +697,7 @@ impl TestnetDeployBuilder {
             self.deployment_type.clone(),
             &self.environment_name,
             rpc_client,
-            S3Repository {},
+            object_store,
             ssh_client,
             terraform_runner,
             working_directory_path,
@@ -662,7 +717,7 @@ pub struct TestnetDeployer {
     pub inventory_file_path: PathBuf,
     pub region: String,
     pub rpc_client: RpcClient,
-    pub s3_repository: S3Repository,
+    pub object_store: Arc<dyn ObjectStore>,
     pub ssh_client: SshClient,
     pub terraform_runner: TerraformRunner,
     pub working_directory_path: PathBuf,
@@ -676,7 +731,7 @@ impl TestnetDeployer {
         deployment_type: EnvironmentType,
         environment_name: &str,
         rpc_client: RpcClient,
-        s3_repository: S3Repository,
+        object_store: Arc<dyn ObjectStore>,
         ssh_client: SshClient,
         terraform_runner: TerraformRunner,
         working_directory_path: PathBuf,
@@ -698,7 +753,7 @@ impl TestnetDeployer {
             region,
             rpc_client,
             ssh_client,
-            s3_repository,
+            object_store,
             terraform_runner,
             working_directory_path,
         })
@@ -706,9 +761,9 @@ impl TestnetDeployer {
 
     pub async fn init(&self) -> Result<()> {
         if self
-            .s3_repository
+            .object_store
             .folder_exists(
-                "sn-testnet",
+                Bucket::Testnet,
                 &format!("testnet-logs/{}", self.environment_name),
             )
             .await?
@@ -727,26 +782,6 @@ impl TestnetDeployer {
             println!("Workspace {} already exists", self.environment_name);
         }
 
-        let rpc_client_path = self.working_directory_path.join("safenode_rpc_client");
-        if !rpc_client_path.is_file() {
-            println!("Downloading the rpc client for safenode...");
-            let archive_name = "safenode_rpc_client-latest-x86_64-unknown-linux-musl.tar.gz";
-            get_and_extract_archive_from_s3(
-                &self.s3_repository,
-                "sn-node-rpc-client",
-                archive_name,
-                &self.working_directory_path,
-            )
-            .await?;
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                let mut permissions = std::fs::metadata(&rpc_client_path)?.permissions();
-                permissions.set_mode(0o755); // rwxr-xr-x
-                std::fs::set_permissions(&rpc_client_path, permissions)?;
-            }
-        }
-
         Ok(())
     }
 
@@ -756,8 +791,13 @@ impl TestnetDeployer {
 
         let args = build_terraform_args(options)?;
 
-        self.terraform_runner
-            .plan(Some(args), options.tfvars_filenames.clone())?;
+        let plan_dir = tempfile::tempdir()?;
+        infra::plan_infra_changes(
+            &self.terraform_runner,
+            args,
+            options.tfvars_filenames.clone(),
+            &plan_dir.path().join(PLAN_FILE_NAME),
+        )?;
         Ok(())
     }
 
@@ -1046,34 +1086,50 @@ impl TestnetDeployer {
         Ok(())
     }
 
-    pub async fn clean(&self) -> Result<()> {
+    /// Destroy the environment and remove its inventory and details.
+    ///
+    /// Funds are only drained from the environment if `drain_funds` is set.
+    pub async fn clean(&self, drain_funds: bool) -> Result<()> {
         let environment_details =
-            get_environment_details(&self.environment_name, &self.s3_repository)
+            get_environment_details(&self.environment_name, &self.object_store)
                 .await
                 .inspect_err(|err| {
                     println!("Failed to get environment details: {err}. Continuing cleanup...");
                 })
                 .ok();
         if let Some(environment_details) = &environment_details {
-            funding::drain_funds(&self.ansible_provisioner, environment_details).await?;
+            if drain_funds {
+                funding::drain_funds(&self.ansible_provisioner, environment_details).await?;
+            } else {
+                println!(
+                    "Funds will not be drained from {}, because it was not requested",
+                    self.environment_name
+                );
+            }
         }
 
         self.destroy_infra(environment_details).await?;
 
-        cleanup_environment_inventory(
-            &self.environment_name,
-            &self
-                .working_directory_path
-                .join("ansible")
-                .join("inventory"),
-            None,
-        )?;
-
-        println!("Deleted Ansible inventory for {}", self.environment_name);
+        if dry_run::is_enabled() {
+            println!(
+                "Dry run: skipping deletion of the Ansible inventory for {}",
+                self.environment_name
+            );
+        } else {
+            cleanup_environment_inventory(
+                &self.environment_name,
+                &self
+                    .working_directory_path
+                    .join("ansible")
+                    .join("inventory"),
+                None,
+            )?;
+            println!("Deleted Ansible inventory for {}", self.environment_name);
+        }
 
         if let Err(err) = self
-            .s3_repository
-            .delete_object("sn-environment-type", &self.environment_name)
+            .object_store
+            .delete_object(Bucket::EnvironmentDetails, &self.environment_name)
             .await
         {
             println!("Failed to delete environment type: {err}. Continuing cleanup...");
@@ -1207,8 +1263,8 @@ pub fn get_multiaddr(
 }
 
 pub async fn get_and_extract_archive_from_s3(
-    s3_repository: &S3Repository,
-    bucket_name: &str,
+    object_store: &dyn ObjectStore,
+    bucket: Bucket,
     archive_bucket_path: &str,
     dest_path: &Path,
 ) -> Result<()> {
@@ -1216,8 +1272,8 @@ pub async fn get_and_extract_archive_from_s3(
     // doesn't seem very valuable.
     let archive_file_name = archive_bucket_path.split('/').next_back().unwrap();
     let archive_dest_path = dest_path.join(archive_file_name);
-    s3_repository
-        .download_object(bucket_name, archive_bucket_path, &archive_dest_path)
+    object_store
+        .download_object(bucket, archive_bucket_path, &archive_dest_path)
         .await?;
     extract_archive(&archive_dest_path, dest_path)?;
     Ok(())
@@ -1256,7 +1312,12 @@ pub fn run_external_command(
     command.stdout(Stdio::piped());
     command.stderr(Stdio::piped());
     command.current_dir(working_directory_path.clone());
-    debug!("Running {binary_path:#?} with args {args:#?}");
+    debug!(
+        "Running {binary_path:#?} with args {:#?}",
+        args.iter()
+            .map(|arg| secrets::redact(arg))
+            .collect::<Vec<_>>()
+    );
     debug!("Working directory set to {working_directory_path:#?}");
 
     let mut child = command.spawn()?;
@@ -1319,9 +1380,6 @@ pub fn get_wallet_directory() -> Result<PathBuf> {
 }
 
 pub async fn notify_slack(inventory: DeploymentInventory) -> Result<()> {
-    let webhook_url =
-        std::env::var("SLACK_WEBHOOK_URL").map_err(|_| Error::SlackWebhookUrlNotSupplied)?;
-
     let mut message = String::new();
     message.push_str("*Testnet Details*\n");
     message.push_str(&format!("Name: {}\n", inventory.name));
@@ -1378,6 +1436,16 @@ pub async fn notify_slack(inventory: DeploymentInventory) -> Result<()> {
     }
     message.push_str("```\n");
 
+    post_slack_message(&message).await?;
+    println!("{message}");
+    println!("Posted notification to Slack");
+    Ok(())
+}
+
+/// Post a message to the Slack channel for the webhook in the `SLACK_WEBHOOK_URL` variable.
+pub async fn post_slack_message(message: &str) -> Result<()> {
+    let webhook_url =
+        std::env::var("SLACK_WEBHOOK_URL").map_err(|_| Error::SlackWebhookUrlNotSupplied)?;
     let payload = json!({
         "text": message,
     });
@@ -1386,8 +1454,6 @@ pub async fn notify_slack(inventory: DeploymentInventory) -> Result<()> {
         .json(&payload)
         .send()
         .await?;
-    println!("{message}");
-    println!("Posted notification to Slack");
     Ok(())
 }
 
@@ -1411,7 +1477,7 @@ pub fn get_progress_bar(length: u64) -> Result<ProgressBar> {
 
 pub async fn get_environment_details(
     environment_name: &str,
-    s3_repository: &S3Repository,
+    object_store: &dyn ObjectStore,
 ) -> Result<EnvironmentDetails> {
     let temp_file = tempfile::NamedTempFile::new()?;
 
@@ -1419,8 +1485,12 @@ pub async fn get_environment_details(
     let mut retries = 0;
     let env_details = loop {
         debug!("Downloading the environment details file for {environment_name} from S3");
-        match s3_repository
-            .download_object("sn-environment-type", environment_name, temp_file.path())
+        match object_store
+            .download_object(
+                Bucket::EnvironmentDetails,
+                environment_name,
+                temp_file.path(),
+            )
             .await
         {
             Ok(_) => {
@@ -1480,7 +1550,7 @@ pub async fn get_environment_details(
 }
 
 pub async fn write_environment_details(
-    s3_repository: &S3Repository,
+    object_store: &dyn ObjectStore,
     environment_name: &str,
     environment_details: &EnvironmentDetails,
 ) -> Result<()> {
@@ -1489,12 +1559,30 @@ pub async fn write_environment_details(
     let mut file = File::create(&path)?;
     let json = serde_json::to_string(environment_details)?;
     file.write_all(json.as_bytes())?;
-    s3_repository
-        .upload_file("sn-environment-type", &path, true)
+    object_store
+        .upload_file(Bucket::EnvironmentDetails, &path, true)
         .await?;
     Ok(())
 }
 
+/// Get the owner to record in the details of a new environment.
+///
+/// If no owner is supplied, the user running the deployment is the owner.
+pub fn get_environment_owner(owner: Option<String>) -> Option<String> {
+    owner.or_else(|| std::env::var("USER").ok())
+}
+
+/// Get the expiry time to record in the details of a new environment, in RFC 3339 format.
+pub fn get_environment_expiry_time(
+    created_at: DateTime<Utc>,
+    lifetime: Option<Duration>,
+) -> Option<String> {
+    let lifetime = chrono::Duration::from_std(lifetime?).ok()?;
+    created_at
+        .checked_add_signed(lifetime)
+        .map(|expires_at| expires_at.to_rfc3339())
+}
+
 pub fn calculate_size_per_attached_volume(node_count: u16) -> u16 {
     if node_count == 0 {
         return 0;
@@ -0,0 +1,239 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crate::{
+    error::{Error, Result},
+    get_environment_owner,
+    logs::line::parse_rfc3339,
+    object_store::{get_object_store, Bucket},
+};
+use chrono::Utc;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+/// A lock whose heartbeat is older than this is assumed to belong to a process that was killed.
+const STALE_LOCK_AGE: Duration = Duration::from_secs(10 * 60);
+/// The time to wait before reading the lock back, to detect another process that acquired it at
+/// the same time.
+const ACQUIRE_SETTLE_TIME: Duration = Duration::from_secs(2);
+
+const LOCK_KEY_PREFIX: &str = "testnet-locks/";
+
+fn get_lock_key(name: &str) -> String {
+    format!("{LOCK_KEY_PREFIX}{name}.json")
+}
+
+/// The lock object for an environment, which is held while a command changes the environment.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EnvironmentLock {
+    /// Identifies the process that holds the lock.
+    pub id: String,
+    pub environment_name: String,
+    pub owner: String,
+    pub command: String,
+    /// The time the lock was acquired, in RFC 3339 format.
+    pub started_at: String,
+    /// The time the holder last confirmed it was still running, in RFC 3339 format.
+    pub heartbeat_at: String,
+}
+
+impl EnvironmentLock {
+    pub fn is_stale(&self) -> bool {
+        parse_rfc3339(&self.heartbeat_at).is_none_or(|heartbeat_at| {
+            (Utc::now() - heartbeat_at).to_std().unwrap_or_default() > STALE_LOCK_AGE
+        })
+    }
+
+    pub fn print(&self) {
+        println!("Environment: {}", self.environment_name);
+        println!("Owner: {}", self.owner);
+        println!("Command: {}", self.command);
+        println!("Started: {}", self.started_at);
+        println!(
+            "Last heartbeat: {}{}",
+            self.heartbeat_at,
+            if self.is_stale() { " (stale)" } else { "" }
+        );
+    }
+}
+
+/// Get the lock for an environment, or `None` if it is not locked.
+pub async fn get_environment_lock(name: &str) -> Result<Option<EnvironmentLock>> {
+    let object_store = get_object_store()?;
+    let Some(contents) = object_store
+        .get_object_contents(Bucket::Testnet, &get_lock_key(name))
+        .await?
+    else {
+        return Ok(None);
+    };
+    Ok(Some(serde_json::from_slice(&contents)?))
+}
+
+/// Get the locks for every environment that is being changed, ignoring any that are stale.
+pub async fn get_held_environment_locks() -> Result<Vec<EnvironmentLock>> {
+    let object_store = get_object_store()?;
+    let mut locks = Vec::new();
+    for key in object_store
+        .list_object_keys(Bucket::Testnet, LOCK_KEY_PREFIX)
+        .await?
+    {
+        let Some(contents) = object_store
+            .get_object_contents(Bucket::Testnet, &key)
+            .await?
+        else {
+            continue;
+        };
+        let lock: EnvironmentLock = serde_json::from_slice(&contents)?;
+        if !lock.is_stale() {
+            locks.push(lock);
+        }
+    }
+    Ok(locks)
+}
+
+/// Remove the lock for an environment.
+///
+/// A lock that is still being held is only removed if `force` is set.
+pub async fn break_environment_lock(name: &str, force: bool) -> Result<()> {
+    let Some(lock) = get_environment_lock(name).await? else {
+        return Err(Error::EnvironmentNotLocked(name.to_string()));
+    };
+    if !lock.is_stale() && !force {
+        return Err(Error::EnvironmentLocked {
+            name: name.to_string(),
+            owner: lock.owner,
+            command: lock.command,
+            started_at: lock.started_at,
+        });
+    }
+    get_object_store()?
+        .delete_object(Bucket::Testnet, &get_lock_key(name))
+        .await
+}
+
+/// A lock on an environment that is held by this process.
+///
+/// The heartbeat is updated in the background until the lock is released. If the process exits
+/// without releasing it, the lock becomes stale and can be taken by the next command.
+pub struct EnvironmentLockGuard {
+    heartbeat_handle: JoinHandle<()>,
+    lock: EnvironmentLock,
+}
+
+impl EnvironmentLockGuard {
+    /// Acquire the lock for an environment, on behalf of the named command.
+    ///
+    /// S3 doesn't support a conditional write, so the lock is read back after it is written, to
+    /// detect another process that acquired it at the same time.
+    pub async fn acquire(name: &str, command: &str) -> Result<Self> {
+        if let Some(existing) = get_environment_lock(name).await? {
+            if !existing.is_stale() {
+                return Err(Error::EnvironmentLocked {
+                    name: name.to_string(),
+                    owner: existing.owner,
+                    command: existing.command,
+                    started_at: existing.started_at,
+                });
+            }
+            println!(
+                "Taking over the stale lock on {name} held by {} for the {} command",
+                existing.owner, existing.command
+            );
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let lock = EnvironmentLock {
+            id: format!("{:016x}", rand::thread_rng().gen::<u64>()),
+            environment_name: name.to_string(),
+            owner: get_environment_owner(None).unwrap_or("unknown".to_string()),
+            command: command.to_string(),
+            started_at: now.clone(),
+            heartbeat_at: now,
+        };
+        write_lock(&lock).await?;
+
+        tokio::time::sleep(ACQUIRE_SETTLE_TIME).await;
+        match get_environment_lock(name).await? {
+            Some(current) if current.id == lock.id => {}
+            Some(current) => {
+                return Err(Error::EnvironmentLocked {
+                    name: name.to_string(),
+                    owner: current.owner,
+                    command: current.command,
+                    started_at: current.started_at,
+                });
+            }
+            None => return Err(Error::EnvironmentNotLocked(name.to_string())),
+        }
+        println!("Acquired the lock on {name}");
+
+        let heartbeat_handle = tokio::spawn(run_heartbeat(lock.clone()));
+        Ok(Self {
+            heartbeat_handle,
+            lock,
+        })
+    }
+
+    /// Stop the heartbeat and remove the lock, unless it was broken and taken by someone else.
+    pub async fn release(self) -> Result<()> {
+        self.heartbeat_handle.abort();
+        let name = &self.lock.environment_name;
+        match get_environment_lock(name).await? {
+            Some(current) if current.id == self.lock.id => {
+                get_object_store()?
+                    .delete_object(Bucket::Testnet, &get_lock_key(name))
+                    .await?;
+                println!("Released the lock on {name}");
+            }
+            _ => println!("The lock on {name} was broken while it was held"),
+        }
+        Ok(())
+    }
+}
+
+async fn write_lock(lock: &EnvironmentLock) -> Result<()> {
+    get_object_store()?
+        .put_object_contents(
+            Bucket::Testnet,
+            &get_lock_key(&lock.environment_name),
+            serde_json::to_vec(lock)?,
+        )
+        .await
+}
+
+// The heartbeat stops if the lock is broken, so that a broken lock isn't recreated.
+async fn run_heartbeat(mut lock: EnvironmentLock) {
+    loop {
+        tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+        match get_environment_lock(&lock.environment_name).await {
+            Ok(Some(current)) if current.id == lock.id => {}
+            Ok(_) => {
+                println!(
+                    "The lock on {} was broken; its heartbeat has stopped",
+                    lock.environment_name
+                );
+                return;
+            }
+            Err(err) => {
+                println!(
+                    "Failed to read the lock on {}: {err}",
+                    lock.environment_name
+                );
+                continue;
+            }
+        }
+        lock.heartbeat_at = Utc::now().to_rfc3339();
+        if let Err(err) = write_lock(&lock).await {
+            println!(
+                "Failed to update the heartbeat for the lock on {}: {err}",
+                lock.environment_name
+            );
+        }
+    }
+}
@@ -0,0 +1,414 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use super::{
+    index::{build_log_index, get_log_dir, open_log_file},
+    line::parse_rfc3339,
+};
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::{BufRead, Write},
+};
+
+const LEVELS: [&str; 5] = ["ERROR", "WARN", "INFO", "DEBUG", "TRACE"];
+const TOP_TARGET_COUNT: usize = 20;
+
+#[derive(Clone, Copy, Debug)]
+pub enum AnalysisFormat {
+    Csv,
+    Json,
+    Table,
+}
+
+/// The key each record is counted under.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dimension {
+    Level,
+    Target,
+    NodeType,
+    Vm,
+}
+
+impl std::fmt::Display for Dimension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dimension::Level => write!(f, "level"),
+            Dimension::Target => write!(f, "target"),
+            Dimension::NodeType => write!(f, "node_type"),
+            Dimension::Vm => write!(f, "vm"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AnalysisRow {
+    /// The start of the time bucket, in RFC 3339 format.
+    pub bucket_start: String,
+    pub dimension: Dimension,
+    pub key: String,
+    pub count: u64,
+}
+
+/// Counts of the JSON log records for an environment, grouped into time buckets.
+#[derive(Clone, Debug, Default)]
+pub struct LogAnalysis {
+    pub bucket_size_secs: i64,
+    /// Counts keyed by the bucket start, as a Unix timestamp, then the dimension and key.
+    pub counts: BTreeMap<(i64, Dimension, String), u64>,
+    pub record_count: u64,
+    /// Lines that were not JSON records, e.g., from nodes using the default log format.
+    pub skipped_line_count: u64,
+}
+
+impl LogAnalysis {
+    fn merge(mut self, other: LogAnalysis) -> LogAnalysis {
+        for (key, count) in other.counts {
+            *self.counts.entry(key).or_insert(0) += count;
+        }
+        self.record_count += other.record_count;
+        self.skipped_line_count += other.skipped_line_count;
+        self
+    }
+
+    pub fn rows(&self) -> Vec<AnalysisRow> {
+        self.counts
+            .iter()
+            .map(|((bucket, dimension, key), count)| AnalysisRow {
+                bucket_start: bucket_start_to_string(*bucket),
+                dimension: *dimension,
+                key: key.clone(),
+                count: *count,
+            })
+            .collect()
+    }
+
+    pub fn write(&self, format: AnalysisFormat, writer: &mut dyn Write) -> Result<()> {
+        match format {
+            AnalysisFormat::Csv => {
+                writeln!(writer, "bucket_start,dimension,key,count")?;
+                for row in self.rows() {
+                    writeln!(
+                        writer,
+                        "{},{},\"{}\",{}",
+                        row.bucket_start,
+                        row.dimension,
+                        row.key.replace('"', "\"\""),
+                        row.count
+                    )?;
+                }
+            }
+            AnalysisFormat::Json => {
+                serde_json::to_writer_pretty(&mut *writer, &self.rows())?;
+                writeln!(writer)?;
+            }
+            AnalysisFormat::Table => self.write_table(writer)?,
+        }
+        Ok(())
+    }
+
+    fn totals(&self, dimension: Dimension) -> Vec<(String, u64)> {
+        let mut totals = BTreeMap::new();
+        for ((_, d, key), count) in self.counts.iter() {
+            if *d == dimension {
+                *totals.entry(key.clone()).or_insert(0) += count;
+            }
+        }
+        let mut totals = totals.into_iter().collect::<Vec<_>>();
+        totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        totals
+    }
+
+    fn write_table(&self, writer: &mut dyn Write) -> Result<()> {
+        writeln!(
+            writer,
+            "==== Records per {}s bucket ====",
+            self.bucket_size_secs
+        )?;
+        write!(writer, "{:<26}", "bucket")?;
+        for level in LEVELS {
+            write!(writer, "{level:>10}")?;
+        }
+        writeln!(writer, "{:>10}", "total")?;
+
+        let buckets = self
+            .counts
+            .keys()
+            .map(|(bucket, _, _)| *bucket)
+            .collect::<BTreeSet<_>>();
+        for bucket in buckets {
+            write!(writer, "{:<26}", bucket_start_to_string(bucket))?;
+            let mut total = 0;
+            for level in LEVELS {
+                let count = self
+                    .counts
+                    .get(&(bucket, Dimension::Level, level.to_string()))
+                    .copied()
+                    .unwrap_or_default();
+                total += count;
+                write!(writer, "{count:>10}")?;
+            }
+            writeln!(writer, "{total:>10}")?;
+        }
+
+        writeln!(writer, "==== Top {TOP_TARGET_COUNT} targets ====")?;
+        for (target, count) in self.totals(Dimension::Target).iter().take(TOP_TARGET_COUNT) {
+            writeln!(writer, "{count:>10} {target}")?;
+        }
+        writeln!(writer, "==== Records per node type ====")?;
+        for (node_type, count) in self.totals(Dimension::NodeType) {
+            writeln!(writer, "{count:>10} {node_type}")?;
+        }
+        writeln!(writer, "==== Records per VM ====")?;
+        for (vm, count) in self.totals(Dimension::Vm) {
+            writeln!(writer, "{count:>10} {vm}")?;
+        }
+        writeln!(writer, "Total records: {}", self.record_count)?;
+        if self.skipped_line_count > 0 {
+            writeln!(
+                writer,
+                "Skipped {} lines that were not JSON records",
+                self.skipped_line_count
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Count the JSON records in the retrieved logs, per level, target, node type and VM, within
+/// buckets of the given size.
+///
+/// Only nodes that were deployed with the JSON log format produce records that can be analysed.
+pub fn analyze_logs(
+    name: &str,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    bucket_size_secs: u64,
+) -> Result<LogAnalysis> {
+    let bucket_size_secs = std::cmp::max(bucket_size_secs, 1) as i64;
+    let log_dir = get_log_dir(name)?;
+    let index = build_log_index(name)?;
+    let files = index
+        .files
+        .into_iter()
+        .filter(|file| file.overlaps(from, to))
+        .collect::<Vec<_>>();
+
+    let analyses = files
+        .into_par_iter()
+        .map(|file| -> Result<LogAnalysis> {
+            let node_type = file.node_type.clone().unwrap_or_else(|| "none".to_string());
+            let reader = open_log_file(&log_dir.join(&file.path))?;
+            count_records(
+                reader,
+                &node_type,
+                &file.vm_name,
+                from,
+                to,
+                bucket_size_secs,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(analyses.into_iter().fold(
+        LogAnalysis {
+            bucket_size_secs,
+            ..Default::default()
+        },
+        LogAnalysis::merge,
+    ))
+}
+
+// Count the records from a single log file.
+fn count_records(
+    reader: impl BufRead,
+    node_type: &str,
+    vm_name: &str,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    bucket_size_secs: i64,
+) -> Result<LogAnalysis> {
+    let mut analysis = LogAnalysis {
+        bucket_size_secs,
+        ..Default::default()
+    };
+    for line in reader.lines() {
+        let line = line?;
+        let Some(record) = parse_json_record(&line) else {
+            analysis.skipped_line_count += 1;
+            continue;
+        };
+        let (timestamp, level, target) = record;
+        if from.is_some_and(|from| timestamp < from) || to.is_some_and(|to| timestamp > to) {
+            continue;
+        }
+
+        let bucket = timestamp.timestamp() - timestamp.timestamp().rem_euclid(bucket_size_secs);
+        for (dimension, key) in [
+            (Dimension::Level, level),
+            (Dimension::Target, target),
+            (Dimension::NodeType, node_type.to_string()),
+            (Dimension::Vm, vm_name.to_string()),
+        ] {
+            *analysis.counts.entry((bucket, dimension, key)).or_insert(0) += 1;
+        }
+        analysis.record_count += 1;
+    }
+    Ok(analysis)
+}
+
+fn parse_json_record(line: &str) -> Option<(DateTime<Utc>, String, String)> {
+    if !line.trim_start().starts_with('{') {
+        return None;
+    }
+    let value: serde_json::Value = serde_json::from_str(line).ok()?;
+    let timestamp = value
+        .get("timestamp")
+        .and_then(|t| t.as_str())
+        .and_then(parse_rfc3339)?;
+    let level = value
+        .get("level")
+        .and_then(|l| l.as_str())
+        .unwrap_or("UNKNOWN")
+        .to_uppercase();
+    let target = value
+        .get("target")
+        .and_then(|t| t.as_str())
+        .unwrap_or("unknown")
+        .to_string();
+    Some((timestamp, level, target))
+}
+
+fn bucket_start_to_string(bucket: i64) -> String {
+    DateTime::from_timestamp(bucket, 0)
+        .map(|t| t.to_rfc3339())
+        .unwrap_or_else(|| bucket.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    const RECORDS: &str = r#"{"timestamp":"2024-10-10T10:00:05Z","level":"INFO","target":"ant_node::node"}
+{"timestamp":"2024-10-10T10:00:59Z","level":"warn","target":"ant_networking"}
+[2024-10-10T10:01:00Z INFO ant_node::node] not a json record
+{"timestamp":"2024-10-10T10:01:10Z","level":"INFO","target":"ant_node::node"}
+{"level":"INFO","target":"ant_node::node"}
+"#;
+
+    fn count(node_type: &str, vm_name: &str, from: Option<&str>, to: Option<&str>) -> LogAnalysis {
+        count_records(
+            Cursor::new(RECORDS),
+            node_type,
+            vm_name,
+            from.and_then(parse_rfc3339),
+            to.and_then(parse_rfc3339),
+            60,
+        )
+        .unwrap()
+    }
+
+    fn get_count(analysis: &LogAnalysis, bucket: &str, dimension: Dimension, key: &str) -> u64 {
+        let bucket = parse_rfc3339(bucket).unwrap().timestamp();
+        analysis
+            .counts
+            .get(&(bucket, dimension, key.to_string()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_records_are_counted_per_bucket_and_dimension() {
+        let analysis = count("generic", "alpha-node-1", None, None);
+        assert_eq!(analysis.record_count, 3);
+        assert_eq!(analysis.skipped_line_count, 2);
+
+        let first = "2024-10-10T10:00:00Z";
+        let second = "2024-10-10T10:01:00Z";
+        assert_eq!(get_count(&analysis, first, Dimension::Level, "INFO"), 1);
+        assert_eq!(get_count(&analysis, first, Dimension::Level, "WARN"), 1);
+        assert_eq!(get_count(&analysis, second, Dimension::Level, "INFO"), 1);
+        assert_eq!(
+            get_count(&analysis, first, Dimension::Target, "ant_networking"),
+            1
+        );
+        assert_eq!(
+            get_count(&analysis, first, Dimension::NodeType, "generic"),
+            2
+        );
+        assert_eq!(
+            get_count(&analysis, second, Dimension::Vm, "alpha-node-1"),
+            1
+        );
+    }
+
+    #[test]
+    fn test_records_outside_the_window_are_not_counted() {
+        let analysis = count(
+            "generic",
+            "alpha-node-1",
+            Some("2024-10-10T10:00:30Z"),
+            Some("2024-10-10T10:01:00Z"),
+        );
+        assert_eq!(analysis.record_count, 1);
+        assert_eq!(
+            get_count(
+                &analysis,
+                "2024-10-10T10:00:00Z",
+                Dimension::Target,
+                "ant_networking"
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn test_merged_analyses_sum_the_counts() {
+        let analysis = count("generic", "alpha-node-1", None, None).merge(count(
+            "generic",
+            "alpha-node-2",
+            None,
+            None,
+        ));
+        assert_eq!(analysis.record_count, 6);
+        assert_eq!(analysis.skipped_line_count, 4);
+        assert_eq!(
+            get_count(
+                &analysis,
+                "2024-10-10T10:00:00Z",
+                Dimension::NodeType,
+                "generic"
+            ),
+            4
+        );
+        assert_eq!(
+            get_count(
+                &analysis,
+                "2024-10-10T10:00:00Z",
+                Dimension::Vm,
+                "alpha-node-2"
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn test_csv_output_has_a_row_per_count() {
+        let analysis = count("generic", "alpha-node-1", None, None);
+        let mut output = Vec::new();
+        analysis.write(AnalysisFormat::Csv, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines = output.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "bucket_start,dimension,key,count");
+        assert_eq!(lines.len(), analysis.counts.len() + 1);
+        assert!(lines.contains(&"2024-10-10T10:00:00+00:00,level,\"WARN\",1"));
+    }
+}
@@ -0,0 +1,344 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use super::{
+    index::{build_log_index, get_log_dir, IndexedLogFile},
+    line::parse_rfc3339,
+};
+use crate::{
+    error::{Error, Result},
+    object_store::{get_object_store, Bucket},
+    NodeType,
+};
+use chrono::{DateTime, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::File,
+    path::Path,
+};
+use tar::Archive;
+
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+
+/// Describes the log archives that were uploaded for an environment, so that only the relevant
+/// archives need to be retrieved.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogArchiveManifest {
+    pub environment_name: String,
+    pub created_at: String,
+    pub archives: Vec<LogArchive>,
+}
+
+/// A compressed archive of the logs from a single VM.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogArchive {
+    pub vm_name: String,
+    pub node_type: Option<String>,
+    pub services: BTreeSet<String>,
+    /// The timestamp of the earliest entry in the archive, in RFC 3339 format.
+    pub start: Option<String>,
+    /// The timestamp of the latest entry in the archive, in RFC 3339 format.
+    pub end: Option<String>,
+    pub file_count: usize,
+    pub uncompressed_size: u64,
+    pub archive_size: u64,
+    pub object_key: String,
+}
+
+impl LogArchive {
+    fn matches(&self, filter: &LogArchiveFilter) -> bool {
+        if let Some(vm) = &filter.vm {
+            if !self.vm_name.contains(vm) {
+                return false;
+            }
+        }
+        if let Some(node_type) = &filter.node_type {
+            if self.node_type.as_deref() != Some(node_type.to_string().as_str()) {
+                return false;
+            }
+        }
+        let start = self.start.as_deref().and_then(parse_rfc3339);
+        let end = self.end.as_deref().and_then(parse_rfc3339);
+        if let (Some(from), Some(end)) = (filter.from, end) {
+            if end < from {
+                return false;
+            }
+        }
+        if let (Some(to), Some(start)) = (filter.to, start) {
+            if start > to {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Selects which archives are retrieved. An archive is selected if it matches all the supplied
+/// criteria.
+#[derive(Clone, Debug, Default)]
+pub struct LogArchiveFilter {
+    /// Only archives for VMs whose name contains this string.
+    pub vm: Option<String>,
+    pub node_type: Option<NodeType>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl LogArchiveFilter {
+    pub fn is_empty(&self) -> bool {
+        self.vm.is_none() && self.node_type.is_none() && self.from.is_none() && self.to.is_none()
+    }
+}
+
+fn get_archive_prefix(name: &str) -> String {
+    format!("testnet-logs/{name}/archives")
+}
+
+/// Compress the logs that have been retrieved to `logs/<name>` into one archive per VM, then
+/// upload the archives to S3 along with a manifest describing them.
+///
+/// The local logs must have been retrieved first. An empty or missing mirror is an error, rather
+/// than uploading an empty manifest over the one for archives uploaded previously.
+pub async fn upload_log_archives(name: &str) -> Result<LogArchiveManifest> {
+    let log_dir = get_log_dir(name).map_err(|err| match err {
+        Error::LogsNotRetrievedError(name) => Error::NoLogsToUpload(name),
+        err => err,
+    })?;
+    let index = build_log_index(name)?;
+    if index.files.is_empty() {
+        return Err(Error::NoLogsToUpload(name.to_string()));
+    }
+    let temp_dir = tempfile::tempdir()?;
+    let prefix = get_archive_prefix(name);
+
+    let mut files_by_vm: BTreeMap<String, Vec<IndexedLogFile>> = BTreeMap::new();
+    for file in index.files {
+        files_by_vm
+            .entry(file.vm_name.clone())
+            .or_default()
+            .push(file);
+    }
+
+    println!("Creating archives for {} VMs...", files_by_vm.len());
+    let archives = files_by_vm
+        .into_par_iter()
+        .map(|(vm_name, files)| -> Result<LogArchive> {
+            let archive_path = temp_dir.path().join(format!("{vm_name}.tar.gz"));
+            create_archive(&log_dir, &files, &archive_path)?;
+            Ok(LogArchive {
+                node_type: files.first().and_then(|f| f.node_type.clone()),
+                services: files.iter().map(|f| f.service.clone()).collect(),
+                start: files.iter().filter_map(|f| f.start.clone()).min(),
+                end: files.iter().filter_map(|f| f.end.clone()).max(),
+                file_count: files.len(),
+                uncompressed_size: files.iter().map(|f| f.size).sum(),
+                archive_size: std::fs::metadata(&archive_path)?.len(),
+                object_key: format!("{prefix}/{vm_name}.tar.gz"),
+                vm_name,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let object_store = get_object_store()?;
+    for archive in archives.iter() {
+        let archive_path = temp_dir.path().join(format!("{}.tar.gz", archive.vm_name));
+        object_store
+            .upload_object(Bucket::Testnet, &archive.object_key, &archive_path, false)
+            .await?;
+    }
+
+    let manifest = LogArchiveManifest {
+        environment_name: name.to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        archives,
+    };
+    let manifest_path = temp_dir.path().join(MANIFEST_FILE_NAME);
+    serde_json::to_writer_pretty(File::create(&manifest_path)?, &manifest)?;
+    object_store
+        .upload_object(
+            Bucket::Testnet,
+            &format!("{prefix}/{MANIFEST_FILE_NAME}"),
+            &manifest_path,
+            false,
+        )
+        .await?;
+
+    Ok(manifest)
+}
+
+/// Retrieve the manifest for the log archives of an environment, or `None` if the logs for the
+/// environment were not uploaded as archives.
+pub async fn get_log_archive_manifest(name: &str) -> Result<Option<LogArchiveManifest>> {
+    let object_store = get_object_store()?;
+    let prefix = get_archive_prefix(name);
+    if !object_store.folder_exists(Bucket::Testnet, &prefix).await? {
+        return Ok(None);
+    }
+
+    let temp_dir = tempfile::tempdir()?;
+    let manifest_path = temp_dir.path().join(MANIFEST_FILE_NAME);
+    object_store
+        .download_object(
+            Bucket::Testnet,
+            &format!("{prefix}/{MANIFEST_FILE_NAME}"),
+            &manifest_path,
+        )
+        .await?;
+    let manifest = serde_json::from_reader(File::open(&manifest_path)?)?;
+    Ok(Some(manifest))
+}
+
+/// Retrieve and extract the archives selected by the filter into `logs/<name>`.
+///
+/// Returns the archives that were retrieved.
+pub async fn get_log_archives(
+    name: &str,
+    manifest: &LogArchiveManifest,
+    filter: &LogArchiveFilter,
+) -> Result<Vec<LogArchive>> {
+    let dest_path = std::env::current_dir()?.join("logs").join(name);
+    std::fs::create_dir_all(&dest_path)?;
+    let temp_dir = tempfile::tempdir()?;
+    let object_store = get_object_store()?;
+
+    let selected = manifest
+        .archives
+        .iter()
+        .filter(|archive| archive.matches(filter))
+        .cloned()
+        .collect::<Vec<_>>();
+    println!(
+        "Retrieving {} of {} archives",
+        selected.len(),
+        manifest.archives.len()
+    );
+    for archive in selected.iter() {
+        let archive_path = temp_dir.path().join(format!("{}.tar.gz", archive.vm_name));
+        object_store
+            .download_object(Bucket::Testnet, &archive.object_key, &archive_path)
+            .await?;
+        let mut tar = Archive::new(GzDecoder::new(File::open(&archive_path)?));
+        tar.unpack(&dest_path)?;
+        std::fs::remove_file(&archive_path)?;
+    }
+    Ok(selected)
+}
+
+// The paths in the archive are relative to `logs/<name>`, so it extracts to the same layout that
+// rsync produces.
+fn create_archive(log_dir: &Path, files: &[IndexedLogFile], archive_path: &Path) -> Result<()> {
+    let encoder = GzEncoder::new(File::create(archive_path)?, Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+    for file in files {
+        builder.append_path_with_name(log_dir.join(&file.path), &file.path)?;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_archive(node_type: Option<&str>, start: Option<&str>, end: Option<&str>) -> LogArchive {
+        LogArchive {
+            vm_name: "alpha-node-1".to_string(),
+            node_type: node_type.map(|t| t.to_string()),
+            services: BTreeSet::from(["antnode1".to_string()]),
+            start: start.map(|s| s.to_string()),
+            end: end.map(|e| e.to_string()),
+            file_count: 1,
+            uncompressed_size: 1024,
+            archive_size: 256,
+            object_key: "testnet-logs/alpha/archives/alpha-node-1.tar.gz".to_string(),
+        }
+    }
+
+    fn get_window_filter(from: Option<&str>, to: Option<&str>) -> LogArchiveFilter {
+        LogArchiveFilter {
+            from: from.and_then(parse_rfc3339),
+            to: to.and_then(parse_rfc3339),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_matches_archive_that_overlaps_the_time_window() {
+        let archive = get_archive(
+            Some("generic"),
+            Some("2024-10-10T10:00:00Z"),
+            Some("2024-10-10T12:00:00Z"),
+        );
+
+        // The window contains the start, the end, or the whole of the archive.
+        for (from, to) in [
+            ("2024-10-10T09:00:00Z", "2024-10-10T11:00:00Z"),
+            ("2024-10-10T11:00:00Z", "2024-10-10T13:00:00Z"),
+            ("2024-10-10T09:00:00Z", "2024-10-10T13:00:00Z"),
+            ("2024-10-10T10:30:00Z", "2024-10-10T11:30:00Z"),
+        ] {
+            assert!(archive.matches(&get_window_filter(Some(from), Some(to))));
+        }
+        // The window touches the start or the end of the archive.
+        assert!(archive.matches(&get_window_filter(None, Some("2024-10-10T10:00:00Z"))));
+        assert!(archive.matches(&get_window_filter(Some("2024-10-10T12:00:00Z"), None)));
+    }
+
+    #[test]
+    fn test_does_not_match_archive_outside_the_time_window() {
+        let archive = get_archive(
+            Some("generic"),
+            Some("2024-10-10T10:00:00Z"),
+            Some("2024-10-10T12:00:00Z"),
+        );
+
+        assert!(!archive.matches(&get_window_filter(
+            Some("2024-10-10T07:00:00Z"),
+            Some("2024-10-10T09:59:59Z")
+        )));
+        assert!(!archive.matches(&get_window_filter(
+            Some("2024-10-10T12:00:01Z"),
+            Some("2024-10-10T14:00:00Z")
+        )));
+        assert!(!archive.matches(&get_window_filter(None, Some("2024-10-10T09:00:00Z"))));
+        assert!(!archive.matches(&get_window_filter(Some("2024-10-10T13:00:00Z"), None)));
+    }
+
+    #[test]
+    fn test_matches_archive_with_a_missing_start_or_end() {
+        // Without a start, the archive can't be excluded by the end of the window.
+        let archive = get_archive(Some("generic"), None, Some("2024-10-10T12:00:00Z"));
+        assert!(archive.matches(&get_window_filter(None, Some("2024-10-10T09:00:00Z"))));
+        assert!(!archive.matches(&get_window_filter(Some("2024-10-10T13:00:00Z"), None)));
+
+        // Without an end, the archive can't be excluded by the start of the window.
+        let archive = get_archive(Some("generic"), Some("2024-10-10T10:00:00Z"), None);
+        assert!(archive.matches(&get_window_filter(Some("2024-10-10T13:00:00Z"), None)));
+        assert!(!archive.matches(&get_window_filter(None, Some("2024-10-10T09:00:00Z"))));
+
+        let archive = get_archive(Some("generic"), None, None);
+        assert!(archive.matches(&get_window_filter(
+            Some("2024-10-10T13:00:00Z"),
+            Some("2024-10-10T14:00:00Z")
+        )));
+    }
+
+    #[test]
+    fn test_matches_archive_by_node_type() {
+        let filter = LogArchiveFilter {
+            node_type: Some(NodeType::PeerCache),
+            ..Default::default()
+        };
+
+        assert!(get_archive(Some("peer-cache"), None, None).matches(&filter));
+        assert!(!get_archive(Some("generic"), None, None).matches(&filter));
+        assert!(!get_archive(None, None, None).matches(&filter));
+        assert!(get_archive(None, None, None).matches(&LogArchiveFilter::default()));
+    }
+}
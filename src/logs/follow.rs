@@ -0,0 +1,205 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use super::create_initial_log_dir_setup;
+use crate::{error::Result, inventory::VirtualMachine, TestnetDeployer};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+pub struct LogFollowOptions {
+    /// Limit the bandwidth used by each rsync process, in KiB per second.
+    pub bandwidth_limit: Option<u64>,
+    pub disable_client_logs: bool,
+    pub interval: Duration,
+    /// The longest time a host that keeps failing will be skipped for.
+    pub max_backoff: Duration,
+    /// Stop after this many passes. Otherwise, follow until the process is stopped.
+    pub max_passes: Option<usize>,
+    pub vm_filter: Option<String>,
+}
+
+#[derive(Default)]
+struct HostBackoff {
+    consecutive_failures: u32,
+    next_attempt: Option<Instant>,
+}
+
+struct TransferStats {
+    files_transferred: u64,
+    bytes_transferred: u64,
+}
+
+enum PassResult {
+    Transferred(TransferStats),
+    Failed(String),
+    Skipped(Duration),
+}
+
+impl TestnetDeployer {
+    /// Keep a local mirror of the logs up to date, by running rsync against every VM at a fixed
+    /// interval.
+    ///
+    /// Only the files that changed since the previous pass are transferred. A host that fails is
+    /// skipped for an exponentially increasing number of passes, up to the maximum backoff, so
+    /// that one unreachable VM doesn't slow down every pass.
+    pub fn follow_logs(&self, name: &str, options: LogFollowOptions) -> Result<()> {
+        // take root_dir at the top as `get_all_node_inventory` changes the working dir.
+        let root_dir = std::env::current_dir()?;
+        let all_inventory =
+            self.get_log_inventory(name, options.vm_filter.clone(), options.disable_client_logs)?;
+        let log_base_dir = create_initial_log_dir_setup(&root_dir, name, &all_inventory)?;
+        std::env::set_current_dir(self.working_directory_path.clone())?;
+
+        let rsync_args = self
+            .get_rsync_args(&all_inventory, &log_base_dir)?
+            .into_iter()
+            .map(|(vm, mut args)| {
+                args.insert(0, "--stats".to_string());
+                if let Some(limit) = options.bandwidth_limit {
+                    args.insert(0, format!("--bwlimit={limit}"));
+                }
+                (vm, args)
+            })
+            .collect::<Vec<_>>();
+
+        let mut backoffs: HashMap<String, HostBackoff> = HashMap::new();
+        let mut pass = 0;
+        loop {
+            pass += 1;
+            let pass_started = Instant::now();
+            println!("===== Log follow pass {pass} =====");
+
+            let results = rsync_args
+                .par_iter()
+                .map(|(vm, args)| {
+                    let backoff = backoffs.get(&vm.name);
+                    if let Some(next_attempt) = backoff.and_then(|b| b.next_attempt) {
+                        let now = Instant::now();
+                        if next_attempt > now {
+                            return (vm, PassResult::Skipped(next_attempt - now));
+                        }
+                    }
+                    match Self::run_rsync(vm, args) {
+                        Ok(output) => (vm, PassResult::Transferred(parse_rsync_stats(&output))),
+                        Err(err) => (vm, PassResult::Failed(err.to_string())),
+                    }
+                })
+                .collect::<Vec<_>>();
+
+            for (vm, result) in results.iter() {
+                let backoff = backoffs.entry(vm.name.clone()).or_default();
+                match result {
+                    PassResult::Transferred(_) => *backoff = HostBackoff::default(),
+                    PassResult::Failed(_) => {
+                        backoff.consecutive_failures += 1;
+                        let delay = options
+                            .interval
+                            .saturating_mul(2u32.saturating_pow(backoff.consecutive_failures - 1))
+                            .min(options.max_backoff);
+                        backoff.next_attempt = Some(Instant::now() + delay);
+                    }
+                    PassResult::Skipped(_) => {}
+                }
+            }
+            print_pass_summary(&results, &backoffs, pass_started.elapsed());
+
+            if options.max_passes.is_some_and(|max| pass >= max) {
+                break;
+            }
+            let sleep_time = options.interval.saturating_sub(pass_started.elapsed());
+            println!("Next pass in {sleep_time:?}");
+            std::thread::sleep(sleep_time);
+        }
+
+        Ok(())
+    }
+}
+
+fn print_pass_summary(
+    results: &[(&VirtualMachine, PassResult)],
+    backoffs: &HashMap<String, HostBackoff>,
+    elapsed: Duration,
+) {
+    let mut total_files = 0;
+    let mut total_bytes = 0;
+    let mut failed = 0;
+    let mut skipped = 0;
+    for (vm, result) in results.iter() {
+        match result {
+            PassResult::Transferred(stats) => {
+                total_files += stats.files_transferred;
+                total_bytes += stats.bytes_transferred;
+                if stats.files_transferred > 0 {
+                    println!(
+                        "{}: {} files, {} bytes",
+                        vm.name, stats.files_transferred, stats.bytes_transferred
+                    );
+                }
+            }
+            PassResult::Failed(err) => {
+                failed += 1;
+                let failures = backoffs
+                    .get(&vm.name)
+                    .map(|b| b.consecutive_failures)
+                    .unwrap_or_default();
+                println!(
+                    "{}: failed ({failures} consecutive failures): {err}",
+                    vm.name
+                );
+            }
+            PassResult::Skipped(remaining) => {
+                skipped += 1;
+                println!(
+                    "{}: skipped, backing off for another {:?}",
+                    vm.name,
+                    Duration::from_secs(remaining.as_secs())
+                );
+            }
+        }
+    }
+    println!("==== Pass Summary ====");
+    println!(
+        "Synced {} of {} VMs in {:?}",
+        results.len() - failed - skipped,
+        results.len(),
+        Duration::from_secs(elapsed.as_secs())
+    );
+    println!("Transferred {total_files} files, {total_bytes} bytes");
+    if failed > 0 {
+        println!("Failed: {failed}");
+    }
+    if skipped > 0 {
+        println!("Skipped due to backoff: {skipped}");
+    }
+}
+
+// Parse the output of `rsync --stats`, e.g.:
+//   Number of regular files transferred: 3
+//   Total transferred file size: 1,234,567 bytes
+fn parse_rsync_stats(output: &[String]) -> TransferStats {
+    let get_value = |prefix: &str| {
+        output
+            .iter()
+            .find(|line| line.starts_with(prefix))
+            .and_then(|line| line.split(':').nth(1))
+            .map(|value| {
+                value
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .replace(',', "")
+            })
+            .and_then(|value| value.parse::<u64>().ok())
+            .unwrap_or_default()
+    };
+    TransferStats {
+        files_transferred: get_value("Number of regular files transferred"),
+        bytes_transferred: get_value("Total transferred file size"),
+    }
+}
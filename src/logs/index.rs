@@ -0,0 +1,437 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use super::line::{parse_rfc3339, parse_timestamp};
+use crate::{
+    error::{Error, Result},
+    NodeType,
+};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use log::debug;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+use walkdir::WalkDir;
+
+pub const LOG_INDEX_FILE_NAME: &str = "index.json";
+
+/// The number of bytes read from the end of an uncompressed file to find its last timestamp.
+const TAIL_READ_SIZE: u64 = 64 * 1024;
+
+/// An index of the logs that have been retrieved for an environment.
+///
+/// It records the time range covered by each file, which allows searches to skip files that
+/// can't contain any entries within the requested window.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LogIndex {
+    pub environment_name: String,
+    pub generated_at: String,
+    pub files: Vec<IndexedLogFile>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexedLogFile {
+    pub vm_name: String,
+    /// The type of node running on the VM, or `None` if the VM doesn't run nodes.
+    pub node_type: Option<String>,
+    /// The service that wrote the file, which is taken from the name of its directory.
+    pub service: String,
+    /// The path of the file, relative to the `logs/<name>` directory.
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: u64,
+    /// The timestamp of the first entry, in RFC 3339 format.
+    pub start: Option<String>,
+    /// The timestamp of the last entry, in RFC 3339 format.
+    pub end: Option<String>,
+}
+
+impl IndexedLogFile {
+    /// Returns whether the file could contain entries within the given window.
+    ///
+    /// Files whose range could not be determined are always considered relevant.
+    pub fn overlaps(&self, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> bool {
+        let start = self.start.as_deref().and_then(parse_rfc3339);
+        let end = self.end.as_deref().and_then(parse_rfc3339);
+        if let (Some(from), Some(end)) = (from, end) {
+            if end < from {
+                return false;
+            }
+        }
+        if let (Some(to), Some(start)) = (to, start) {
+            if start > to {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn is_node_type(&self, node_type: &NodeType) -> bool {
+        self.node_type.as_deref() == Some(node_type.to_string().as_str())
+    }
+}
+
+impl LogIndex {
+    pub fn read(path: &Path) -> Result<LogIndex> {
+        let file = File::open(path)?;
+        let index = serde_json::from_reader(file)?;
+        Ok(index)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = File::create(path)?;
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    pub fn print_report(&self) {
+        let mut vms: HashMap<&str, (usize, u64)> = HashMap::new();
+        for file in self.files.iter() {
+            let entry = vms.entry(&file.vm_name).or_default();
+            entry.0 += 1;
+            entry.1 += file.size;
+        }
+        let mut vms = vms.into_iter().collect::<Vec<_>>();
+        vms.sort_by(|a, b| a.0.cmp(b.0));
+
+        println!("==== Log Index for {} ====", self.environment_name);
+        for (vm_name, (file_count, size)) in vms {
+            println!("{vm_name}: {file_count} files, {size} bytes");
+        }
+        let start = self.files.iter().filter_map(|f| f.start.as_ref()).min();
+        let end = self.files.iter().filter_map(|f| f.end.as_ref()).max();
+        if let (Some(start), Some(end)) = (start, end) {
+            println!("Time range: {start} to {end}");
+        }
+        println!("Total files: {}", self.files.len());
+    }
+}
+
+/// A line that matched a search, annotated with where it came from.
+#[derive(Clone, Debug, Serialize)]
+pub struct LogMatch {
+    pub vm_name: String,
+    pub service: String,
+    pub timestamp: Option<String>,
+    pub line: String,
+}
+
+/// Returns the path of the `logs/<name>` directory the logs were retrieved to.
+pub fn get_log_dir(name: &str) -> Result<PathBuf> {
+    let log_dir = std::env::current_dir()?.join("logs").join(name);
+    if !log_dir.exists() {
+        return Err(Error::LogsNotRetrievedError(name.to_string()));
+    }
+    Ok(log_dir)
+}
+
+/// Build or refresh the index for the logs at `logs/<name>`.
+///
+/// Files that are unchanged since the previous index was built are not read again.
+pub fn build_log_index(name: &str) -> Result<LogIndex> {
+    let log_dir = get_log_dir(name)?;
+    let index_path = log_dir.join(LOG_INDEX_FILE_NAME);
+    let previous = if index_path.exists() {
+        LogIndex::read(&index_path)
+            .map(|index| {
+                index
+                    .files
+                    .into_iter()
+                    .map(|file| (file.path.clone(), file))
+                    .collect::<HashMap<_, _>>()
+            })
+            .unwrap_or_default()
+    } else {
+        HashMap::new()
+    };
+
+    let paths = WalkDir::new(&log_dir)
+        .min_depth(2)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.into_path())
+        .filter(|path| is_log_file(path))
+        .collect::<Vec<_>>();
+
+    let mut files = paths
+        .into_par_iter()
+        .map(|path| -> Result<IndexedLogFile> {
+            let relative_path = path.strip_prefix(&log_dir)?.to_path_buf();
+            let metadata = std::fs::metadata(&path)?;
+            let size = metadata.len();
+            let modified = metadata
+                .modified()?
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default();
+            if let Some(file) = previous.get(&relative_path) {
+                if file.size == size && file.modified == modified {
+                    return Ok(file.clone());
+                }
+            }
+
+            debug!("Indexing {}", path.to_string_lossy());
+            let vm_name = relative_path
+                .components()
+                .next()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .unwrap_or_default();
+            let service = path
+                .parent()
+                .filter(|parent| *parent != log_dir.join(&vm_name))
+                .and_then(|parent| parent.file_name())
+                .map(|service| service.to_string_lossy().to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            let (start, end) = get_time_range(&path)?;
+            Ok(IndexedLogFile {
+                node_type: get_node_type_from_vm_name(name, &vm_name).map(|t| t.to_string()),
+                vm_name,
+                service,
+                path: relative_path,
+                size,
+                modified,
+                start: start.map(|t| t.to_rfc3339()),
+                end: end.map(|t| t.to_rfc3339()),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let index = LogIndex {
+        environment_name: name.to_string(),
+        generated_at: Utc::now().to_rfc3339(),
+        files,
+    };
+    index.save(&index_path)?;
+    Ok(index)
+}
+
+/// Search the retrieved logs for lines matching the pattern.
+///
+/// The index is refreshed first, then only the files that overlap the time window and belong to
+/// the requested node type are scanned.
+pub fn search_logs(
+    name: &str,
+    pattern: &Regex,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    node_type: Option<NodeType>,
+) -> Result<Vec<LogMatch>> {
+    let log_dir = get_log_dir(name)?;
+    let index = build_log_index(name)?;
+    let files = index
+        .files
+        .into_iter()
+        .filter(|file| file.overlaps(from, to))
+        .filter(|file| {
+            node_type
+                .as_ref()
+                .is_none_or(|node_type| file.is_node_type(node_type))
+        })
+        .collect::<Vec<_>>();
+    eprintln!("Searching {} files", files.len());
+
+    let mut matches = files
+        .into_par_iter()
+        .map(|file| -> Result<Vec<LogMatch>> {
+            let reader = open_log_file(&log_dir.join(&file.path))?;
+            let mut matches = Vec::new();
+            // Lines without a timestamp belong to the entry above them.
+            let mut current_timestamp = None;
+            for line in reader.lines() {
+                let line = line?;
+                if let Some(timestamp) = parse_timestamp(&line) {
+                    current_timestamp = Some(timestamp);
+                }
+                if !pattern.is_match(&line) {
+                    continue;
+                }
+                if let Some(timestamp) = current_timestamp {
+                    if from.is_some_and(|from| timestamp < from)
+                        || to.is_some_and(|to| timestamp > to)
+                    {
+                        continue;
+                    }
+                }
+                matches.push(LogMatch {
+                    vm_name: file.vm_name.clone(),
+                    service: file.service.clone(),
+                    timestamp: current_timestamp.map(|t| t.to_rfc3339()),
+                    line,
+                });
+            }
+            Ok(matches)
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    matches.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+    Ok(matches)
+}
+
+/// Open a log file for reading, decompressing it if it was compressed on rotation.
+pub fn open_log_file(path: &Path) -> Result<Box<dyn BufRead + Send>> {
+    let file = File::open(path)?;
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        Ok(Box::new(BufReader::new(GzDecoder::new(file))))
+    } else {
+        Ok(Box::new(BufReader::new(file)))
+    }
+}
+
+/// Infer the type of node from the name of the VM, which follows the naming in the Terraform
+/// manifests.
+pub fn get_node_type_from_vm_name(name: &str, vm_name: &str) -> Option<NodeType> {
+    let suffix = vm_name.strip_prefix(&format!("{name}-"))?;
+    if suffix.starts_with("peer-cache-node") {
+        Some(NodeType::PeerCache)
+    } else if suffix.starts_with("genesis") {
+        Some(NodeType::Genesis)
+    } else if suffix.starts_with("full-cone-private-node") {
+        Some(NodeType::FullConePrivateNode)
+    } else if suffix.starts_with("port-restricted-cone-private-node") {
+        Some(NodeType::PortRestrictedConePrivateNode)
+    } else if suffix.starts_with("symmetric-private-node") {
+        Some(NodeType::SymmetricPrivateNode)
+    } else if suffix.starts_with("upnp-private-node") {
+        Some(NodeType::Upnp)
+    } else if suffix.starts_with("node") {
+        Some(NodeType::Generic)
+    } else {
+        None
+    }
+}
+
+// Only the files selected by the rsync filter are logs. The output of `logs rg` is also written
+// into the VM directories, so it is excluded.
+fn is_log_file(path: &Path) -> bool {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    file_name.contains(".log") && !file_name.starts_with("rg-")
+}
+
+fn get_time_range(path: &Path) -> Result<(Option<DateTime<Utc>>, Option<DateTime<Utc>>)> {
+    let mut reader = open_log_file(path)?;
+    let mut start = None;
+    let mut end = None;
+
+    let mut line = String::new();
+    while start.is_none() && reader.read_line(&mut line)? > 0 {
+        start = parse_timestamp(&line);
+        line.clear();
+    }
+
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        // A compressed file can't be read from the end, so the remainder is read in full.
+        for line in reader.lines() {
+            if let Some(timestamp) = parse_timestamp(&line?) {
+                end = Some(timestamp);
+            }
+        }
+    } else {
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        file.seek(SeekFrom::Start(len.saturating_sub(TAIL_READ_SIZE)))?;
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail)?;
+        end = String::from_utf8_lossy(&tail)
+            .lines()
+            .rev()
+            .find_map(parse_timestamp);
+    }
+
+    Ok((start, end.or(start)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn get_indexed_file(start: Option<&str>, end: Option<&str>) -> IndexedLogFile {
+        IndexedLogFile {
+            vm_name: "alpha-node-1".to_string(),
+            node_type: Some(NodeType::Generic.to_string()),
+            service: "antnode1".to_string(),
+            path: PathBuf::from("alpha-node-1/antnode1/antnode.log"),
+            size: 0,
+            modified: 0,
+            start: start.map(|s| s.to_string()),
+            end: end.map(|s| s.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_indexed_file_overlaps_the_window() {
+        let file = get_indexed_file(Some("2024-10-10T10:00:00Z"), Some("2024-10-10T12:00:00Z"));
+        let at = |s: &str| parse_rfc3339(s);
+        assert!(file.overlaps(None, None));
+        assert!(file.overlaps(at("2024-10-10T11:00:00Z"), at("2024-10-10T13:00:00Z")));
+        assert!(file.overlaps(at("2024-10-10T12:00:00Z"), None));
+        assert!(!file.overlaps(at("2024-10-10T12:00:01Z"), None));
+        assert!(!file.overlaps(None, at("2024-10-10T09:59:59Z")));
+        // A file whose range is unknown can't be ruled out.
+        assert!(get_indexed_file(None, None).overlaps(at("2030-01-01T00:00:00Z"), None));
+    }
+
+    #[test]
+    fn test_node_type_is_inferred_from_the_vm_name() {
+        let get = |vm_name: &str| get_node_type_from_vm_name("alpha", vm_name);
+        assert!(matches!(
+            get("alpha-peer-cache-node-1"),
+            Some(NodeType::PeerCache)
+        ));
+        assert!(matches!(
+            get("alpha-genesis-bootstrap"),
+            Some(NodeType::Genesis)
+        ));
+        assert!(matches!(
+            get("alpha-symmetric-private-node-2"),
+            Some(NodeType::SymmetricPrivateNode)
+        ));
+        assert!(matches!(get("alpha-node-3"), Some(NodeType::Generic)));
+        assert!(get("alpha-client-1").is_none());
+        assert!(get_node_type_from_vm_name("alpha", "beta-node-1").is_none());
+    }
+
+    #[test]
+    fn test_only_log_files_are_indexed() {
+        assert!(is_log_file(Path::new("alpha-node-1/antnode1/antnode.log")));
+        assert!(is_log_file(Path::new(
+            "alpha-node-1/antnode1/antnode.log.20241010T120000.gz"
+        )));
+        assert!(!is_log_file(Path::new("alpha-node-1/rg-antnode.log")));
+        assert!(!is_log_file(Path::new("alpha-node-1/node_registry.json")));
+    }
+
+    #[test]
+    fn test_time_range_is_read_from_the_first_and_last_timestamps() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("antnode.log");
+        let mut file = File::create(&path).unwrap();
+        writeln!(file, "no timestamp").unwrap();
+        writeln!(file, "[2024-10-10T10:00:00Z INFO ant_node] first").unwrap();
+        writeln!(file, "[2024-10-10T11:00:00Z INFO ant_node] second").unwrap();
+        writeln!(file, "trailing continuation").unwrap();
+        drop(file);
+
+        let (start, end) = get_time_range(&path).unwrap();
+        assert_eq!(start, parse_rfc3339("2024-10-10T10:00:00Z"));
+        assert_eq!(end, parse_rfc3339("2024-10-10T11:00:00Z"));
+    }
+}
@@ -0,0 +1,210 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use std::io::BufRead;
+
+/// A single entry from a node log, which may have spanned several lines.
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+/// Parse the timestamp from a node log line.
+///
+/// Both of the formats a node can be configured to log in are supported:
+/// * default: `[2024-10-10T12:00:00.123456Z INFO ant_node::node] message`
+/// * json: `{"timestamp":"2024-10-10T12:00:00.123456Z","level":"INFO",...}`
+///
+/// Returns `None` for lines that don't have a timestamp, e.g., the continuation of a multi-line
+/// message.
+pub fn parse_timestamp(line: &str) -> Option<DateTime<Utc>> {
+    let line = line.trim_start();
+    if let Some(rest) = line.strip_prefix('[') {
+        let timestamp = rest.split_whitespace().next()?;
+        return parse_rfc3339(timestamp);
+    }
+    if line.starts_with('{') {
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        return value
+            .get("timestamp")
+            .and_then(|timestamp| timestamp.as_str())
+            .and_then(parse_rfc3339);
+    }
+    None
+}
+
+/// Parse a node log line in either format into an entry.
+///
+/// Returns `None` if the line is not the start of an entry.
+pub fn parse_entry(line: &str) -> Option<LogEntry> {
+    let line = line.trim_start();
+    if let Some(rest) = line.strip_prefix('[') {
+        let (header, message) = rest.split_once(']')?;
+        let mut parts = header.split_whitespace();
+        let timestamp = parse_rfc3339(parts.next()?)?;
+        let level = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or_default().to_string();
+        return Some(LogEntry {
+            timestamp,
+            level,
+            target,
+            message: message.trim_start().to_string(),
+        });
+    }
+    if line.starts_with('{') {
+        let value: serde_json::Value = serde_json::from_str(line).ok()?;
+        let timestamp = value
+            .get("timestamp")
+            .and_then(|timestamp| timestamp.as_str())
+            .and_then(parse_rfc3339)?;
+        let get_str = |key: &str| {
+            value
+                .get(key)
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        // Structured fields other than the message are appended, so they aren't lost.
+        let mut message = String::new();
+        if let Some(fields) = value.get("fields").and_then(|f| f.as_object()) {
+            if let Some(msg) = fields.get("message").and_then(|m| m.as_str()) {
+                message.push_str(msg);
+            }
+            for (key, field) in fields.iter().filter(|(key, _)| *key != "message") {
+                let field = field
+                    .as_str()
+                    .map(|s| s.to_string())
+                    .unwrap_or_else(|| field.to_string());
+                message.push_str(&format!(" {key}={field}"));
+            }
+        }
+        return Some(LogEntry {
+            timestamp,
+            level: get_str("level"),
+            target: get_str("target"),
+            message: message.trim_start().to_string(),
+        });
+    }
+    None
+}
+
+pub fn parse_rfc3339(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+}
+
+/// Reads the entries from a log, joining lines that don't start a new entry onto the message of
+/// the entry above them.
+///
+/// Any lines before the first entry are discarded.
+pub struct LogEntryReader<R: BufRead> {
+    reader: R,
+    pending: Option<LogEntry>,
+    buf: String,
+}
+
+impl<R: BufRead> LogEntryReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            pending: None,
+            buf: String::new(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LogEntryReader<R> {
+    type Item = Result<LogEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.buf.clear();
+            match self.reader.read_line(&mut self.buf) {
+                Ok(0) => return self.pending.take().map(Ok),
+                Ok(_) => {
+                    let line = self.buf.trim_end_matches(['\r', '\n']);
+                    match parse_entry(line) {
+                        Some(entry) => {
+                            if let Some(previous) = self.pending.replace(entry) {
+                                return Some(Ok(previous));
+                            }
+                        }
+                        None => {
+                            if let Some(pending) = self.pending.as_mut() {
+                                pending.message.push('\n');
+                                pending.message.push_str(line);
+                            }
+                        }
+                    }
+                }
+                Err(err) => return Some(Err(err.into())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp_supports_both_log_formats() {
+        let expected = parse_rfc3339("2024-10-10T12:00:00.123456Z");
+        assert!(expected.is_some());
+        assert_eq!(
+            parse_timestamp("[2024-10-10T12:00:00.123456Z INFO ant_node::node] Started"),
+            expected
+        );
+        assert_eq!(
+            parse_timestamp(
+                r#"{"timestamp":"2024-10-10T12:00:00.123456Z","level":"INFO","fields":{}}"#
+            ),
+            expected
+        );
+        assert_eq!(parse_timestamp("    at ant_node::node::run"), None);
+        assert_eq!(parse_timestamp("[not-a-timestamp INFO x] y"), None);
+    }
+
+    #[test]
+    fn test_parse_entry_reads_the_header_and_json_fields() {
+        let entry =
+            parse_entry("[2024-10-10T12:00:00Z WARN ant_networking::driver] Peer dropped").unwrap();
+        assert_eq!(entry.level, "WARN");
+        assert_eq!(entry.target, "ant_networking::driver");
+        assert_eq!(entry.message, "Peer dropped");
+
+        let entry = parse_entry(
+            r#"{"timestamp":"2024-10-10T12:00:00Z","level":"ERROR","target":"ant_node","fields":{"message":"Failed","peer":"12D3"}}"#,
+        )
+        .unwrap();
+        assert_eq!(entry.level, "ERROR");
+        assert_eq!(entry.target, "ant_node");
+        assert_eq!(entry.message, "Failed peer=12D3");
+
+        assert!(parse_entry("continuation line").is_none());
+    }
+
+    #[test]
+    fn test_log_entry_reader_joins_continuation_lines() {
+        let log = "preamble\n\
+                   [2024-10-10T12:00:00Z ERROR ant_node] Panicked\n\
+                   backtrace line\n\
+                   [2024-10-10T12:00:01Z INFO ant_node] Restarted\n";
+        let entries = LogEntryReader::new(log.as_bytes())
+            .collect::<Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].message, "Panicked\nbacktrace line");
+        assert_eq!(entries[1].message, "Restarted");
+    }
+}
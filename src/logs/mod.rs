@@ -4,18 +4,29 @@
 // This SAFE Network Software is licensed under the BSD-3-Clause license.
 // Please see the LICENSE file for more details.
 
+pub mod analyze;
+pub mod archive;
+pub mod follow;
+pub mod index;
+pub mod line;
+pub mod summarize;
+pub mod timeline;
+
+use archive::{get_log_archive_manifest, get_log_archives, LogArchiveFilter};
+
 use crate::{
     error::{Error, Result},
     get_progress_bar,
     inventory::VirtualMachine,
-    run_external_command,
-    s3::S3Repository,
-    TestnetDeployer,
+    object_store::{get_object_store, Bucket},
+    run_external_command, TestnetDeployer,
 };
 use fs_extra::dir::{copy, remove, CopyOptions};
 use log::debug;
 use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use serde::Serialize;
 use std::{
+    collections::BTreeMap,
     fs::File,
     io::{Cursor, Read, Write},
     path::{Path, PathBuf},
@@ -33,6 +44,75 @@ const DEFAULT_RSYNC_ARGS: [&str; 8] = [
 ];
 
 const NODE_LOG_DIR: &str = "/mnt/antnode-storage/log/";
+const CLIENT_LOG_DIR: &str = "/mnt/client/log/";
+
+/// The matches for a ripgrep query across all the VMs in an environment.
+#[derive(Clone, Debug, Serialize)]
+pub struct RgReport {
+    pub command: String,
+    pub timestamp: String,
+    pub vms: Vec<RgVmResult>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RgVmResult {
+    pub vm_name: String,
+    /// The error if the query could not be run on the VM.
+    pub error: Option<String>,
+    pub match_count: u64,
+    /// The number of matches per service, which is taken from the directory of the log file.
+    pub services: BTreeMap<String, u64>,
+}
+
+impl RgVmResult {
+    // Each line of output is in the form `<path>:<line number>:<text>`.
+    fn from_output(vm_name: &str, output: &[String]) -> Self {
+        let mut services = BTreeMap::new();
+        let mut match_count = 0;
+        for line in output {
+            let Some((path, _)) = line.split_once(':') else {
+                continue;
+            };
+            let service = Path::new(path)
+                .parent()
+                .and_then(|parent| parent.file_name())
+                .map(|service| service.to_string_lossy().to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            *services.entry(service).or_insert(0) += 1;
+            match_count += 1;
+        }
+        Self {
+            vm_name: vm_name.to_string(),
+            error: None,
+            match_count,
+            services,
+        }
+    }
+}
+
+impl RgReport {
+    pub fn print(&self) {
+        println!("==== Ripgrep Report ====");
+        println!("Command: {}", self.command);
+        for vm in self.vms.iter() {
+            match &vm.error {
+                Some(err) => println!("{}: failed: {err}", vm.vm_name),
+                None => {
+                    println!("{}: {} matches", vm.vm_name, vm.match_count);
+                    for (service, count) in vm.services.iter() {
+                        println!("  {service}: {count}");
+                    }
+                }
+            }
+        }
+        let total: u64 = self.vms.iter().map(|vm| vm.match_count).sum();
+        let failed = self.vms.iter().filter(|vm| vm.error.is_some()).count();
+        println!("Total matches: {total}");
+        if failed > 0 {
+            println!("Failed to query {failed} VMs");
+        }
+    }
+}
 
 impl TestnetDeployer {
     pub fn rsync_logs(
@@ -43,23 +123,7 @@ impl TestnetDeployer {
     ) -> Result<()> {
         // take root_dir at the top as `get_all_node_inventory` changes the working dir.
         let root_dir = std::env::current_dir()?;
-
-        let mut all_inventory = vec![];
-        if !disable_client_logs {
-            all_inventory.extend(self.get_client_inventory(name)?);
-        }
-
-        all_inventory.extend(self.get_all_node_inventory(name)?);
-
-        let all_inventory = if let Some(filter) = vm_filter {
-            all_inventory
-                .into_iter()
-                .filter(|vm| vm.name.contains(&filter))
-                .collect()
-        } else {
-            all_inventory
-        };
-
+        let all_inventory = self.get_log_inventory(name, vm_filter, disable_client_logs)?;
         let log_base_dir = create_initial_log_dir_setup(&root_dir, name, &all_inventory)?;
 
         // We might use the script, so goto the resource dir.
@@ -67,35 +131,7 @@ impl TestnetDeployer {
         println!("Starting to rsync the log files");
         let progress_bar = get_progress_bar(all_inventory.len() as u64)?;
 
-        let rsync_args = all_inventory
-            .iter()
-            .map(|vm| {
-                let args = if vm.name.contains("symmetric") {
-                    let args = self.construct_symmetric_private_node_args(vm, &log_base_dir)?;
-                    debug!("Using symmetric rsync args for {:?}", vm.name);
-                    debug!("Args for {}: {:?}", vm.name, args);
-                    args
-                } else if vm.name.contains("full-cone") {
-                    let args = self.construct_full_cone_private_node_args(vm, &log_base_dir)?;
-                    debug!("Using symmetric rsync args for {:?}", vm.name);
-                    debug!("Args for {}: {:?}", vm.name, args);
-                    args
-                } else if vm.name.contains("ant-client") {
-                    let args = self.construct_client_args(vm, &log_base_dir);
-                    debug!("Using Client rsync args for {:?} ", vm.name);
-                    debug!("Args for {}: {:?}", vm.name, args);
-                    args
-                } else {
-                    let args = self.construct_public_node_args(vm, &log_base_dir);
-                    debug!("Using public rsync args for {:?}", vm.name);
-                    debug!("Args for {}: {:?}", vm.name, args);
-                    args
-                };
-
-                Ok((vm.clone(), args))
-            })
-            .collect::<Result<Vec<_>>>()?;
-
+        let rsync_args = self.get_rsync_args(&all_inventory, &log_base_dir)?;
         let failed_inventory = rsync_args
             .par_iter()
             .filter_map(|(vm, args)| {
@@ -136,6 +172,67 @@ impl TestnetDeployer {
         Ok(())
     }
 
+    // Return the VMs that logs should be retrieved from.
+    fn get_log_inventory(
+        &self,
+        name: &str,
+        vm_filter: Option<String>,
+        disable_client_logs: bool,
+    ) -> Result<Vec<VirtualMachine>> {
+        let mut all_inventory = vec![];
+        if !disable_client_logs {
+            all_inventory.extend(self.get_client_inventory(name)?);
+        }
+
+        all_inventory.extend(self.get_all_node_inventory(name)?);
+
+        let all_inventory = if let Some(filter) = vm_filter {
+            all_inventory
+                .into_iter()
+                .filter(|vm| vm.name.contains(&filter))
+                .collect()
+        } else {
+            all_inventory
+        };
+        Ok(all_inventory)
+    }
+
+    // Construct the rsync arguments for each VM, based on the type of VM and how it is routed.
+    fn get_rsync_args(
+        &self,
+        all_inventory: &[VirtualMachine],
+        log_base_dir: &Path,
+    ) -> Result<Vec<(VirtualMachine, Vec<String>)>> {
+        all_inventory
+            .iter()
+            .map(|vm| {
+                let args = if vm.name.contains("symmetric") {
+                    let args = self.construct_symmetric_private_node_args(vm, log_base_dir)?;
+                    debug!("Using symmetric rsync args for {:?}", vm.name);
+                    debug!("Args for {}: {:?}", vm.name, args);
+                    args
+                } else if vm.name.contains("full-cone") {
+                    let args = self.construct_full_cone_private_node_args(vm, log_base_dir)?;
+                    debug!("Using symmetric rsync args for {:?}", vm.name);
+                    debug!("Args for {}: {:?}", vm.name, args);
+                    args
+                } else if vm.name.contains("ant-client") {
+                    let args = self.construct_client_args(vm, log_base_dir);
+                    debug!("Using Client rsync args for {:?} ", vm.name);
+                    debug!("Args for {}: {:?}", vm.name, args);
+                    args
+                } else {
+                    let args = self.construct_public_node_args(vm, log_base_dir);
+                    debug!("Using public rsync args for {:?}", vm.name);
+                    debug!("Args for {}: {:?}", vm.name, args);
+                    args
+                };
+
+                Ok((vm.clone(), args))
+            })
+            .collect::<Result<Vec<_>>>()
+    }
+
     fn construct_client_args(&self, vm: &VirtualMachine, log_base_dir: &Path) -> Vec<String> {
         let vm_path = log_base_dir.join(&vm.name);
         let mut rsync_args = DEFAULT_RSYNC_ARGS
@@ -154,7 +251,7 @@ impl TestnetDeployer {
                     .to_string_lossy()
                     .as_ref()
             ),
-            format!("root@{}:/mnt/client/log/", vm.public_ip_addr),
+            format!("root@{}:{CLIENT_LOG_DIR}", vm.public_ip_addr),
             vm_path.to_string_lossy().to_string(),
         ]);
 
@@ -267,12 +364,12 @@ impl TestnetDeployer {
 
         Ok(rsync_args)
     }
-    fn run_rsync(vm: &VirtualMachine, rsync_args: &[String]) -> Result<()> {
+    fn run_rsync(vm: &VirtualMachine, rsync_args: &[String]) -> Result<Vec<String>> {
         debug!(
             "Rsync logs to our machine for {:?} : {}",
             vm.name, vm.public_ip_addr
         );
-        run_external_command(
+        let output = run_external_command(
             PathBuf::from("rsync"),
             PathBuf::from("."),
             rsync_args.to_vec(),
@@ -284,95 +381,112 @@ impl TestnetDeployer {
             "Finished rsync for for {:?} : {}",
             vm.name, vm.public_ip_addr
         );
-        Ok(())
+        Ok(output)
     }
 
-    pub fn ripgrep_logs(&self, name: &str, rg_args: &str) -> Result<()> {
+    /// Run a ripgrep query against the logs on each VM, through the NAT gateway for private nodes.
+    ///
+    /// The output for each VM is written to `logs/<name>/<vm>/rg-<timestamp>.log` and the report
+    /// with the match counts per VM and service is written to `logs/<name>/rg-<timestamp>.json`.
+    ///
+    /// Progress is written to stderr, so stdout only has the report when it is requested as JSON.
+    pub fn ripgrep_logs(
+        &self,
+        name: &str,
+        rg_args: &str,
+        include_clients: bool,
+    ) -> Result<RgReport> {
         // take root_dir at the top as `get_all_node_inventory` changes the working dir.
         let root_dir = std::env::current_dir()?;
-        let all_node_inventory = self.get_all_node_inventory(name)?;
-        let log_abs_dest = create_initial_log_dir_setup(&root_dir, name, &all_node_inventory)?;
+        let all_inventory = self.get_log_inventory(name, None, !include_clients)?;
+        let log_abs_dest = create_initial_log_dir_setup(&root_dir, name, &all_inventory)?;
+        let ssh_user = self.cloud_provider.get_ssh_user();
 
-        let rg_cmd = format!("rg {rg_args} /mnt/antnode-storage/log//");
-        println!("Running ripgrep with command: {rg_cmd}");
+        // The filename and line number are needed to attribute each match to a service.
+        let node_rg_cmd =
+            format!("rg --with-filename --no-heading --line-number {rg_args} {NODE_LOG_DIR}");
+        let client_rg_cmd =
+            format!("rg --with-filename --no-heading --line-number {rg_args} {CLIENT_LOG_DIR}");
+        eprintln!("Running ripgrep with command: {node_rg_cmd}");
 
         // Get current date and time
         let now = chrono::Utc::now();
         let timestamp = now.format("%Y%m%dT%H%M%S").to_string();
-        let progress_bar = get_progress_bar(all_node_inventory.len() as u64)?;
-        let _failed_inventory = all_node_inventory
+        let progress_bar = get_progress_bar(all_inventory.len() as u64)?;
+        let mut results = all_inventory
             .par_iter()
-            .filter_map(|vm| {
-                let op =
+            .map(|vm| {
+                let rg_cmd = if vm.name.contains("ant-client") {
+                    &client_rg_cmd
+                } else {
+                    &node_rg_cmd
+                };
+                let result =
                     match self
                         .ssh_client
-                        .run_command(&vm.public_ip_addr, "root", &rg_cmd, true)
+                        .run_command(&vm.public_ip_addr, &ssh_user, rg_cmd, true)
                     {
-                        Ok(output) => {
-                            match Self::store_rg_output(
-                                &timestamp,
-                                &rg_cmd,
-                                &output,
-                                &log_abs_dest,
-                                &vm.name,
-                            ) {
-                                Ok(_) => None,
-                                Err(err) => {
-                                    println!(
-                                        "Failed store output for {:?} with: {err:?}",
-                                        vm.public_ip_addr
-                                    );
-                                    Some(vm)
-                                }
-                            }
-                        }
+                        Ok(output) => Self::store_rg_output(
+                            &timestamp,
+                            rg_cmd,
+                            &output,
+                            &log_abs_dest,
+                            &vm.name,
+                        )
+                        .map(|_| RgVmResult::from_output(&vm.name, &output)),
                         Err(Error::ExternalCommandRunFailed {
                             binary,
                             exit_status,
                         }) => {
                             if let Some(1) = exit_status.code() {
                                 debug!("No matches found for {:?}", vm.public_ip_addr);
-                                match Self::store_rg_output(
+                                Self::store_rg_output(
                                     &timestamp,
-                                    &rg_cmd,
+                                    rg_cmd,
                                     &["No matches found".to_string()],
                                     &log_abs_dest,
                                     &vm.name,
-                                ) {
-                                    Ok(_) => None,
-                                    Err(err) => {
-                                        println!(
-                                            "Failed store output for {:?} with: {err:?}",
-                                            vm.public_ip_addr
-                                        );
-                                        Some(vm)
-                                    }
-                                }
+                                )
+                                .map(|_| RgVmResult::from_output(&vm.name, &[]))
                             } else {
-                                println!(
-                                    "Failed to run rg query for {:?} with: {binary}",
-                                    vm.public_ip_addr
-                                );
-                                Some(vm)
+                                Err(Error::ExternalCommandRunFailed {
+                                    binary,
+                                    exit_status,
+                                })
                             }
                         }
-                        Err(err) => {
-                            println!(
-                                "Failed to run rg query for {:?} with: {err:?}",
-                                vm.public_ip_addr
-                            );
-                            Some(vm)
-                        }
+                        Err(err) => Err(err),
                     };
                 progress_bar.inc(1);
-                op
+                result.unwrap_or_else(|err| {
+                    eprintln!(
+                        "Failed to run rg query for {} ({:?}) with: {err:?}",
+                        vm.name, vm.public_ip_addr
+                    );
+                    RgVmResult {
+                        vm_name: vm.name.clone(),
+                        error: Some(err.to_string()),
+                        match_count: 0,
+                        services: BTreeMap::new(),
+                    }
+                })
             })
             .collect::<Vec<_>>();
+        results.sort_by(|a, b| a.vm_name.cmp(&b.vm_name));
 
         progress_bar.finish_and_clear();
-        println!("Ripgrep completed!");
+        eprintln!("Ripgrep completed!");
 
-        Ok(())
+        let report = RgReport {
+            command: node_rg_cmd,
+            timestamp: timestamp.clone(),
+            vms: results,
+        };
+        let report_path = log_abs_dest.join(format!("rg-{timestamp}.json"));
+        serde_json::to_writer_pretty(File::create(&report_path)?, &report)?;
+        eprintln!("Report written to {}", report_path.to_string_lossy());
+
+        Ok(report)
     }
 
     fn store_rg_output(
@@ -432,12 +546,24 @@ impl TestnetDeployer {
     }
 }
 
-pub async fn get_logs(name: &str) -> Result<()> {
+/// Retrieve the logs for an environment from S3.
+///
+/// If the logs were uploaded as archives, only the archives selected by the filter are retrieved.
+/// Otherwise, the whole folder for the environment is retrieved, and a filter can't be used.
+pub async fn get_logs(name: &str, filter: LogArchiveFilter) -> Result<()> {
+    if let Some(manifest) = get_log_archive_manifest(name).await? {
+        get_log_archives(name, &manifest, &filter).await?;
+        return Ok(());
+    }
+    if !filter.is_empty() {
+        return Err(Error::LogArchiveManifestNotFound(name.to_string()));
+    }
+
     let dest_path = std::env::current_dir()?.join("logs").join(name);
     std::fs::create_dir_all(dest_path.clone())?;
-    let s3_repository = S3Repository {};
-    s3_repository
-        .download_folder("sn-testnet", &format!("testnet-logs/{name}"), &dest_path)
+    let object_store = get_object_store()?;
+    object_store
+        .download_folder(Bucket::Testnet, &format!("testnet-logs/{name}"), &dest_path)
         .await?;
     Ok(())
 }
@@ -465,9 +591,9 @@ pub fn reassemble_logs(name: &str) -> Result<()> {
 }
 
 pub async fn rm_logs(name: &str) -> Result<()> {
-    let s3_repository = S3Repository {};
-    s3_repository
-        .delete_folder("sn-testnet", &format!("testnet-logs/{name}"))
+    let object_store = get_object_store()?;
+    object_store
+        .delete_folder(Bucket::Testnet, &format!("testnet-logs/{name}"))
         .await?;
     Ok(())
 }
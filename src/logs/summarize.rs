@@ -0,0 +1,248 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use super::{
+    index::{build_log_index, get_log_dir, open_log_file},
+    line::LogEntryReader,
+};
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+
+// The level, target and normalised message.
+type TemplateKey = (String, String, String);
+
+/// A group of error or warning entries that share the same message, once the variable parts have
+/// been replaced with placeholders.
+#[derive(Clone, Debug, Serialize)]
+pub struct MessageTemplate {
+    pub level: String,
+    pub target: String,
+    pub template: String,
+    pub count: u64,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub vms: BTreeSet<String>,
+    /// The first message that matched the template, before it was normalised.
+    pub example: String,
+}
+
+// Each pattern is replaced in order, so the more specific ones come first. For example, a multiaddr
+// contains both an IP address and a peer ID.
+const NORMALISATION_RULES: [(&str, &str); 7] = [
+    (r"/(?:ip4|ip6|dns|dns4|dns6)/\S+", "<multiaddr>"),
+    (
+        r"\b(?:12D3KooW|Qm)[1-9A-HJ-NP-Za-km-z]{40,50}\b",
+        "<peer-id>",
+    ),
+    (r"\b0x[0-9a-fA-F]+\b", "<hex>"),
+    (r"\b[0-9a-fA-F]{16,}\b", "<key>"),
+    (r"\b\d{1,3}(?:\.\d{1,3}){3}(?::\d+)?\b", "<ip>"),
+    (r"\b[0-9a-f]{6}\(\w+\)", "<key>"),
+    (r"\b\d+(?:\.\d+)?(?:ns|µs|us|ms|s)?\b", "<n>"),
+];
+
+/// Replaces the variable parts of log messages, such as peer IDs, multiaddrs, record keys and
+/// numbers, so that messages with the same cause can be grouped together.
+pub struct MessageNormaliser {
+    rules: Vec<(Regex, &'static str)>,
+}
+
+impl MessageNormaliser {
+    pub fn new() -> Result<Self> {
+        let rules = NORMALISATION_RULES
+            .iter()
+            .map(|(pattern, placeholder)| Ok((Regex::new(pattern)?, *placeholder)))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Only the first line of a message is used, because the remainder is usually a backtrace or
+    /// a dump of some structure.
+    pub fn normalise(&self, message: &str) -> String {
+        let mut normalised = message.lines().next().unwrap_or_default().to_string();
+        for (regex, placeholder) in self.rules.iter() {
+            normalised = regex.replace_all(&normalised, *placeholder).into_owned();
+        }
+        normalised
+    }
+}
+
+/// Cluster the error and warning entries in the retrieved logs into message templates.
+///
+/// The templates are returned in descending order of how many times they occurred.
+pub fn summarize_logs(
+    name: &str,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Vec<MessageTemplate>> {
+    let normaliser = MessageNormaliser::new()?;
+    let log_dir = get_log_dir(name)?;
+    let index = build_log_index(name)?;
+    let files = index
+        .files
+        .into_iter()
+        .filter(|file| file.overlaps(from, to))
+        .collect::<Vec<_>>();
+
+    let partial_summaries = files
+        .into_par_iter()
+        .map(|file| -> Result<HashMap<TemplateKey, MessageTemplate>> {
+            let mut templates = HashMap::new();
+            let reader = open_log_file(&log_dir.join(&file.path))?;
+            for entry in LogEntryReader::new(reader) {
+                let entry = entry?;
+                let level = entry.level.to_uppercase();
+                if level != "ERROR" && level != "WARN" {
+                    continue;
+                }
+                if from.is_some_and(|from| entry.timestamp < from)
+                    || to.is_some_and(|to| entry.timestamp > to)
+                {
+                    continue;
+                }
+
+                let template = normaliser.normalise(&entry.message);
+                let timestamp = entry.timestamp.to_rfc3339();
+                templates
+                    .entry((level.clone(), entry.target.clone(), template.clone()))
+                    .and_modify(|existing: &mut MessageTemplate| {
+                        existing.count += 1;
+                        existing.last_seen = timestamp.clone();
+                    })
+                    .or_insert_with(|| MessageTemplate {
+                        level,
+                        target: entry.target.clone(),
+                        template,
+                        count: 1,
+                        first_seen: timestamp.clone(),
+                        last_seen: timestamp.clone(),
+                        vms: BTreeSet::from([file.vm_name.clone()]),
+                        example: entry.message.clone(),
+                    });
+            }
+            Ok(templates)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut merged: HashMap<TemplateKey, MessageTemplate> = HashMap::new();
+    for (key, template) in partial_summaries.into_iter().flatten() {
+        match merged.get_mut(&key) {
+            Some(existing) => {
+                existing.count += template.count;
+                // The timestamps are all RFC 3339 in UTC, so they order correctly as strings.
+                if template.first_seen < existing.first_seen {
+                    existing.first_seen = template.first_seen;
+                    existing.example = template.example;
+                }
+                if template.last_seen > existing.last_seen {
+                    existing.last_seen = template.last_seen;
+                }
+                existing.vms.extend(template.vms);
+            }
+            None => {
+                merged.insert(key, template);
+            }
+        }
+    }
+
+    let mut templates = merged.into_values().collect::<Vec<_>>();
+    templates.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| a.first_seen.cmp(&b.first_seen))
+    });
+    Ok(templates)
+}
+
+pub fn print_summary(templates: &[MessageTemplate], top: usize) {
+    let total: u64 = templates.iter().map(|t| t.count).sum();
+    println!("==== Error and Warning Summary ====");
+    println!(
+        "{total} entries clustered into {} templates",
+        templates.len()
+    );
+    for (i, template) in templates.iter().take(top).enumerate() {
+        println!();
+        println!(
+            "#{} [{}] {} ({} occurrences)",
+            i + 1,
+            template.level,
+            template.target,
+            template.count
+        );
+        println!("  {}", template.template);
+        println!("  First seen: {}", template.first_seen);
+        println!("  Last seen:  {}", template.last_seen);
+        let vms = template.vms.iter().cloned().collect::<Vec<_>>();
+        println!("  VMs ({}): {}", vms.len(), vms.join(", "));
+        println!(
+            "  Example: {}",
+            template.example.lines().next().unwrap_or_default()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEER_ID: &str = "12D3KooWRBhwfeP2Y4TCx1SM6s9rUoHhR5nCoP67mCePjm7NZyHR";
+
+    #[test]
+    fn test_normalisation_rules_are_valid() {
+        assert!(MessageNormaliser::new().is_ok());
+    }
+
+    #[test]
+    fn test_multiaddr_is_replaced_before_the_peer_id_and_ip_inside_it() {
+        let normaliser = MessageNormaliser::new().unwrap();
+        assert_eq!(
+            normaliser.normalise(&format!(
+                "Failed to dial {PEER_ID} at /ip4/10.0.0.1/udp/12000/quic-v1/p2p/{PEER_ID} after \
+                 3 attempts"
+            )),
+            "Failed to dial <peer-id> at <multiaddr> after <n> attempts"
+        );
+    }
+
+    #[test]
+    fn test_keys_addresses_and_durations_are_replaced() {
+        let normaliser = MessageNormaliser::new().unwrap();
+        assert_eq!(
+            normaliser.normalise("Replication of 4a5b6c(b1c2d3) to 10.0.0.1:12000 took 125ms"),
+            "Replication of <key> to <ip> took <n>"
+        );
+        assert_eq!(
+            normaliser.normalise(
+                "Payment 0xdeadbeef for record \
+                 9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08 failed after 2.5s"
+            ),
+            "Payment <hex> for record <key> failed after <n>"
+        );
+    }
+
+    #[test]
+    fn test_only_the_first_line_is_normalised() {
+        let normaliser = MessageNormaliser::new().unwrap();
+        assert_eq!(
+            normaliser.normalise("Failed to store chunk\n  at ant_node::put_validation:42"),
+            "Failed to store chunk"
+        );
+    }
+
+    #[test]
+    fn test_messages_that_differ_in_variable_parts_share_a_template() {
+        let normaliser = MessageNormaliser::new().unwrap();
+        assert_eq!(
+            normaliser.normalise("Peer 10.0.0.1:12000 timed out after 30s"),
+            normaliser.normalise("Peer 10.0.0.25:12001 timed out after 5s")
+        );
+    }
+}
@@ -0,0 +1,317 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use super::{
+    index::{build_log_index, get_log_dir, open_log_file},
+    line::{LogEntry, LogEntryReader},
+};
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap, VecDeque},
+    io::{BufRead, Write},
+    path::PathBuf,
+};
+
+// The log files for one service, which are read one after the other, in order of their start
+// time. This keeps only one file open per service.
+struct TimelineSource {
+    entries: Option<LogEntryReader<Box<dyn BufRead + Send>>>,
+    files: VecDeque<PathBuf>,
+    vm_name: String,
+    service: String,
+}
+
+impl TimelineSource {
+    fn next_entry(&mut self) -> Result<Option<LogEntry>> {
+        loop {
+            if let Some(entry) = self.entries.as_mut().and_then(|entries| entries.next()) {
+                return entry.map(Some);
+            }
+            match self.files.pop_front() {
+                Some(path) => self.entries = Some(LogEntryReader::new(open_log_file(&path)?)),
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+/// Write the entries from every retrieved log file into one chronologically ordered stream.
+///
+/// Each log file is already in order, so the files are merged as they are read, rather than
+/// loading every entry into memory. Each entry is prefixed with the VM and service it came from.
+///
+/// If a peer ID is supplied, only the entries that mention it are included.
+///
+/// Returns the number of entries written.
+pub fn write_timeline(
+    name: &str,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    peer_id: Option<&str>,
+    writer: &mut dyn Write,
+) -> Result<usize> {
+    let log_dir = get_log_dir(name)?;
+    let index = build_log_index(name)?;
+
+    let mut files_by_service = BTreeMap::new();
+    for file in index
+        .files
+        .into_iter()
+        .filter(|file| file.overlaps(from, to))
+    {
+        files_by_service
+            .entry((file.vm_name.clone(), file.service.clone()))
+            .or_default()
+            .push(file);
+    }
+    let mut sources = files_by_service
+        .into_iter()
+        .map(|((vm_name, service), mut files)| {
+            files.sort_by(|a, b| a.start.cmp(&b.start));
+            TimelineSource {
+                entries: None,
+                files: files
+                    .into_iter()
+                    .map(|file| log_dir.join(file.path))
+                    .collect(),
+                vm_name,
+                service,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    merge_sources(&mut sources, from, to, peer_id, writer)
+}
+
+// Merge the sources with a heap of the next entry from each of them, which is a k-way merge.
+fn merge_sources(
+    sources: &mut [TimelineSource],
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    peer_id: Option<&str>,
+    writer: &mut dyn Write,
+) -> Result<usize> {
+    let mut heap = BinaryHeap::new();
+    let mut heads: Vec<Option<LogEntry>> = Vec::with_capacity(sources.len());
+    for (i, source) in sources.iter_mut().enumerate() {
+        let entry = next_entry_in_window(source, from, to)?;
+        if let Some(entry) = &entry {
+            heap.push(Reverse((entry.timestamp, i)));
+        }
+        heads.push(entry);
+    }
+
+    let mut written = 0;
+    while let Some(Reverse((_, i))) = heap.pop() {
+        if let Some(entry) = heads[i].take() {
+            let include = peer_id.is_none_or(|peer_id| entry.message.contains(peer_id));
+            if include {
+                writeln!(
+                    writer,
+                    "{} {}/{} {} {}: {}",
+                    entry.timestamp.to_rfc3339(),
+                    sources[i].vm_name,
+                    sources[i].service,
+                    entry.level,
+                    entry.target,
+                    entry.message
+                )?;
+                written += 1;
+            }
+        }
+
+        let next = next_entry_in_window(&mut sources[i], from, to)?;
+        if let Some(entry) = &next {
+            heap.push(Reverse((entry.timestamp, i)));
+        }
+        heads[i] = next;
+    }
+
+    Ok(written)
+}
+
+// Returns the next entry that is within the window, or `None` when the source has no more.
+fn next_entry_in_window(
+    source: &mut TimelineSource,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<Option<LogEntry>> {
+    while let Some(entry) = source.next_entry()? {
+        if from.is_some_and(|from| entry.timestamp < from) {
+            continue;
+        }
+        if to.is_some_and(|to| entry.timestamp > to) {
+            return Ok(None);
+        }
+        return Ok(Some(entry));
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logs::line::parse_rfc3339;
+    use tempfile::TempDir;
+
+    fn get_source(dir: &TempDir, service: &str, files: &[&str]) -> TimelineSource {
+        let paths = files
+            .iter()
+            .enumerate()
+            .map(|(i, contents)| {
+                let path = dir.path().join(format!("{service}-{i}.log"));
+                std::fs::write(&path, contents).unwrap();
+                path
+            })
+            .collect();
+        TimelineSource {
+            entries: None,
+            files: paths,
+            vm_name: "alpha-node-1".to_string(),
+            service: service.to_string(),
+        }
+    }
+
+    fn merge(
+        sources: &mut [TimelineSource],
+        from: Option<&str>,
+        to: Option<&str>,
+        peer_id: Option<&str>,
+    ) -> Vec<String> {
+        let mut output = Vec::new();
+        let written = merge_sources(
+            sources,
+            from.and_then(parse_rfc3339),
+            to.and_then(parse_rfc3339),
+            peer_id,
+            &mut output,
+        )
+        .unwrap();
+        let lines = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(lines.len(), written);
+        lines
+    }
+
+    fn get_messages(lines: &[String]) -> Vec<&str> {
+        lines
+            .iter()
+            .map(|line| line.rsplit(": ").next().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_entries_from_all_sources_are_merged_in_order() {
+        let dir = TempDir::new().unwrap();
+        let mut sources = vec![
+            get_source(
+                &dir,
+                "antnode1",
+                &[
+                    "[2024-10-10T10:00:00Z INFO ant_node] a1\n\
+                     [2024-10-10T10:00:03Z INFO ant_node] a2\n",
+                    "[2024-10-10T10:00:05Z INFO ant_node] a3\n",
+                ],
+            ),
+            get_source(
+                &dir,
+                "antnode2",
+                &["[2024-10-10T10:00:01Z INFO ant_node] b1\n\
+                   [2024-10-10T10:00:04Z WARN ant_node] b2\n"],
+            ),
+            get_source(&dir, "antnode3", &[""]),
+        ];
+
+        let lines = merge(&mut sources, None, None, None);
+        assert_eq!(get_messages(&lines), vec!["a1", "b1", "a2", "b2", "a3"]);
+        assert_eq!(
+            lines[3],
+            "2024-10-10T10:00:04+00:00 alpha-node-1/antnode2 WARN ant_node: b2"
+        );
+    }
+
+    #[test]
+    fn test_merge_only_includes_entries_in_the_window() {
+        let dir = TempDir::new().unwrap();
+        let mut sources = vec![
+            get_source(
+                &dir,
+                "antnode1",
+                &["[2024-10-10T10:00:00Z INFO ant_node] a1\n\
+                   [2024-10-10T10:00:02Z INFO ant_node] a2\n\
+                   [2024-10-10T10:00:04Z INFO ant_node] a3\n"],
+            ),
+            get_source(
+                &dir,
+                "antnode2",
+                &["[2024-10-10T10:00:01Z INFO ant_node] b1\n\
+                   [2024-10-10T10:00:03Z INFO ant_node] b2\n\
+                   [2024-10-10T10:00:05Z INFO ant_node] b3\n"],
+            ),
+        ];
+
+        let lines = merge(
+            &mut sources,
+            Some("2024-10-10T10:00:01Z"),
+            Some("2024-10-10T10:00:03Z"),
+            None,
+        );
+        assert_eq!(get_messages(&lines), vec!["b1", "a2", "b2"]);
+    }
+
+    #[test]
+    fn test_merge_filters_by_peer_id() {
+        let dir = TempDir::new().unwrap();
+        let mut sources = vec![
+            get_source(
+                &dir,
+                "antnode1",
+                &["[2024-10-10T10:00:00Z INFO ant_node] dialled 12D3KooWA\n\
+                   [2024-10-10T10:00:02Z INFO ant_node] dialled 12D3KooWB\n"],
+            ),
+            get_source(
+                &dir,
+                "antnode2",
+                &["[2024-10-10T10:00:01Z INFO ant_node] removed 12D3KooWA\n"],
+            ),
+        ];
+
+        let lines = merge(&mut sources, None, None, Some("12D3KooWA"));
+        assert_eq!(
+            get_messages(&lines),
+            vec!["dialled 12D3KooWA", "removed 12D3KooWA"]
+        );
+    }
+
+    #[test]
+    fn test_next_entry_in_window_stops_at_the_end_of_the_window() {
+        let dir = TempDir::new().unwrap();
+        let mut source = get_source(
+            &dir,
+            "antnode1",
+            &[
+                "[2024-10-10T10:00:00Z INFO ant_node] a1\n",
+                "[2024-10-10T10:00:02Z INFO ant_node] a2\n\
+                 [2024-10-10T10:00:04Z INFO ant_node] a3\n",
+            ],
+        );
+        let from = parse_rfc3339("2024-10-10T10:00:01Z");
+        let to = parse_rfc3339("2024-10-10T10:00:03Z");
+
+        let entry = next_entry_in_window(&mut source, from, to)
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.message, "a2");
+        assert!(next_entry_in_window(&mut source, from, to)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -6,7 +6,7 @@
 mod cmd;
 
 use crate::cmd::{
-    network::{ChurnCommands, NetworkCommands},
+    network::{ChurnCommands, ChurnTimeline, NetworkCommands},
     nginx::NginxCommands,
     nodes,
     provision::ProvisionCommands,
@@ -17,7 +17,7 @@ use clap::Parser;
 use color_eyre::Result;
 use dotenv::dotenv;
 use sn_testnet_deploy::{
-    inventory::DeploymentInventoryService, setup::setup_dotenv_file, CloudProvider,
+    dry_run, inventory::DeploymentInventoryService, setup::setup_dotenv_file, CloudProvider,
     EnvironmentType, TestnetDeployBuilder,
 };
 use std::env;
@@ -27,6 +27,12 @@ use std::env;
 struct Opt {
     #[command(subcommand)]
     command: Commands,
+    /// Print the Ansible playbooks, Terraform commands, SSH commands and S3 changes that would be
+    /// run, rather than running them.
+    ///
+    /// Commands that only read state still run, so the plan reflects the current environment.
+    #[arg(global = true, long)]
+    dry_run: bool,
 }
 
 #[tokio::main]
@@ -36,8 +42,12 @@ async fn main() -> Result<()> {
     env_logger::init();
 
     let opt = Opt::parse();
-    match opt.command {
+    if opt.dry_run {
+        dry_run::enable();
+    }
+    let result = match opt.command {
         Commands::Bootstrap {
+            allow_destructive_changes,
             ansible_verbose,
             antctl_version,
             antnode_features,
@@ -54,6 +64,7 @@ async fn main() -> Result<()> {
             full_cone_private_node_volume_size,
             forks,
             interval,
+            lifetime,
             log_format,
             max_archived_log_files,
             max_log_files,
@@ -65,6 +76,7 @@ async fn main() -> Result<()> {
             node_vm_count,
             node_volume_size,
             node_vm_size,
+            owner,
             peer,
             provider,
             region,
@@ -78,64 +90,86 @@ async fn main() -> Result<()> {
             upnp_private_node_vm_count,
             upnp_private_node_volume_size,
         } => {
-            cmd::deployments::handle_bootstrap(
-                ansible_verbose,
-                antctl_version,
-                antnode_features,
-                antnode_version,
-                branch,
-                chunk_size,
-                node_env_variables,
-                environment_type,
-                evm_data_payments_address,
-                evm_network_type,
-                evm_payment_token_address,
-                evm_rpc_url,
-                forks,
-                full_cone_private_node_count,
-                full_cone_private_node_vm_count,
-                full_cone_private_node_volume_size,
-                interval,
-                log_format,
-                max_archived_log_files,
-                max_log_files,
-                name,
-                network_contacts_url,
-                network_id,
-                node_count,
-                node_vm_count,
-                node_vm_size,
-                node_volume_size,
-                peer,
-                provider,
-                region,
-                repo_owner,
-                rewards_address,
-                skip_binary_build,
-                symmetric_private_node_count,
-                symmetric_private_node_vm_count,
-                symmetric_private_node_volume_size,
-                upnp_private_node_count,
-                upnp_private_node_vm_count,
-                upnp_private_node_volume_size,
+            cmd::lock::run_with_lock(
+                &name.clone(),
+                "bootstrap",
+                cmd::deployments::handle_bootstrap(
+                    allow_destructive_changes,
+                    ansible_verbose,
+                    antctl_version,
+                    antnode_features,
+                    antnode_version,
+                    branch,
+                    chunk_size,
+                    node_env_variables,
+                    environment_type,
+                    evm_data_payments_address,
+                    evm_network_type,
+                    evm_payment_token_address,
+                    evm_rpc_url,
+                    forks,
+                    full_cone_private_node_count,
+                    full_cone_private_node_vm_count,
+                    full_cone_private_node_volume_size,
+                    interval,
+                    lifetime,
+                    log_format,
+                    max_archived_log_files,
+                    max_log_files,
+                    name,
+                    network_contacts_url,
+                    network_id,
+                    node_count,
+                    node_vm_count,
+                    node_vm_size,
+                    node_volume_size,
+                    owner,
+                    peer,
+                    provider,
+                    region,
+                    repo_owner,
+                    rewards_address,
+                    skip_binary_build,
+                    symmetric_private_node_count,
+                    symmetric_private_node_vm_count,
+                    symmetric_private_node_volume_size,
+                    upnp_private_node_count,
+                    upnp_private_node_vm_count,
+                    upnp_private_node_volume_size,
+                ),
             )
             .await?;
             Ok(())
         }
-        Commands::Clean { name, provider } => {
-            let testnet_deployer = TestnetDeployBuilder::default()
-                .environment_name(&name)
-                .provider(provider)
-                .build()?;
-
-            testnet_deployer.clean().await?;
+        Commands::Clean {
+            drain_funds,
+            name,
+            provider,
+        } => {
+            cmd::lock::run_with_lock(
+                &name.clone(),
+                "clean",
+                cmd::deployments::handle_clean(drain_funds, name, provider),
+            )
+            .await?;
             Ok(())
         }
         Commands::Clients(clients_cmd) => {
-            cmd::clients::handle_clients_command(clients_cmd).await?;
+            match clients_cmd.get_lock_details() {
+                Some((name, command)) => {
+                    cmd::lock::run_with_lock(
+                        &name,
+                        command,
+                        cmd::clients::handle_clients_command(clients_cmd),
+                    )
+                    .await?
+                }
+                None => cmd::clients::handle_clients_command(clients_cmd).await?,
+            }
             Ok(())
         }
         Commands::Deploy {
+            allow_destructive_changes,
             ansible_verbose,
             ant_version,
             antctl_version,
@@ -166,6 +200,7 @@ async fn main() -> Result<()> {
             initial_gas,
             initial_tokens,
             interval,
+            lifetime,
             log_format,
             max_archived_log_files,
             max_log_files,
@@ -179,6 +214,7 @@ async fn main() -> Result<()> {
             node_vm_count,
             node_vm_size,
             node_volume_size,
+            owner,
             peer_cache_node_count,
             peer_cache_node_vm_count,
             peer_cache_node_vm_size,
@@ -211,86 +247,106 @@ async fn main() -> Result<()> {
             upnp_private_node_vm_count,
             upnp_private_node_volume_size,
         } => {
-            cmd::deployments::handle_deploy(
-                ansible_verbose,
-                ant_version,
-                antctl_version,
-                antnode_features,
-                antnode_version,
-                branch,
-                chunk_size,
-                chunk_tracker_data_addresses,
-                chunk_tracker_services,
-                client_env_variables,
-                client_vm_count,
-                client_vm_size,
-                disable_logging,
-                disable_metrics,
-                environment_type,
-                evm_data_payments_address,
-                evm_network_type,
-                evm_node_vm_size,
-                evm_payment_token_address,
-                evm_rpc_url,
-                full_cone_vm_size,
-                full_cone_private_node_count,
-                full_cone_private_node_vm_count,
-                full_cone_private_node_volume_size,
-                forks,
-                funding_wallet_secret_key,
-                genesis_node_volume_size,
-                initial_gas,
-                initial_tokens,
-                interval,
-                log_format,
-                max_archived_log_files,
-                max_log_files,
-                max_uploads,
-                name,
-                network_id,
-                network_contacts_file_name,
-                network_dashboard_branch,
-                node_count,
-                node_env_variables,
-                node_vm_count,
-                node_vm_size,
-                node_volume_size,
-                peer_cache_node_count,
-                peer_cache_node_vm_count,
-                peer_cache_node_vm_size,
-                peer_cache_node_volume_size,
-                port_restricted_cone_vm_size,
-                port_restricted_cone_private_node_count,
-                port_restricted_cone_private_node_vm_count,
-                port_restricted_cone_private_node_volume_size,
-                provider,
-                public_rpc,
-                region,
-                repo_owner,
-                rewards_address,
-                single_node_payment,
-                skip_binary_build,
-                start_chunk_trackers,
-                start_download_verifier,
-                start_performance_verifier,
-                start_random_verifier,
-                symmetric_nat_gateway_vm_size,
-                symmetric_private_node_count,
-                symmetric_private_node_vm_count,
-                symmetric_private_node_volume_size,
-                to_genesis,
-                uploaders_count,
-                upload_interval,
-                upload_size,
-                upnp_private_node_count,
-                upnp_private_node_vm_count,
-                upnp_private_node_volume_size,
-                upnp_vm_size,
+            cmd::lock::run_with_lock(
+                &name.clone(),
+                "deploy",
+                cmd::deployments::handle_deploy(
+                    allow_destructive_changes,
+                    ansible_verbose,
+                    ant_version,
+                    antctl_version,
+                    antnode_features,
+                    antnode_version,
+                    branch,
+                    chunk_size,
+                    chunk_tracker_data_addresses,
+                    chunk_tracker_services,
+                    client_env_variables,
+                    client_vm_count,
+                    client_vm_size,
+                    disable_logging,
+                    disable_metrics,
+                    environment_type,
+                    evm_data_payments_address,
+                    evm_network_type,
+                    evm_node_vm_size,
+                    evm_payment_token_address,
+                    evm_rpc_url,
+                    full_cone_vm_size,
+                    full_cone_private_node_count,
+                    full_cone_private_node_vm_count,
+                    full_cone_private_node_volume_size,
+                    forks,
+                    funding_wallet_secret_key,
+                    genesis_node_volume_size,
+                    initial_gas,
+                    initial_tokens,
+                    interval,
+                    lifetime,
+                    log_format,
+                    max_archived_log_files,
+                    max_log_files,
+                    max_uploads,
+                    name,
+                    network_id,
+                    network_contacts_file_name,
+                    network_dashboard_branch,
+                    node_count,
+                    node_env_variables,
+                    node_vm_count,
+                    node_vm_size,
+                    node_volume_size,
+                    owner,
+                    peer_cache_node_count,
+                    peer_cache_node_vm_count,
+                    peer_cache_node_vm_size,
+                    peer_cache_node_volume_size,
+                    port_restricted_cone_vm_size,
+                    port_restricted_cone_private_node_count,
+                    port_restricted_cone_private_node_vm_count,
+                    port_restricted_cone_private_node_volume_size,
+                    provider,
+                    public_rpc,
+                    region,
+                    repo_owner,
+                    rewards_address,
+                    single_node_payment,
+                    skip_binary_build,
+                    start_chunk_trackers,
+                    start_download_verifier,
+                    start_performance_verifier,
+                    start_random_verifier,
+                    symmetric_nat_gateway_vm_size,
+                    symmetric_private_node_count,
+                    symmetric_private_node_vm_count,
+                    symmetric_private_node_volume_size,
+                    to_genesis,
+                    uploaders_count,
+                    upload_interval,
+                    upload_size,
+                    upnp_private_node_count,
+                    upnp_private_node_vm_count,
+                    upnp_private_node_volume_size,
+                    upnp_vm_size,
+                ),
             )
             .await?;
             Ok(())
         }
+        Commands::Drift {
+            json,
+            name,
+            provider,
+        } => {
+            cmd::misc::handle_drift(json, name, provider).await?;
+            Ok(())
+        }
+        Commands::Environments(environments_cmd) => {
+            cmd::environments::handle_environments_command(environments_cmd).await?;
+            Ok(())
+        }
         Commands::ExtendVolumeSize {
+            allow_destructive_changes,
             ansible_verbose,
             genesis_node_volume_size,
             full_cone_private_node_volume_size,
@@ -301,6 +357,7 @@ async fn main() -> Result<()> {
             symmetric_private_node_volume_size,
         } => {
             cmd::misc::handle_extend_volume_size(
+                allow_destructive_changes,
                 ansible_verbose,
                 genesis_node_volume_size,
                 full_cone_private_node_volume_size,
@@ -336,6 +393,10 @@ async fn main() -> Result<()> {
             .await?;
             Ok(())
         }
+        Commands::Lock(lock_cmd) => {
+            cmd::lock::handle_lock_command(lock_cmd).await?;
+            Ok(())
+        }
         Commands::Logs(log_cmd) => {
             cmd::logs::handle_logs_command(log_cmd).await?;
             Ok(())
@@ -360,37 +421,67 @@ async fn main() -> Result<()> {
                     churn_cycles,
                     concurrent_churns,
                     interval,
+                    name,
                     retain_peer_id,
+                    timeline_format,
+                    timeline_path,
+                    verify_timeout,
                     ..
                 } => {
-                    cmd::network::handle_fixed_interval_network_churn(
-                        inventory,
-                        interval,
-                        concurrent_churns,
-                        retain_peer_id,
-                        churn_cycles,
+                    let timeline = ChurnTimeline::new(&name, timeline_path, timeline_format)?;
+                    cmd::lock::run_with_lock(
+                        &name,
+                        "churn",
+                        cmd::network::handle_fixed_interval_network_churn(
+                            inventory,
+                            interval,
+                            concurrent_churns,
+                            retain_peer_id,
+                            churn_cycles,
+                            timeline,
+                            verify_timeout,
+                        ),
                     )
                     .await?;
                 }
                 ChurnCommands::RandomInterval {
                     churn_count,
                     churn_cycles,
+                    name,
                     retain_peer_id,
                     time_frame,
+                    timeline_format,
+                    timeline_path,
+                    verify_timeout,
                     ..
                 } => {
-                    cmd::network::handle_random_interval_network_churn(
-                        inventory,
-                        time_frame,
-                        churn_count,
-                        retain_peer_id,
-                        churn_cycles,
+                    let timeline = ChurnTimeline::new(&name, timeline_path, timeline_format)?;
+                    cmd::lock::run_with_lock(
+                        &name,
+                        "churn",
+                        cmd::network::handle_random_interval_network_churn(
+                            inventory,
+                            time_frame,
+                            churn_count,
+                            retain_peer_id,
+                            churn_cycles,
+                            timeline,
+                            verify_timeout,
+                        ),
                     )
                     .await?;
                 }
             }
             Ok(())
         }
+        Commands::Network(NetworkCommands::Health {
+            concurrent_requests,
+            json,
+            name,
+        }) => {
+            cmd::network::handle_network_health(concurrent_requests, json, name).await?;
+            Ok(())
+        }
         Commands::Network(NetworkCommands::UpdateNodeLogLevel {
             concurrent_updates,
             log_level,
@@ -414,7 +505,12 @@ async fn main() -> Result<()> {
             node_type,
             provider,
         } => {
-            nodes::handle_reset_command(custom_inventory, forks, name, node_type, provider).await?;
+            cmd::lock::run_with_lock(
+                &name.clone(),
+                "reset",
+                nodes::handle_reset_command(custom_inventory, forks, name, node_type, provider),
+            )
+            .await?;
             Ok(())
         }
         Commands::Start {
@@ -425,13 +521,17 @@ async fn main() -> Result<()> {
             node_type,
             provider,
         } => {
-            nodes::handle_start_command(
-                custom_inventory,
-                forks,
-                interval,
-                name,
-                node_type,
-                provider,
+            cmd::lock::run_with_lock(
+                &name.clone(),
+                "start",
+                nodes::handle_start_command(
+                    custom_inventory,
+                    forks,
+                    interval,
+                    name,
+                    node_type,
+                    provider,
+                ),
             )
             .await?;
             Ok(())
@@ -471,15 +571,19 @@ async fn main() -> Result<()> {
             provider,
             service_name,
         } => {
-            nodes::handle_stop_command(
-                custom_inventory,
-                delay,
-                forks,
-                interval,
-                name,
-                node_type,
-                provider,
-                service_name,
+            cmd::lock::run_with_lock(
+                &name.clone(),
+                "stop",
+                nodes::handle_stop_command(
+                    custom_inventory,
+                    delay,
+                    forks,
+                    interval,
+                    name,
+                    node_type,
+                    provider,
+                    service_name,
+                ),
             )
             .await?;
             Ok(())
@@ -508,20 +612,24 @@ async fn main() -> Result<()> {
             repo_owner,
             version,
         } => {
-            cmd::upgrade::handle_upgrade_command(
-                ansible_verbose,
-                branch,
-                custom_inventory,
-                node_env_variables,
-                force,
-                forks,
-                interval,
-                name,
-                node_type,
-                provider,
-                pre_upgrade_delay,
-                repo_owner,
-                version,
+            cmd::lock::run_with_lock(
+                &name.clone(),
+                "upgrade",
+                cmd::upgrade::handle_upgrade_command(
+                    ansible_verbose,
+                    branch,
+                    custom_inventory,
+                    node_env_variables,
+                    force,
+                    forks,
+                    interval,
+                    name,
+                    node_type,
+                    provider,
+                    pre_upgrade_delay,
+                    repo_owner,
+                    version,
+                ),
             )
             .await?;
             Ok(())
@@ -533,17 +641,22 @@ async fn main() -> Result<()> {
             provider,
             version,
         } => {
-            cmd::upgrade::handle_upgrade_antctl_command(
-                custom_inventory,
-                name,
-                node_type,
-                provider,
-                version,
+            cmd::lock::run_with_lock(
+                &name.clone(),
+                "upgrade-antctl",
+                cmd::upgrade::handle_upgrade_antctl_command(
+                    custom_inventory,
+                    name,
+                    node_type,
+                    provider,
+                    version,
+                ),
             )
             .await?;
             Ok(())
         }
         Commands::Upscale {
+            allow_destructive_changes,
             ansible_verbose,
             ant_version,
             antctl_version,
@@ -576,38 +689,43 @@ async fn main() -> Result<()> {
             repo_owner,
             skip_binary_build,
         } => {
-            cmd::deployments::handle_upscale(
-                ansible_verbose,
-                ant_version,
-                antctl_version,
-                antnode_version,
-                branch,
-                desired_client_vm_count,
-                desired_node_count,
-                desired_full_cone_private_node_count,
-                desired_full_cone_private_node_vm_count,
-                desired_node_vm_count,
-                desired_peer_cache_node_count,
-                desired_peer_cache_node_vm_count,
-                desired_symmetric_private_node_count,
-                desired_symmetric_private_node_vm_count,
-                desired_uploaders_count,
-                start_download_verifier,
-                start_performance_verifier,
-                start_random_verifier,
-                funding_wallet_secret_key,
-                infra_only,
-                interval,
-                max_archived_log_files,
-                max_log_files,
-                name,
-                network_dashboard_branch,
-                node_env_variables,
-                plan,
-                provider,
-                public_rpc,
-                repo_owner,
-                skip_binary_build,
+            cmd::lock::run_with_lock(
+                &name.clone(),
+                "upscale",
+                cmd::deployments::handle_upscale(
+                    allow_destructive_changes,
+                    ansible_verbose,
+                    ant_version,
+                    antctl_version,
+                    antnode_version,
+                    branch,
+                    desired_client_vm_count,
+                    desired_node_count,
+                    desired_full_cone_private_node_count,
+                    desired_full_cone_private_node_vm_count,
+                    desired_node_vm_count,
+                    desired_peer_cache_node_count,
+                    desired_peer_cache_node_vm_count,
+                    desired_symmetric_private_node_count,
+                    desired_symmetric_private_node_vm_count,
+                    desired_uploaders_count,
+                    start_download_verifier,
+                    start_performance_verifier,
+                    start_random_verifier,
+                    funding_wallet_secret_key,
+                    infra_only,
+                    interval,
+                    max_archived_log_files,
+                    max_log_files,
+                    name,
+                    network_dashboard_branch,
+                    node_env_variables,
+                    plan,
+                    provider,
+                    public_rpc,
+                    repo_owner,
+                    skip_binary_build,
+                ),
             )
             .await?;
             Ok(())
@@ -635,41 +753,61 @@ async fn main() -> Result<()> {
             stop_interval,
             version,
         } => {
-            nodes::handle_reset_to_n_nodes_command(
-                custom_inventory,
-                evm_network_type,
-                forks,
-                name,
-                node_count,
-                node_type,
-                provider,
-                start_interval,
-                stop_interval,
-                version,
+            cmd::lock::run_with_lock(
+                &name.clone(),
+                "reset-to-n-nodes",
+                nodes::handle_reset_to_n_nodes_command(
+                    custom_inventory,
+                    evm_network_type,
+                    forks,
+                    name,
+                    node_count,
+                    node_type,
+                    provider,
+                    start_interval,
+                    stop_interval,
+                    version,
+                ),
             )
             .await?;
             Ok(())
         }
         Commands::Provision(provision_cmd) => match provision_cmd {
-            ProvisionCommands::Clients { name } => {
-                cmd::provision::handle_provision_clients(name).await?;
+            ProvisionCommands::Clients {
+                funding_wallet_secret_key,
+                name,
+            } => {
+                cmd::lock::run_with_lock(
+                    &name.clone(),
+                    "provision clients",
+                    cmd::provision::handle_provision_clients(name, funding_wallet_secret_key),
+                )
+                .await?;
                 Ok(())
             }
             ProvisionCommands::FullConePrivateNodes {
                 name,
                 disable_nodes,
             } => {
-                cmd::provision::handle_provision_full_cone_private_nodes(name, disable_nodes)
-                    .await?;
+                cmd::lock::run_with_lock(
+                    &name.clone(),
+                    "provision full-cone-private-nodes",
+                    cmd::provision::handle_provision_full_cone_private_nodes(name, disable_nodes),
+                )
+                .await?;
                 Ok(())
             }
             ProvisionCommands::PortRestrictedConePrivateNodes {
                 name,
                 disable_nodes,
             } => {
-                cmd::provision::handle_provision_port_restricted_cone_private_nodes(
-                    name,
-                    disable_nodes,
+                cmd::lock::run_with_lock(
+                    &name.clone(),
+                    "provision port-restricted-cone-private-nodes",
+                    cmd::provision::handle_provision_port_restricted_cone_private_nodes(
+                        name,
+                        disable_nodes,
+                    ),
                 )
                 .await?;
                 Ok(())
@@ -678,32 +816,55 @@ async fn main() -> Result<()> {
                 name,
                 disable_nodes,
             } => {
-                cmd::provision::handle_provision_peer_cache_nodes(name, disable_nodes).await?;
+                cmd::lock::run_with_lock(
+                    &name.clone(),
+                    "provision peer-cache-nodes",
+                    cmd::provision::handle_provision_peer_cache_nodes(name, disable_nodes),
+                )
+                .await?;
                 Ok(())
             }
             ProvisionCommands::GenericNodes {
                 name,
                 disable_nodes,
             } => {
-                cmd::provision::handle_provision_generic_nodes(name, disable_nodes).await?;
+                cmd::lock::run_with_lock(
+                    &name.clone(),
+                    "provision generic-nodes",
+                    cmd::provision::handle_provision_generic_nodes(name, disable_nodes),
+                )
+                .await?;
                 Ok(())
             }
             ProvisionCommands::SymmetricPrivateNodes {
                 name,
                 disable_nodes,
             } => {
-                cmd::provision::handle_provision_symmetric_private_nodes(name, disable_nodes)
-                    .await?;
+                cmd::lock::run_with_lock(
+                    &name.clone(),
+                    "provision symmetric-private-nodes",
+                    cmd::provision::handle_provision_symmetric_private_nodes(name, disable_nodes),
+                )
+                .await?;
                 Ok(())
             }
             ProvisionCommands::UpnpNodes {
                 name,
                 disable_nodes,
             } => {
-                cmd::provision::handle_provision_upnp_nodes(name, disable_nodes).await?;
+                cmd::lock::run_with_lock(
+                    &name.clone(),
+                    "provision upnp-nodes",
+                    cmd::provision::handle_provision_upnp_nodes(name, disable_nodes),
+                )
+                .await?;
                 Ok(())
             }
         },
+        Commands::Sweep { json, provider } => {
+            cmd::misc::handle_sweep(json, provider).await?;
+            Ok(())
+        }
         Commands::Telegraf(telegraf_cmd) => match telegraf_cmd {
             TelegrafCommands::Start {
                 custom_inventory,
@@ -764,5 +925,9 @@ async fn main() -> Result<()> {
                     .await
             }
         },
+    };
+    if opt.dry_run {
+        dry_run::print_plan();
     }
+    result
 }
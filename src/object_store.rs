@@ -0,0 +1,431 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crate::{
+    dry_run::{self, PlannedAction},
+    error::{Error, Result},
+    inventory::get_data_directory,
+    s3::S3Repository,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use walkdir::WalkDir;
+
+pub const DEFAULT_S3_REGION: &str = "eu-west-2";
+
+/// The buckets used by the deployer, which are mapped to the names of the real buckets by the
+/// store.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bucket {
+    /// The options used for deploying or provisioning an environment.
+    DeploymentOptions,
+    /// The details of each environment, with one object per environment.
+    EnvironmentDetails,
+    /// The Terraform state for each workspace, which is only written by Terraform.
+    TerraformState,
+    /// Logs, locks and network contacts files for the environments.
+    Testnet,
+}
+
+/// The names of the real buckets.
+///
+/// Each can be overridden with an environment variable, so another organisation can use its own
+/// buckets.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BucketNames {
+    pub deployment_options: String,
+    pub environment_details: String,
+    pub terraform_state: String,
+    pub testnet: String,
+}
+
+impl Default for BucketNames {
+    fn default() -> Self {
+        Self {
+            deployment_options: "ant-testnet-deploy-options".to_string(),
+            environment_details: "sn-environment-type".to_string(),
+            terraform_state: "maidsafe-org-infra-tfstate".to_string(),
+            testnet: "sn-testnet".to_string(),
+        }
+    }
+}
+
+impl BucketNames {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            deployment_options: std::env::var("OBJECT_STORE_DEPLOYMENT_OPTIONS_BUCKET")
+                .unwrap_or(defaults.deployment_options),
+            environment_details: std::env::var("OBJECT_STORE_ENVIRONMENT_DETAILS_BUCKET")
+                .unwrap_or(defaults.environment_details),
+            terraform_state: std::env::var("TERRAFORM_STATE_BUCKET_NAME")
+                .unwrap_or(defaults.terraform_state),
+            testnet: std::env::var("OBJECT_STORE_TESTNET_BUCKET").unwrap_or(defaults.testnet),
+        }
+    }
+
+    pub fn get(&self, bucket: Bucket) -> &str {
+        match bucket {
+            Bucket::DeploymentOptions => &self.deployment_options,
+            Bucket::EnvironmentDetails => &self.environment_details,
+            Bucket::TerraformState => &self.terraform_state,
+            Bucket::Testnet => &self.testnet,
+        }
+    }
+}
+
+/// Storage for the objects that are shared between the users of the deployer, such as environment
+/// details, locks and logs.
+///
+/// Object keys use '/' to separate folders, regardless of the store.
+#[async_trait]
+pub trait ObjectStore: Send + Sync {
+    /// A URL for an object, which is used for output.
+    fn get_object_url(&self, bucket: Bucket, object_key: &str) -> String;
+
+    /// Upload a file to the given key, which may include a folder path.
+    ///
+    /// A public object can be read by anyone, if the store supports it.
+    async fn upload_object(
+        &self,
+        bucket: Bucket,
+        object_key: &str,
+        file_path: &Path,
+        public: bool,
+    ) -> Result<()>;
+
+    /// Write an object from memory, without any output.
+    ///
+    /// This is intended for small objects that are written frequently, like lock files.
+    async fn put_object_contents(
+        &self,
+        bucket: Bucket,
+        object_key: &str,
+        contents: Vec<u8>,
+    ) -> Result<()>;
+
+    /// Read an object into memory, or return `None` if the object does not exist.
+    async fn get_object_contents(
+        &self,
+        bucket: Bucket,
+        object_key: &str,
+    ) -> Result<Option<Vec<u8>>>;
+
+    async fn download_object(
+        &self,
+        bucket: Bucket,
+        object_key: &str,
+        dest_path: &Path,
+    ) -> Result<()>;
+
+    /// Download all the objects in a folder, including its subfolders.
+    ///
+    /// Each object is saved at its key under the destination, and objects that have already been
+    /// downloaded are skipped.
+    async fn download_folder(
+        &self,
+        bucket: Bucket,
+        folder_path: &str,
+        dest_path: &Path,
+    ) -> Result<()>;
+
+    async fn delete_object(&self, bucket: Bucket, object_key: &str) -> Result<()>;
+
+    /// Delete all the objects in a folder, including its subfolders.
+    async fn delete_folder(&self, bucket: Bucket, folder_path: &str) -> Result<()>;
+
+    /// Whether the folder directly contains any objects.
+    async fn folder_exists(&self, bucket: Bucket, folder_path: &str) -> Result<bool>;
+
+    /// List the keys of all the objects in the bucket that begin with the prefix.
+    async fn list_object_keys(&self, bucket: Bucket, prefix: &str) -> Result<Vec<String>>;
+
+    /// The latest time any object that begins with the prefix was modified, or `None` if there are
+    /// no such objects.
+    async fn get_last_modified_time(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+    ) -> Result<Option<DateTime<Utc>>>;
+
+    /// Upload a file using its file name as the key.
+    async fn upload_file(&self, bucket: Bucket, file_path: &Path, public: bool) -> Result<()> {
+        let object_key = file_path
+            .file_name()
+            .ok_or_else(|| Error::FilenameNotRetrieved)?
+            .to_str()
+            .ok_or_else(|| Error::FilenameNotRetrieved)?;
+        self.upload_object(bucket, object_key, file_path, public)
+            .await
+    }
+}
+
+/// Get the object store that is configured by the environment.
+///
+/// `OBJECT_STORE` selects the store, which is either `s3` or `local`, and defaults to `s3`.
+///
+/// For S3, `OBJECT_STORE_REGION` defaults to `eu-west-2`, and `OBJECT_STORE_ENDPOINT` can be used
+/// for an S3 compatible service like MinIO. The local store keeps the objects under
+/// `OBJECT_STORE_PATH`, which defaults to a directory in the data directory.
+pub fn get_object_store() -> Result<Arc<dyn ObjectStore>> {
+    let bucket_names = BucketNames::from_env();
+    let store = std::env::var("OBJECT_STORE").unwrap_or_else(|_| "s3".to_string());
+    match store.as_str() {
+        "local" => {
+            let root_path = match std::env::var("OBJECT_STORE_PATH") {
+                Ok(path) => PathBuf::from(path),
+                Err(_) => get_data_directory()
+                    .map_err(|_| Error::CouldNotRetrieveDataDirectory)?
+                    .join("object-store"),
+            };
+            Ok(Arc::new(LocalObjectStore::new(root_path, bucket_names)))
+        }
+        "s3" => {
+            let region = std::env::var("OBJECT_STORE_REGION")
+                .unwrap_or_else(|_| DEFAULT_S3_REGION.to_string());
+            let endpoint_url = std::env::var("OBJECT_STORE_ENDPOINT").ok();
+            Ok(Arc::new(S3Repository::new(
+                &region,
+                endpoint_url,
+                bucket_names,
+            )))
+        }
+        _ => Err(Error::ObjectStoreNotSupported(store)),
+    }
+}
+
+/// Keeps the objects as files on the local machine, at `<root>/<bucket name>/<object key>`.
+///
+/// This is for development without access to the shared buckets. Nothing is public, so the
+/// `public` flag has no effect.
+#[derive(Clone, Debug)]
+pub struct LocalObjectStore {
+    pub bucket_names: BucketNames,
+    pub root_path: PathBuf,
+}
+
+impl LocalObjectStore {
+    pub fn new(root_path: PathBuf, bucket_names: BucketNames) -> Self {
+        Self {
+            bucket_names,
+            root_path,
+        }
+    }
+
+    fn get_bucket_path(&self, bucket: Bucket) -> PathBuf {
+        self.root_path.join(self.bucket_names.get(bucket))
+    }
+
+    fn get_object_path(&self, bucket: Bucket, object_key: &str) -> PathBuf {
+        object_key
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .fold(self.get_bucket_path(bucket), |path, part| path.join(part))
+    }
+
+    /// The keys of all the objects under a path in the bucket.
+    fn get_object_keys(&self, bucket: Bucket, path: &Path) -> Result<Vec<String>> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let bucket_path = self.get_bucket_path(bucket);
+        let mut keys = Vec::new();
+        for entry in WalkDir::new(path).sort_by_file_name() {
+            let entry = entry.map_err(std::io::Error::from)?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let key = entry
+                .path()
+                .strip_prefix(&bucket_path)?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+}
+
+#[async_trait]
+impl ObjectStore for LocalObjectStore {
+    fn get_object_url(&self, bucket: Bucket, object_key: &str) -> String {
+        format!(
+            "file://{}",
+            self.get_object_path(bucket, object_key).to_string_lossy()
+        )
+    }
+
+    async fn upload_object(
+        &self,
+        bucket: Bucket,
+        object_key: &str,
+        file_path: &Path,
+        _public: bool,
+    ) -> Result<()> {
+        if dry_run::is_enabled() {
+            dry_run::record(PlannedAction::ObjectUpload {
+                source: file_path.to_string_lossy().to_string(),
+                url: self.get_object_url(bucket, object_key),
+            });
+            return Ok(());
+        }
+        let object_path = self.get_object_path(bucket, object_key);
+        if let Some(parent) = object_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::copy(file_path, &object_path).await?;
+        println!(
+            "{object_key} has been copied to {}",
+            object_path.to_string_lossy()
+        );
+        Ok(())
+    }
+
+    async fn put_object_contents(
+        &self,
+        bucket: Bucket,
+        object_key: &str,
+        contents: Vec<u8>,
+    ) -> Result<()> {
+        if dry_run::is_enabled() {
+            dry_run::record(PlannedAction::ObjectUpload {
+                source: format!("{} bytes from memory", contents.len()),
+                url: self.get_object_url(bucket, object_key),
+            });
+            return Ok(());
+        }
+        let object_path = self.get_object_path(bucket, object_key);
+        if let Some(parent) = object_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(object_path, contents).await?;
+        Ok(())
+    }
+
+    async fn get_object_contents(
+        &self,
+        bucket: Bucket,
+        object_key: &str,
+    ) -> Result<Option<Vec<u8>>> {
+        let object_path = self.get_object_path(bucket, object_key);
+        if !object_path.is_file() {
+            return Ok(None);
+        }
+        Ok(Some(tokio::fs::read(object_path).await?))
+    }
+
+    async fn download_object(
+        &self,
+        bucket: Bucket,
+        object_key: &str,
+        dest_path: &Path,
+    ) -> Result<()> {
+        let object_path = self.get_object_path(bucket, object_key);
+        if !object_path.is_file() {
+            return Err(Error::GetS3ObjectError(
+                object_key.to_string(),
+                self.bucket_names.get(bucket).to_string(),
+            ));
+        }
+        if let Some(parent) = dest_path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::copy(object_path, dest_path).await?;
+        Ok(())
+    }
+
+    async fn download_folder(
+        &self,
+        bucket: Bucket,
+        folder_path: &str,
+        dest_path: &Path,
+    ) -> Result<()> {
+        tokio::fs::create_dir_all(dest_path).await?;
+        let keys = self.get_object_keys(bucket, &self.get_object_path(bucket, folder_path))?;
+        for key in keys {
+            let dest_file_path = dest_path.join(&key);
+            if dest_file_path.exists() {
+                println!("Has already been retrieved in a previous sync.");
+                continue;
+            }
+            self.download_object(bucket, &key, &dest_file_path).await?;
+            println!("Saved at {}", dest_file_path.to_string_lossy());
+        }
+        Ok(())
+    }
+
+    async fn delete_object(&self, bucket: Bucket, object_key: &str) -> Result<()> {
+        if dry_run::is_enabled() {
+            dry_run::record(PlannedAction::ObjectDelete {
+                url: self.get_object_url(bucket, object_key),
+            });
+            return Ok(());
+        }
+        let object_path = self.get_object_path(bucket, object_key);
+        if object_path.is_file() {
+            println!("Deleting {object_key}...");
+            tokio::fs::remove_file(object_path).await?;
+        }
+        Ok(())
+    }
+
+    async fn delete_folder(&self, bucket: Bucket, folder_path: &str) -> Result<()> {
+        if dry_run::is_enabled() {
+            dry_run::record(PlannedAction::ObjectDelete {
+                url: format!("{}/*", self.get_object_url(bucket, folder_path)),
+            });
+            return Ok(());
+        }
+        let path = self.get_object_path(bucket, folder_path);
+        if path.is_dir() {
+            println!("Deleting {folder_path}...");
+            tokio::fs::remove_dir_all(path).await?;
+        }
+        Ok(())
+    }
+
+    async fn folder_exists(&self, bucket: Bucket, folder_path: &str) -> Result<bool> {
+        let path = self.get_object_path(bucket, folder_path);
+        if !path.is_dir() {
+            return Ok(false);
+        }
+        for entry in std::fs::read_dir(path)? {
+            if entry?.file_type()?.is_file() {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    async fn list_object_keys(&self, bucket: Bucket, prefix: &str) -> Result<Vec<String>> {
+        let keys = self.get_object_keys(bucket, &self.get_bucket_path(bucket))?;
+        Ok(keys
+            .into_iter()
+            .filter(|key| key.starts_with(prefix))
+            .collect())
+    }
+
+    async fn get_last_modified_time(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let mut last_modified = None;
+        for key in self.list_object_keys(bucket, prefix).await? {
+            let modified: DateTime<Utc> = std::fs::metadata(self.get_object_path(bucket, &key))?
+                .modified()?
+                .into();
+            last_modified = last_modified.max(Some(modified));
+        }
+        Ok(last_modified)
+    }
+}
@@ -0,0 +1,238 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crate::{
+    dry_run,
+    error::{Error, Result},
+    get_environment_details, get_environment_owner,
+    inventory::{DeploymentInventory, VirtualMachine},
+    object_store::ObjectStore,
+    terraform::TerraformResource,
+    EnvironmentType, NodeType,
+};
+use inquire::{InquireError, Text};
+
+/// A comma-separated list of the operators who may run destructive commands against Production
+/// environments. If it's not set, any operator may run them.
+///
+/// The operator is identified by the `USER` variable, which anyone can set, so the list only
+/// guards against mistakes. It is not access control: that is provided by the credentials for the
+/// cloud provider and the object store.
+const PRODUCTION_OPERATORS_VAR: &str = "PRODUCTION_OPERATORS";
+
+/// The safeguards for destructive commands, such as `clean`, `reset` and `stop`, which depend on
+/// the type of the environment.
+#[derive(Clone, Debug)]
+pub struct EnvironmentPolicy {
+    pub environment_type: EnvironmentType,
+    /// The operator must type the name of the environment to confirm the command.
+    pub confirm_name: bool,
+    /// A preview of what the command will change is shown before it runs.
+    pub preview: bool,
+    /// Only these operators may run destructive commands. This is advisory, because the operator
+    /// is identified by the `USER` variable.
+    pub allowed_operators: Option<Vec<String>>,
+    /// Funds are drained when the environment is cleaned, without it being requested.
+    pub drain_funds_by_default: bool,
+}
+
+impl EnvironmentPolicy {
+    pub fn for_environment_type(environment_type: &EnvironmentType) -> Self {
+        match environment_type {
+            EnvironmentType::Production => Self {
+                environment_type: environment_type.clone(),
+                confirm_name: true,
+                preview: true,
+                allowed_operators: std::env::var(PRODUCTION_OPERATORS_VAR).ok().map(|value| {
+                    value
+                        .split(',')
+                        .map(|operator| operator.trim().to_string())
+                        .filter(|operator| !operator.is_empty())
+                        .collect()
+                }),
+                drain_funds_by_default: false,
+            },
+            EnvironmentType::Development | EnvironmentType::Staging => Self {
+                environment_type: environment_type.clone(),
+                confirm_name: false,
+                preview: false,
+                allowed_operators: None,
+                drain_funds_by_default: true,
+            },
+        }
+    }
+
+    /// Get the policy for an environment, based on the type in its environment details.
+    ///
+    /// If the details can't be retrieved, the environment could be of any type, so it gets the
+    /// strictest policy, which is for Production. An environment without details can then still be
+    /// cleaned, once its name is confirmed.
+    pub async fn get(name: &str, object_store: &dyn ObjectStore) -> Result<Self> {
+        let environment_type = match get_environment_details(name, object_store).await {
+            Ok(details) => details.environment_type,
+            Err(err) => {
+                println!(
+                    "Failed to get environment details for {name}: {err}. Applying the Production \
+                     policy."
+                );
+                EnvironmentType::Production
+            }
+        };
+        Ok(Self::for_environment_type(&environment_type))
+    }
+
+    pub fn should_drain_funds(&self, requested: bool) -> bool {
+        requested || self.drain_funds_by_default
+    }
+
+    /// Check that the current operator may run the command, show the preview and have the
+    /// operator confirm the name of the environment, as required by the policy.
+    ///
+    /// The preview is only generated if the policy requires it. The confirmation can't be given
+    /// when running non-interactively, so the command is refused. It isn't needed for a dry run.
+    pub fn enforce(
+        &self,
+        name: &str,
+        command: &str,
+        get_preview: impl FnOnce() -> Result<DestructivePreview>,
+    ) -> Result<()> {
+        if let Some(allowed_operators) = &self.allowed_operators {
+            let operator = get_environment_owner(None).unwrap_or_default();
+            if !allowed_operators.contains(&operator) {
+                return Err(Error::OperatorNotAllowed {
+                    operator,
+                    name: name.to_string(),
+                    environment_type: self.environment_type.to_string(),
+                });
+            }
+        }
+
+        if self.preview {
+            get_preview()?.print(name, command);
+        }
+
+        if self.confirm_name && !dry_run::is_enabled() {
+            let confirmation = match Text::new(&format!(
+                "{name} is a {} environment. Type its name to run the '{}' command:",
+                self.environment_type, command
+            ))
+            .prompt()
+            {
+                Ok(confirmation) => confirmation,
+                Err(InquireError::NotTTY) => String::new(),
+                Err(err) => return Err(err.into()),
+            };
+            if confirmation.trim() != name {
+                return Err(Error::EnvironmentNameNotConfirmed(name.to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describes what a destructive command will change, before it runs.
+#[derive(Clone, Debug)]
+pub struct DestructivePreview {
+    pub description: String,
+    /// The VMs or resources the command applies to.
+    pub targets: Vec<String>,
+}
+
+impl DestructivePreview {
+    /// A preview of the destruction of all the resources in the Terraform state.
+    pub fn from_terraform_state(resources: &[TerraformResource]) -> Self {
+        Self {
+            description: format!(
+                "{} resources in the Terraform state will be destroyed, including these VMs",
+                resources.len()
+            ),
+            targets: resources
+                .iter()
+                .filter(|resource| {
+                    resource.resource_type == "digitalocean_droplet"
+                        || resource.resource_type == "aws_instance"
+                })
+                .map(|resource| {
+                    resource
+                        .values
+                        .get("name")
+                        .and_then(|name| name.as_str())
+                        .map(|name| name.to_string())
+                        .unwrap_or_else(|| resource.address.clone())
+                })
+                .collect(),
+        }
+    }
+
+    /// A preview of a command that applies to node VMs, which are either the custom inventory, or
+    /// the VMs for the node type, or otherwise every node VM.
+    pub fn from_node_vms(
+        description: &str,
+        inventory: &DeploymentInventory,
+        node_type: Option<&NodeType>,
+        custom_inventory: Option<&Vec<VirtualMachine>>,
+    ) -> Self {
+        let targets = if let Some(custom_inventory) = custom_inventory {
+            custom_inventory.iter().map(|vm| vm.name.clone()).collect()
+        } else {
+            let node_vms = match node_type {
+                Some(NodeType::FullConePrivateNode) => inventory.full_cone_private_node_vms.clone(),
+                Some(NodeType::Generic) => inventory.node_vms.clone(),
+                Some(NodeType::Genesis) => inventory.genesis_vm.iter().cloned().collect(),
+                Some(NodeType::PeerCache) => inventory.peer_cache_node_vms.clone(),
+                Some(NodeType::PortRestrictedConePrivateNode) => {
+                    inventory.port_restricted_cone_private_node_vms.clone()
+                }
+                Some(NodeType::SymmetricPrivateNode) => {
+                    inventory.symmetric_private_node_vms.clone()
+                }
+                Some(NodeType::Upnp) => inventory.upnp_private_node_vms.clone(),
+                None => inventory.node_vm_list(),
+            };
+            node_vms
+                .into_iter()
+                .map(|node_vm| node_vm.vm.name)
+                .collect()
+        };
+        Self {
+            description: description.to_string(),
+            targets,
+        }
+    }
+
+    pub fn print(&self, name: &str, command: &str) {
+        println!("==== Preview of '{command}' on {name} ====");
+        println!("{}:", self.description);
+        for target in self.targets.iter() {
+            println!("  {target}");
+        }
+        println!("{} targets", self.targets.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object_store::{BucketNames, LocalObjectStore};
+
+    #[tokio::test]
+    async fn test_environment_without_details_gets_the_production_policy() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let object_store =
+            LocalObjectStore::new(temp_dir.path().to_path_buf(), BucketNames::default());
+
+        let policy = EnvironmentPolicy::get("alpha", &object_store)
+            .await
+            .unwrap();
+        assert!(matches!(
+            policy.environment_type,
+            EnvironmentType::Production
+        ));
+        assert!(policy.confirm_name);
+        assert!(policy.preview);
+        assert!(!policy.drain_funds_by_default);
+    }
+}
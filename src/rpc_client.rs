@@ -4,96 +4,196 @@
 // This SAFE Network Software is licensed under the BSD-3-Clause license.
 // Please see the LICENSE file for more details.
 
-use crate::{error::Result, run_external_command};
-use std::{net::SocketAddr, path::PathBuf};
+use crate::{
+    error::{Error, Result},
+    inventory::NodeVirtualMachine,
+};
+use ant_service_management::rpc::{self, RpcActions};
+use futures::StreamExt;
+use serde::Serialize;
+use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf, time::Duration};
 
+const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 50;
+
+/// Information obtained from a node through its RPC endpoint.
+#[derive(Clone, Debug, Serialize)]
 pub struct NodeInfo {
-    pub endpoint: String,
+    pub endpoint: SocketAddr,
+    pub vm_name: String,
     pub peer_id: String,
     pub logs_dir: PathBuf,
-    pub pid: u16,
+    pub pid: u32,
     pub safenode_version: String,
-    pub last_restart: u32,
+    pub uptime: Duration,
+    pub connected_peers: usize,
+}
+
+/// A node that could not be queried.
+#[derive(Clone, Debug, Serialize)]
+pub struct FailedNodeQuery {
+    pub endpoint: SocketAddr,
+    pub vm_name: String,
+    pub error: String,
+}
+
+/// The aggregated result of querying every node in an environment.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct NetworkHealth {
+    pub nodes: Vec<NodeInfo>,
+    pub failed: Vec<FailedNodeQuery>,
+}
+
+impl NetworkHealth {
+    /// The number of nodes that have each version, keyed by version.
+    pub fn version_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for node in self.nodes.iter() {
+            *counts.entry(node.safenode_version.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// The nodes which are not connected to any peers.
+    pub fn isolated_nodes(&self) -> Vec<&NodeInfo> {
+        self.nodes
+            .iter()
+            .filter(|node| node.connected_peers == 0)
+            .collect()
+    }
+
+    pub fn print_summary(&self) {
+        println!("==== Network Health Summary ====");
+        println!("Nodes queried: {}", self.nodes.len() + self.failed.len());
+        println!("Responding: {}", self.nodes.len());
+        println!("Not responding: {}", self.failed.len());
+
+        if !self.nodes.is_empty() {
+            let mut peer_counts = self
+                .nodes
+                .iter()
+                .map(|node| node.connected_peers)
+                .collect::<Vec<_>>();
+            peer_counts.sort_unstable();
+            let total_peers: usize = peer_counts.iter().sum();
+            println!(
+                "Connected peers: min {}, median {}, max {}, mean {:.1}",
+                peer_counts[0],
+                peer_counts[peer_counts.len() / 2],
+                peer_counts[peer_counts.len() - 1],
+                total_peers as f64 / peer_counts.len() as f64
+            );
+
+            let mut uptimes = self
+                .nodes
+                .iter()
+                .map(|node| node.uptime)
+                .collect::<Vec<_>>();
+            uptimes.sort_unstable();
+            println!(
+                "Uptime: min {:?}, median {:?}, max {:?}",
+                uptimes[0],
+                uptimes[uptimes.len() / 2],
+                uptimes[uptimes.len() - 1]
+            );
+
+            println!("Versions:");
+            for (version, count) in self.version_counts() {
+                println!("  {version}: {count}");
+            }
+
+            let isolated = self.isolated_nodes();
+            if !isolated.is_empty() {
+                println!("Nodes with no connected peers: {}", isolated.len());
+                for node in isolated {
+                    println!("  {} @ {} ({})", node.peer_id, node.endpoint, node.vm_name);
+                }
+            }
+        }
+
+        if !self.failed.is_empty() {
+            println!("Failed queries:");
+            for failed in self.failed.iter() {
+                println!(
+                    "  {} ({}): {}",
+                    failed.endpoint, failed.vm_name, failed.error
+                );
+            }
+        }
+    }
 }
 
+/// Queries nodes directly through their gRPC endpoints.
 #[derive(Clone)]
 pub struct RpcClient {
-    pub binary_path: PathBuf,
-    pub working_directory_path: PathBuf,
+    pub max_concurrent_requests: usize,
+}
+
+impl Default for RpcClient {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENT_REQUESTS)
+    }
 }
 
 impl RpcClient {
-    pub fn new(binary_path: PathBuf, working_directory_path: PathBuf) -> RpcClient {
+    pub fn new(max_concurrent_requests: usize) -> RpcClient {
         RpcClient {
-            binary_path,
-            working_directory_path,
+            max_concurrent_requests: std::cmp::max(max_concurrent_requests, 1),
         }
     }
 
-    pub fn get_info(&self, rpc_address: SocketAddr) -> Result<NodeInfo> {
-        let output = run_external_command(
-            self.binary_path.clone(),
-            self.working_directory_path.clone(),
-            vec![rpc_address.to_string(), "info".to_string()],
-            false,
-            false,
-        )?;
+    pub async fn get_info(&self, rpc_address: SocketAddr, vm_name: &str) -> Result<NodeInfo> {
+        let client = rpc::RpcClient::from_socket_addr(rpc_address);
+        let node_info = client
+            .node_info()
+            .await
+            .map_err(|err| Error::RpcRequestFailed(rpc_address, err.to_string()))?;
+        let network_info = client
+            .network_info()
+            .await
+            .map_err(|err| Error::RpcRequestFailed(rpc_address, err.to_string()))?;
 
-        parse_output(output)
+        Ok(NodeInfo {
+            endpoint: rpc_address,
+            vm_name: vm_name.to_string(),
+            peer_id: node_info.peer_id.to_string(),
+            logs_dir: node_info.log_path,
+            pid: node_info.pid,
+            safenode_version: node_info.version,
+            uptime: node_info.uptime,
+            connected_peers: network_info.connected_peers.len(),
+        })
     }
-}
 
-pub fn parse_output(output: Vec<String>) -> Result<NodeInfo> {
-    let endpoint = output
-        .iter()
-        .find(|line| line.starts_with("RPC endpoint:"))
-        .map(|line| line.split(": ").nth(1).unwrap_or("").to_string())
-        .unwrap_or_default();
-    let peer_id = output
-        .iter()
-        .find(|line| line.starts_with("Peer Id:"))
-        .map(|line| line.split(": ").nth(1).unwrap_or("").to_string())
-        .unwrap_or_default();
-    let logs_dir = output
-        .iter()
-        .find(|line| line.starts_with("Logs dir:"))
-        .map(|line| line.split(": ").nth(1).unwrap_or("").to_string())
-        .unwrap_or_default();
-    let pid = output
-        .iter()
-        .find(|line| line.starts_with("PID:"))
-        .map(|line| {
-            line.split(": ")
-                .nth(1)
-                .unwrap_or("")
-                .parse::<u16>()
-                .unwrap_or(0)
-        })
-        .unwrap_or_default();
-    let safenode_version = output
-        .iter()
-        .find(|line| line.starts_with("Binary version:"))
-        .map(|line| line.split(": ").nth(1).unwrap_or("").to_string())
-        .unwrap_or_default();
-    let last_restart = output
-        .iter()
-        .find(|line| line.starts_with("Time since last restart:"))
-        .map(|line| {
-            line.split(": ")
-                .nth(1)
-                .unwrap_or("")
-                .trim_end_matches('s')
-                .parse::<u32>()
-                .unwrap_or(0)
-        })
-        .unwrap_or_default();
-
-    Ok(NodeInfo {
-        endpoint,
-        peer_id,
-        logs_dir: PathBuf::from(logs_dir),
-        pid,
-        safenode_version,
-        last_restart,
-    })
+    /// Query the RPC endpoint of every node on the given VMs concurrently.
+    ///
+    /// Nodes that fail to respond are recorded in the result rather than causing an error.
+    pub async fn get_network_health(&self, node_vms: &[NodeVirtualMachine]) -> NetworkHealth {
+        let endpoints = node_vms.iter().flat_map(|node_vm| {
+            node_vm
+                .rpc_endpoint
+                .values()
+                .map(move |endpoint| (*endpoint, node_vm.vm.name.clone()))
+        });
+        let mut stream = futures::stream::iter(endpoints)
+            .map(|(endpoint, vm_name)| async move {
+                let result = self.get_info(endpoint, &vm_name).await;
+                (endpoint, vm_name, result)
+            })
+            .buffer_unordered(self.max_concurrent_requests);
+
+        let mut health = NetworkHealth::default();
+        while let Some((endpoint, vm_name, result)) = stream.next().await {
+            match result {
+                Ok(info) => health.nodes.push(info),
+                Err(err) => health.failed.push(FailedNodeQuery {
+                    endpoint,
+                    vm_name,
+                    error: err.to_string(),
+                }),
+            }
+        }
+        health.nodes.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+        health.failed.sort_by(|a, b| a.endpoint.cmp(&b.endpoint));
+        health
+    }
 }
@@ -0,0 +1,424 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+pub mod transfer;
+
+use crate::{
+    dry_run::{self, PlannedAction},
+    error::{Error, Result},
+    object_store::{Bucket, BucketNames, ObjectStore},
+};
+use async_recursion::async_recursion;
+use async_trait::async_trait;
+use aws_sdk_s3::{config::Region, error::ProvideErrorMetadata, Client};
+use chrono::{DateTime, Utc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use tokio::sync::OnceCell;
+
+/// An object store on S3, or a service that is compatible with it, such as MinIO.
+#[derive(Clone, Debug)]
+pub struct S3Repository {
+    pub bucket_names: BucketNames,
+    // Loading the config resolves the credentials, so the client is only built once, and it is
+    // shared by any clones of the repository.
+    client: Arc<OnceCell<Client>>,
+    /// The endpoint of a service other than AWS.
+    pub endpoint_url: Option<String>,
+    pub region: String,
+}
+
+impl S3Repository {
+    pub fn new(region: &str, endpoint_url: Option<String>, bucket_names: BucketNames) -> Self {
+        Self {
+            bucket_names,
+            client: Arc::new(OnceCell::new()),
+            endpoint_url,
+            region: region.to_string(),
+        }
+    }
+
+    /// Get the client, which is built on first use. Clones of the client share its connections.
+    async fn get_client(&self) -> Client {
+        self.client
+            .get_or_init(|| async {
+                let conf = aws_config::from_env()
+                    .region(Region::new(self.region.clone()))
+                    .load()
+                    .await;
+                let mut builder = aws_sdk_s3::config::Builder::from(&conf);
+                if let Some(endpoint_url) = &self.endpoint_url {
+                    // Services like MinIO don't generally support bucket names as subdomains.
+                    builder = builder.endpoint_url(endpoint_url).force_path_style(true);
+                }
+                Client::from_conf(builder.build())
+            })
+            .await
+            .clone()
+    }
+
+    #[async_recursion]
+    async fn list_and_retrieve(
+        &self,
+        client: &Client,
+        bucket_name: &str,
+        prefix: &str,
+        root_path: &PathBuf,
+    ) -> Result<(), Error> {
+        let output = client
+            .list_objects_v2()
+            .bucket(bucket_name)
+            .prefix(prefix)
+            .delimiter("/")
+            .send()
+            .await
+            .map_err(|err| Error::ListS3ObjectsError {
+                prefix: prefix.to_string(),
+                error: err.meta().message().unwrap_or_default().to_string(),
+            })?;
+
+        // So-called 'common prefixes' are subdirectories.
+        if let Some(common_prefixes) = output.common_prefixes {
+            for cp in common_prefixes {
+                let next_prefix = cp.prefix.unwrap();
+                self.list_and_retrieve(client, bucket_name, &next_prefix, root_path)
+                    .await?;
+            }
+        }
+
+        if let Some(objects) = output.contents {
+            for object in objects {
+                let object_key = object.key.unwrap();
+                let mut dest_file_path = root_path.clone();
+                dest_file_path.push(&object_key);
+                if dest_file_path.exists() {
+                    println!("Has already been retrieved in a previous sync.");
+                    continue;
+                }
+                self.retrieve_object(client, bucket_name, &object_key, &dest_file_path)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[async_recursion]
+    async fn list_and_delete(
+        &self,
+        client: &Client,
+        bucket_name: &str,
+        prefix: &str,
+    ) -> Result<(), Error> {
+        let output = client
+            .list_objects_v2()
+            .bucket(bucket_name)
+            .prefix(prefix)
+            .delimiter("/")
+            .send()
+            .await
+            .map_err(|err| Error::ListS3ObjectsError {
+                prefix: prefix.to_string(),
+                error: err.meta().message().unwrap_or_default().to_string(),
+            })?;
+
+        // So-called 'common prefixes' are subdirectories.
+        if let Some(common_prefixes) = output.common_prefixes {
+            for cp in common_prefixes {
+                let next_prefix = cp.prefix.unwrap();
+                self.list_and_delete(client, bucket_name, &next_prefix)
+                    .await?;
+            }
+        }
+
+        if let Some(objects) = output.contents {
+            for object in objects {
+                let object_key = object.key.unwrap();
+                self.do_delete_object(client, bucket_name, &object_key)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn retrieve_object(
+        &self,
+        client: &Client,
+        bucket_name: &str,
+        object_key: &str,
+        dest_path: &PathBuf,
+    ) -> Result<()> {
+        println!("Retrieving {object_key} from S3...");
+        transfer::download(client, bucket_name, object_key, dest_path).await?;
+        println!("Saved at {}", dest_path.to_string_lossy());
+        Ok(())
+    }
+
+    async fn do_delete_object(
+        &self,
+        client: &Client,
+        bucket_name: &str,
+        object_key: &str,
+    ) -> Result<()> {
+        println!("Deleting {object_key} from S3...");
+        client
+            .delete_object()
+            .bucket(bucket_name)
+            .key(object_key)
+            .send()
+            .await
+            .map_err(|_| {
+                Error::DeleteS3ObjectError(object_key.to_string(), bucket_name.to_string())
+            })?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ObjectStore for S3Repository {
+    fn get_object_url(&self, bucket: Bucket, object_key: &str) -> String {
+        format!("s3://{}/{object_key}", self.bucket_names.get(bucket))
+    }
+
+    async fn upload_object(
+        &self,
+        bucket: Bucket,
+        object_key: &str,
+        file_path: &Path,
+        public: bool,
+    ) -> Result<()> {
+        if dry_run::is_enabled() {
+            dry_run::record(PlannedAction::ObjectUpload {
+                source: file_path.to_string_lossy().to_string(),
+                url: self.get_object_url(bucket, object_key),
+            });
+            return Ok(());
+        }
+        let bucket_name = self.bucket_names.get(bucket);
+        let client = self.get_client().await;
+
+        println!("Uploading {object_key} to bucket {bucket_name}");
+        if tokio::fs::metadata(file_path).await?.len() < transfer::MULTIPART_THRESHOLD {
+            transfer::upload(&client, bucket_name, object_key, file_path, public).await?;
+        } else {
+            transfer::upload_multipart(&client, bucket_name, object_key, file_path, public).await?;
+        }
+        println!("{object_key} has been uploaded to {bucket_name}");
+        Ok(())
+    }
+
+    async fn put_object_contents(
+        &self,
+        bucket: Bucket,
+        object_key: &str,
+        contents: Vec<u8>,
+    ) -> Result<()> {
+        if dry_run::is_enabled() {
+            dry_run::record(PlannedAction::ObjectUpload {
+                source: format!("{} bytes from memory", contents.len()),
+                url: self.get_object_url(bucket, object_key),
+            });
+            return Ok(());
+        }
+        let bucket_name = self.bucket_names.get(bucket);
+        let client = self.get_client().await;
+        client
+            .put_object()
+            .bucket(bucket_name)
+            .key(object_key)
+            .body(contents.into())
+            .send()
+            .await
+            .map_err(|_| {
+                Error::PutS3ObjectError(object_key.to_string(), bucket_name.to_string())
+            })?;
+        Ok(())
+    }
+
+    async fn get_object_contents(
+        &self,
+        bucket: Bucket,
+        object_key: &str,
+    ) -> Result<Option<Vec<u8>>> {
+        let bucket_name = self.bucket_names.get(bucket);
+        let client = self.get_client().await;
+        let output = match client
+            .get_object()
+            .bucket(bucket_name)
+            .key(object_key)
+            .send()
+            .await
+        {
+            Ok(output) => output,
+            Err(err) => {
+                if err
+                    .as_service_error()
+                    .is_some_and(|err| err.is_no_such_key())
+                {
+                    return Ok(None);
+                }
+                return Err(Error::GetS3ObjectError(
+                    object_key.to_string(),
+                    bucket_name.to_string(),
+                ));
+            }
+        };
+        let contents = output
+            .body
+            .collect()
+            .await
+            .map_err(|_| Error::S3ByteStreamError)?;
+        Ok(Some(contents.into_bytes().to_vec()))
+    }
+
+    async fn download_object(
+        &self,
+        bucket: Bucket,
+        object_key: &str,
+        dest_path: &Path,
+    ) -> Result<()> {
+        let client = self.get_client().await;
+        self.retrieve_object(
+            &client,
+            self.bucket_names.get(bucket),
+            object_key,
+            &dest_path.to_path_buf(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn download_folder(
+        &self,
+        bucket: Bucket,
+        folder_path: &str,
+        dest_path: &Path,
+    ) -> Result<()> {
+        let client = self.get_client().await;
+        tokio::fs::create_dir_all(dest_path).await?;
+        self.list_and_retrieve(
+            &client,
+            self.bucket_names.get(bucket),
+            folder_path,
+            &dest_path.to_path_buf(),
+        )
+        .await?;
+        Ok(())
+    }
+
+    async fn delete_object(&self, bucket: Bucket, object_key: &str) -> Result<()> {
+        if dry_run::is_enabled() {
+            dry_run::record(PlannedAction::ObjectDelete {
+                url: self.get_object_url(bucket, object_key),
+            });
+            return Ok(());
+        }
+        let client = self.get_client().await;
+        self.do_delete_object(&client, self.bucket_names.get(bucket), object_key)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_folder(&self, bucket: Bucket, folder_path: &str) -> Result<()> {
+        if dry_run::is_enabled() {
+            dry_run::record(PlannedAction::ObjectDelete {
+                url: format!("{}/*", self.get_object_url(bucket, folder_path)),
+            });
+            return Ok(());
+        }
+        let client = self.get_client().await;
+        self.list_and_delete(&client, self.bucket_names.get(bucket), folder_path)
+            .await?;
+        Ok(())
+    }
+
+    async fn folder_exists(&self, bucket: Bucket, folder_path: &str) -> Result<bool> {
+        let client = self.get_client().await;
+        let prefix = if folder_path.ends_with('/') {
+            folder_path.to_string()
+        } else {
+            format!("{folder_path}/")
+        };
+        let output = client
+            .list_objects_v2()
+            .bucket(self.bucket_names.get(bucket))
+            .prefix(&prefix)
+            .delimiter("/")
+            .send()
+            .await
+            .map_err(|err| Error::ListS3ObjectsError {
+                prefix,
+                error: err.meta().message().unwrap_or_default().to_string(),
+            })?;
+        Ok(!output.contents().unwrap_or_default().is_empty())
+    }
+
+    async fn list_object_keys(&self, bucket: Bucket, prefix: &str) -> Result<Vec<String>> {
+        let client = self.get_client().await;
+
+        let mut keys = Vec::new();
+        let mut continuation_token = None;
+        loop {
+            let output = client
+                .list_objects_v2()
+                .bucket(self.bucket_names.get(bucket))
+                .prefix(prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(|err| Error::ListS3ObjectsError {
+                    prefix: prefix.to_string(),
+                    error: err.meta().message().unwrap_or_default().to_string(),
+                })?;
+            if let Some(objects) = output.contents {
+                keys.extend(objects.into_iter().filter_map(|object| object.key));
+            }
+            match output.next_continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => break,
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn get_last_modified_time(
+        &self,
+        bucket: Bucket,
+        prefix: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let client = self.get_client().await;
+
+        let mut last_modified = None;
+        let mut continuation_token = None;
+        loop {
+            let output = client
+                .list_objects_v2()
+                .bucket(self.bucket_names.get(bucket))
+                .prefix(prefix)
+                .set_continuation_token(continuation_token)
+                .send()
+                .await
+                .map_err(|err| Error::ListS3ObjectsError {
+                    prefix: prefix.to_string(),
+                    error: err.meta().message().unwrap_or_default().to_string(),
+                })?;
+            for object in output.contents.unwrap_or_default() {
+                let modified = object
+                    .last_modified
+                    .and_then(|modified| DateTime::from_timestamp(modified.secs(), 0));
+                last_modified = last_modified.max(modified);
+            }
+            match output.next_continuation_token {
+                Some(token) => continuation_token = Some(token),
+                None => break,
+            }
+        }
+        Ok(last_modified)
+    }
+}
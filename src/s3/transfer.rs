@@ -0,0 +1,597 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crate::{
+    error::{Error, Result},
+    inventory::get_data_directory,
+};
+use aws_sdk_s3::{
+    primitives::ByteStream,
+    types::{CompletedMultipartUpload, CompletedPart, ObjectCannedAcl},
+    Client,
+};
+use futures::{stream, StreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::Metadata,
+    io::SeekFrom,
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// Objects of at least this size are transferred in parts.
+pub const MULTIPART_THRESHOLD: u64 = 64 * 1024 * 1024;
+/// The metadata key for the SHA-256 checksum of an object, which is set when it's uploaded.
+pub const CHECKSUM_METADATA_KEY: &str = "sha256";
+
+const MIN_PART_SIZE: u64 = 16 * 1024 * 1024;
+/// S3 does not allow more parts than this in an upload.
+const MAX_PART_COUNT: u64 = 10_000;
+/// The number of parts transferred at the same time. Each part is held in memory while it's
+/// being transferred.
+const TRANSFER_CONCURRENCY: usize = 4;
+
+/// The progress of a multipart upload, which is saved after each part, so an interrupted upload
+/// can be resumed by running the same command again.
+#[derive(Debug, Deserialize, Serialize)]
+struct UploadState {
+    checksum: String,
+    /// The ETag of each uploaded part, by part number.
+    completed_parts: BTreeMap<i32, String>,
+    file_path: PathBuf,
+    file_size: u64,
+    modified_at: u64,
+    part_size: u64,
+    upload_id: String,
+}
+
+impl UploadState {
+    fn is_for_file(&self, file_path: &Path, file_size: u64, modified_at: u64) -> bool {
+        self.file_path == file_path
+            && self.file_size == file_size
+            && self.modified_at == modified_at
+    }
+}
+
+/// The progress of a ranged download, which is saved next to the partially downloaded file.
+#[derive(Debug, Deserialize, Serialize)]
+struct DownloadState {
+    /// The indexes of the parts that have been written.
+    completed_parts: BTreeSet<u64>,
+    e_tag: String,
+    part_size: u64,
+    size: u64,
+}
+
+/// Upload a file with a single request, which streams the file rather than reading it into
+/// memory.
+pub async fn upload(
+    client: &Client,
+    bucket_name: &str,
+    object_key: &str,
+    file_path: &Path,
+    public: bool,
+) -> Result<()> {
+    let checksum = get_file_checksum(file_path).await?;
+    let body = ByteStream::from_path(file_path)
+        .await
+        .map_err(|_| Error::PutS3ObjectError(object_key.to_string(), bucket_name.to_string()))?;
+    let mut req = client
+        .put_object()
+        .bucket(bucket_name)
+        .key(object_key)
+        .metadata(CHECKSUM_METADATA_KEY, checksum)
+        .body(body);
+    if public {
+        req = req.acl(ObjectCannedAcl::PublicRead);
+    }
+    req.send()
+        .await
+        .map_err(|_| Error::PutS3ObjectError(object_key.to_string(), bucket_name.to_string()))?;
+    Ok(())
+}
+
+/// Upload a large file in parts, several at a time.
+///
+/// If a previous upload of the same unchanged file to the same key was interrupted, it is resumed
+/// from the parts that were already uploaded.
+pub async fn upload_multipart(
+    client: &Client,
+    bucket_name: &str,
+    object_key: &str,
+    file_path: &Path,
+    public: bool,
+) -> Result<()> {
+    let metadata = tokio::fs::metadata(file_path).await?;
+    let file_size = metadata.len();
+    let modified_at = get_modified_at(&metadata)?;
+    let state_path = get_upload_state_path(bucket_name, object_key)?;
+
+    let previous_state = read_state::<UploadState>(&state_path)?;
+    let mut state = match previous_state {
+        Some(state)
+            if state.is_for_file(file_path, file_size, modified_at)
+                && is_upload_in_progress(client, bucket_name, object_key, &state.upload_id)
+                    .await =>
+        {
+            println!(
+                "Resuming the upload of {object_key}, with {} parts already uploaded",
+                state.completed_parts.len()
+            );
+            state
+        }
+        previous_state => {
+            if let Some(previous_state) = previous_state {
+                abort_upload(client, bucket_name, object_key, &previous_state.upload_id).await;
+            }
+            let checksum = get_file_checksum(file_path).await?;
+            let mut req = client
+                .create_multipart_upload()
+                .bucket(bucket_name)
+                .key(object_key)
+                .metadata(CHECKSUM_METADATA_KEY, checksum.clone());
+            if public {
+                req = req.acl(ObjectCannedAcl::PublicRead);
+            }
+            let output = req.send().await.map_err(|_| {
+                Error::PutS3ObjectError(object_key.to_string(), bucket_name.to_string())
+            })?;
+            let upload_id = output.upload_id().ok_or_else(|| {
+                Error::PutS3ObjectError(object_key.to_string(), bucket_name.to_string())
+            })?;
+            UploadState {
+                checksum,
+                completed_parts: BTreeMap::new(),
+                file_path: file_path.to_path_buf(),
+                file_size,
+                modified_at,
+                part_size: get_part_size(file_size),
+                upload_id: upload_id.to_string(),
+            }
+        }
+    };
+    write_state(&state_path, &state)?;
+
+    let part_size = state.part_size;
+    let upload_id = state.upload_id.clone();
+    let pending_parts = (0..get_part_count(file_size, part_size))
+        .filter(|index| !state.completed_parts.contains_key(&get_part_number(*index)))
+        .collect::<Vec<_>>();
+    let progress_bar = get_transfer_progress_bar(file_size, object_key)?;
+    progress_bar.inc(
+        state
+            .completed_parts
+            .keys()
+            .map(|part_number| {
+                let (start, end) = get_part_range(*part_number as u64 - 1, part_size, file_size);
+                end - start + 1
+            })
+            .sum(),
+    );
+
+    let mut uploads = stream::iter(pending_parts)
+        .map(|index| {
+            let upload_id = &upload_id;
+            async move {
+                let (start, end) = get_part_range(index, part_size, file_size);
+                let mut file = tokio::fs::File::open(file_path).await?;
+                file.seek(SeekFrom::Start(start)).await?;
+                let mut contents = vec![0; (end - start + 1) as usize];
+                file.read_exact(&mut contents).await?;
+
+                let part_number = get_part_number(index);
+                let output = client
+                    .upload_part()
+                    .bucket(bucket_name)
+                    .key(object_key)
+                    .upload_id(upload_id)
+                    .part_number(part_number)
+                    .body(ByteStream::from(contents))
+                    .send()
+                    .await
+                    .map_err(|_| {
+                        Error::PutS3ObjectError(object_key.to_string(), bucket_name.to_string())
+                    })?;
+                let e_tag = output.e_tag().unwrap_or_default().to_string();
+                Ok::<_, Error>((part_number, e_tag, end - start + 1))
+            }
+        })
+        .buffer_unordered(TRANSFER_CONCURRENCY);
+    while let Some(result) = uploads.next().await {
+        let (part_number, e_tag, length) = result.inspect_err(|_| {
+            progress_bar.abandon();
+            println!(
+                "The upload of {object_key} was interrupted. Run the command again to resume it."
+            );
+        })?;
+        state.completed_parts.insert(part_number, e_tag);
+        write_state(&state_path, &state)?;
+        progress_bar.inc(length);
+    }
+    progress_bar.finish_and_clear();
+
+    let parts = state
+        .completed_parts
+        .iter()
+        .map(|(part_number, e_tag)| {
+            CompletedPart::builder()
+                .part_number(*part_number)
+                .e_tag(e_tag)
+                .build()
+        })
+        .collect::<Vec<_>>();
+    client
+        .complete_multipart_upload()
+        .bucket(bucket_name)
+        .key(object_key)
+        .upload_id(&state.upload_id)
+        .multipart_upload(
+            CompletedMultipartUpload::builder()
+                .set_parts(Some(parts))
+                .build(),
+        )
+        .send()
+        .await
+        .map_err(|_| Error::PutS3ObjectError(object_key.to_string(), bucket_name.to_string()))?;
+    std::fs::remove_file(&state_path)?;
+    Ok(())
+}
+
+/// Download an object to a file, verifying its checksum if it was uploaded with one.
+///
+/// Large objects are downloaded in ranges, several at a time. The object is written to a partial
+/// file next to the destination, which is renamed once it's complete, so an interrupted download
+/// can be resumed by running the same command again, as long as the object has not changed.
+pub async fn download(
+    client: &Client,
+    bucket_name: &str,
+    object_key: &str,
+    dest_path: &Path,
+) -> Result<()> {
+    let output = client
+        .head_object()
+        .bucket(bucket_name)
+        .key(object_key)
+        .send()
+        .await
+        .map_err(|_| Error::GetS3ObjectError(object_key.to_string(), bucket_name.to_string()))?;
+    let size = output.content_length().max(0) as u64;
+    let e_tag = output.e_tag().unwrap_or_default().to_string();
+    let checksum = output
+        .metadata()
+        .and_then(|metadata| metadata.get(CHECKSUM_METADATA_KEY))
+        .cloned();
+
+    if let Some(parent) = dest_path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    let partial_path = get_partial_download_path(dest_path, "part");
+    let state_path = get_partial_download_path(dest_path, "part.json");
+    if size < MULTIPART_THRESHOLD {
+        download_whole(client, bucket_name, object_key, &partial_path).await?;
+    } else {
+        download_ranges(
+            client,
+            bucket_name,
+            object_key,
+            &partial_path,
+            &state_path,
+            size,
+            &e_tag,
+        )
+        .await?;
+    }
+
+    if let Some(expected) = checksum {
+        let actual = get_file_checksum(&partial_path).await?;
+        if actual != expected {
+            // The partial file is removed so the next attempt downloads it again.
+            tokio::fs::remove_file(&partial_path).await?;
+            if state_path.exists() {
+                tokio::fs::remove_file(&state_path).await?;
+            }
+            return Err(Error::ChecksumMismatch {
+                object_key: object_key.to_string(),
+                expected,
+                actual,
+            });
+        }
+    }
+    tokio::fs::rename(&partial_path, dest_path).await?;
+    if state_path.exists() {
+        tokio::fs::remove_file(&state_path).await?;
+    }
+    Ok(())
+}
+
+async fn download_whole(
+    client: &Client,
+    bucket_name: &str,
+    object_key: &str,
+    dest_path: &Path,
+) -> Result<()> {
+    let output = client
+        .get_object()
+        .bucket(bucket_name)
+        .key(object_key)
+        .send()
+        .await
+        .map_err(|_| Error::GetS3ObjectError(object_key.to_string(), bucket_name.to_string()))?;
+    let mut file = tokio::fs::File::create(dest_path).await?;
+    tokio::io::copy(&mut output.body.into_async_read(), &mut file)
+        .await
+        .map_err(|_| Error::S3ByteStreamError)?;
+    file.flush().await?;
+    Ok(())
+}
+
+async fn download_ranges(
+    client: &Client,
+    bucket_name: &str,
+    object_key: &str,
+    partial_path: &Path,
+    state_path: &Path,
+    size: u64,
+    e_tag: &str,
+) -> Result<()> {
+    let part_size = get_part_size(size);
+    let previous_state = read_state::<DownloadState>(state_path)?;
+    let mut state = match previous_state {
+        Some(state)
+            if state.e_tag == e_tag
+                && state.size == size
+                && state.part_size == part_size
+                && partial_path.exists() =>
+        {
+            println!(
+                "Resuming the download of {object_key}, with {} parts already downloaded",
+                state.completed_parts.len()
+            );
+            state
+        }
+        _ => {
+            let file = std::fs::File::create(partial_path)?;
+            file.set_len(size)?;
+            DownloadState {
+                completed_parts: BTreeSet::new(),
+                e_tag: e_tag.to_string(),
+                part_size,
+                size,
+            }
+        }
+    };
+    write_state(state_path, &state)?;
+
+    let pending_parts = (0..get_part_count(size, part_size))
+        .filter(|index| !state.completed_parts.contains(index))
+        .collect::<Vec<_>>();
+    let progress_bar = get_transfer_progress_bar(size, object_key)?;
+    progress_bar.inc(
+        state
+            .completed_parts
+            .iter()
+            .map(|index| {
+                let (start, end) = get_part_range(*index, part_size, size);
+                end - start + 1
+            })
+            .sum(),
+    );
+
+    let mut downloads = stream::iter(pending_parts)
+        .map(|index| async move {
+            let (start, end) = get_part_range(index, part_size, size);
+            // The ETag condition makes sure every range comes from the same version of the object.
+            let output = client
+                .get_object()
+                .bucket(bucket_name)
+                .key(object_key)
+                .range(format!("bytes={start}-{end}"))
+                .if_match(e_tag)
+                .send()
+                .await
+                .map_err(|_| {
+                    Error::GetS3ObjectError(object_key.to_string(), bucket_name.to_string())
+                })?;
+            let contents = output
+                .body
+                .collect()
+                .await
+                .map_err(|_| Error::S3ByteStreamError)?
+                .into_bytes();
+
+            let mut file = tokio::fs::OpenOptions::new()
+                .write(true)
+                .open(partial_path)
+                .await?;
+            file.seek(SeekFrom::Start(start)).await?;
+            file.write_all(&contents).await?;
+            file.sync_data().await?;
+            Ok::<_, Error>((index, contents.len() as u64))
+        })
+        .buffer_unordered(TRANSFER_CONCURRENCY);
+    while let Some(result) = downloads.next().await {
+        let (index, length) = result.inspect_err(|_| {
+            progress_bar.abandon();
+            println!(
+                "The download of {object_key} was interrupted. Run the command again to resume it."
+            );
+        })?;
+        state.completed_parts.insert(index);
+        write_state(state_path, &state)?;
+        progress_bar.inc(length);
+    }
+    progress_bar.finish_and_clear();
+    Ok(())
+}
+
+/// Returns `false` if the upload has been completed or aborted, or has expired.
+async fn is_upload_in_progress(
+    client: &Client,
+    bucket_name: &str,
+    object_key: &str,
+    upload_id: &str,
+) -> bool {
+    client
+        .list_parts()
+        .bucket(bucket_name)
+        .key(object_key)
+        .upload_id(upload_id)
+        .max_parts(1)
+        .send()
+        .await
+        .is_ok()
+}
+
+/// Abort an upload that can't be resumed, so its parts are not stored indefinitely.
+async fn abort_upload(client: &Client, bucket_name: &str, object_key: &str, upload_id: &str) {
+    if let Err(err) = client
+        .abort_multipart_upload()
+        .bucket(bucket_name)
+        .key(object_key)
+        .upload_id(upload_id)
+        .send()
+        .await
+    {
+        log::debug!("Failed to abort the previous upload of {object_key}: {err:?}");
+    }
+}
+
+pub async fn get_file_checksum(file_path: &Path) -> Result<String> {
+    let mut file = tokio::fs::File::open(file_path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn get_transfer_progress_bar(length: u64, object_key: &str) -> Result<ProgressBar> {
+    let progress_bar = ProgressBar::new(length);
+    progress_bar.set_style(
+        ProgressStyle::default_bar()
+            .template(
+                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}) {msg}",
+            )?
+            .progress_chars("#>-"),
+    );
+    progress_bar.set_message(object_key.to_string());
+    progress_bar.enable_steady_tick(Duration::from_millis(100));
+    Ok(progress_bar)
+}
+
+/// The part size grows for very large files, to stay within the limit on the number of parts.
+fn get_part_size(size: u64) -> u64 {
+    MIN_PART_SIZE.max(size.div_ceil(MAX_PART_COUNT))
+}
+
+fn get_part_count(size: u64, part_size: u64) -> u64 {
+    size.div_ceil(part_size)
+}
+
+/// The first and last byte of a part, inclusive, as used in a range request.
+fn get_part_range(index: u64, part_size: u64, size: u64) -> (u64, u64) {
+    let start = index * part_size;
+    let end = (start + part_size).min(size) - 1;
+    (start, end)
+}
+
+/// Part numbers start at 1.
+fn get_part_number(index: u64) -> i32 {
+    index as i32 + 1
+}
+
+fn get_modified_at(metadata: &Metadata) -> Result<u64> {
+    let modified_at = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Ok(modified_at.as_millis() as u64)
+}
+
+fn get_upload_state_path(bucket_name: &str, object_key: &str) -> Result<PathBuf> {
+    let transfers_dir_path = get_data_directory()
+        .map_err(|_| Error::CouldNotRetrieveDataDirectory)?
+        .join("transfers");
+    std::fs::create_dir_all(&transfers_dir_path)?;
+    let id = format!(
+        "{:x}",
+        Sha256::digest(format!("{bucket_name}/{object_key}").as_bytes())
+    );
+    Ok(transfers_dir_path.join(format!("upload-{id}.json")))
+}
+
+fn get_partial_download_path(dest_path: &Path, extension: &str) -> PathBuf {
+    let mut file_name = dest_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".{extension}"));
+    dest_path.with_file_name(file_name)
+}
+
+fn read_state<T: serde::de::DeserializeOwned>(path: &Path) -> Result<Option<T>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    // A state file that can't be read is treated as missing, so the transfer starts again.
+    Ok(serde_json::from_reader(std::fs::File::open(path)?).ok())
+}
+
+/// The state is written to a temporary file and renamed, so it's never left partially written.
+fn write_state<T: Serialize>(path: &Path, state: &T) -> Result<()> {
+    let temp_path = get_partial_download_path(path, "tmp");
+    serde_json::to_writer(std::fs::File::create(&temp_path)?, state)?;
+    std::fs::rename(temp_path, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    #[test]
+    fn test_get_part_range_covers_the_whole_object() {
+        let size = 100 * MIB + 1;
+        let part_size = get_part_size(size);
+        let part_count = get_part_count(size, part_size);
+        assert_eq!(part_size, 16 * MIB);
+        assert_eq!(part_count, 7);
+
+        let ranges = (0..part_count)
+            .map(|index| get_part_range(index, part_size, size))
+            .collect::<Vec<_>>();
+        assert_eq!(ranges[0], (0, 16 * MIB - 1));
+        assert_eq!(ranges[1], (16 * MIB, 32 * MIB - 1));
+        assert_eq!(ranges[6], (96 * MIB, 100 * MIB));
+        for window in ranges.windows(2) {
+            assert_eq!(window[0].1 + 1, window[1].0);
+        }
+    }
+
+    #[test]
+    fn test_get_part_size_stays_within_the_part_count_limit() {
+        let size = 500 * 1024 * MIB;
+        let part_size = get_part_size(size);
+        assert!(part_size > MIN_PART_SIZE);
+        assert!(get_part_count(size, part_size) <= MAX_PART_COUNT);
+    }
+
+    #[test]
+    fn test_get_partial_download_path_appends_an_extension() {
+        assert_eq!(
+            get_partial_download_path(Path::new("/tmp/logs/alpha-node-1.tar.gz"), "part"),
+            PathBuf::from("/tmp/logs/alpha-node-1.tar.gz.part")
+        );
+    }
+}
@@ -0,0 +1,278 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crate::error::{Error, Result};
+use std::{path::PathBuf, process::Command, str::FromStr, sync::Mutex};
+
+/// The keyring service used when a `keyring:` reference only names the account.
+pub const DEFAULT_KEYRING_SERVICE: &str = "testnet-deploy";
+const REDACTED: &str = "********";
+
+// Secrets are registered as they are resolved, or generated, so that anything that writes out
+// commands or extra vars can remove them, without needing to know which values are sensitive.
+static SECRETS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Where to obtain a secret, such as a wallet secret key, rather than supplying it directly as an
+/// argument, where it would be visible in the process table and the shell history.
+///
+/// The reference is parsed from one of these forms:
+/// * `env:NAME`: the value of an environment variable.
+/// * `file:PATH`: the contents of a file.
+/// * `vault:PATH`: the contents of a file encrypted with `ansible-vault`, which is decrypted with
+///   the password file at `ANSIBLE_VAULT_PASSWORD_PATH`.
+/// * `keyring:[SERVICE/]ACCOUNT`: an entry in the OS keyring, which uses `secret-tool` on Linux
+///   and `security` on macOS. The service defaults to `testnet-deploy`.
+///
+/// Anything else is treated as the secret itself, which is still supported for compatibility.
+#[derive(Clone, PartialEq, Eq)]
+pub enum SecretReference {
+    Env(String),
+    File(PathBuf),
+    Keyring { service: String, account: String },
+    Value(String),
+    Vault(PathBuf),
+}
+
+impl std::fmt::Debug for SecretReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretReference::Value(_) => write!(f, "Value({REDACTED})"),
+            _ => write!(f, "{self}"),
+        }
+    }
+}
+
+impl std::fmt::Display for SecretReference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SecretReference::Env(name) => write!(f, "env:{name}"),
+            SecretReference::File(path) => write!(f, "file:{}", path.to_string_lossy()),
+            SecretReference::Keyring { service, account } => {
+                write!(f, "keyring:{service}/{account}")
+            }
+            SecretReference::Value(_) => write!(f, "{REDACTED}"),
+            SecretReference::Vault(path) => write!(f, "vault:{}", path.to_string_lossy()),
+        }
+    }
+}
+
+impl FromStr for SecretReference {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let reference = if let Some(name) = s.strip_prefix("env:") {
+            SecretReference::Env(name.to_string())
+        } else if let Some(path) = s.strip_prefix("file:") {
+            SecretReference::File(PathBuf::from(path))
+        } else if let Some(entry) = s.strip_prefix("keyring:") {
+            let (service, account) = entry
+                .split_once('/')
+                .unwrap_or((DEFAULT_KEYRING_SERVICE, entry));
+            if service.is_empty() || account.is_empty() {
+                return Err(Error::InvalidSecretReference(s.to_string()));
+            }
+            SecretReference::Keyring {
+                service: service.to_string(),
+                account: account.to_string(),
+            }
+        } else if let Some(path) = s.strip_prefix("vault:") {
+            SecretReference::Vault(PathBuf::from(path))
+        } else {
+            return Ok(SecretReference::Value(s.to_string()));
+        };
+
+        let is_empty = match &reference {
+            SecretReference::Env(name) => name.is_empty(),
+            SecretReference::File(path) | SecretReference::Vault(path) => {
+                path.as_os_str().is_empty()
+            }
+            _ => false,
+        };
+        if is_empty {
+            return Err(Error::InvalidSecretReference(s.to_string()));
+        }
+        Ok(reference)
+    }
+}
+
+impl SecretReference {
+    /// Obtain the secret and register it for redaction.
+    pub fn resolve(&self) -> Result<String> {
+        let secret = match self {
+            SecretReference::Env(name) => std::env::var(name)
+                .map_err(|e| Error::SecretReferenceNotResolved(self.to_string(), e.to_string()))?,
+            SecretReference::File(path) => std::fs::read_to_string(path)
+                .map_err(|e| Error::SecretReferenceNotResolved(self.to_string(), e.to_string()))?,
+            SecretReference::Keyring { service, account } => {
+                let (binary, args) = if cfg!(target_os = "macos") {
+                    (
+                        "security",
+                        vec![
+                            "find-generic-password".to_string(),
+                            "-s".to_string(),
+                            service.clone(),
+                            "-a".to_string(),
+                            account.clone(),
+                            "-w".to_string(),
+                        ],
+                    )
+                } else {
+                    (
+                        "secret-tool",
+                        vec![
+                            "lookup".to_string(),
+                            "service".to_string(),
+                            service.clone(),
+                            "account".to_string(),
+                            account.clone(),
+                        ],
+                    )
+                };
+                self.run_command(binary, args)?
+            }
+            SecretReference::Value(value) => {
+                println!(
+                    "WARNING: a secret was supplied directly as an argument, so it is visible in \
+                     the process table and shell history. Use an env:, file:, vault: or keyring: \
+                     reference instead."
+                );
+                value.clone()
+            }
+            SecretReference::Vault(path) => {
+                let vault_password_path =
+                    std::env::var("ANSIBLE_VAULT_PASSWORD_PATH").map_err(|e| {
+                        Error::SecretReferenceNotResolved(self.to_string(), e.to_string())
+                    })?;
+                self.run_command(
+                    "ansible-vault",
+                    vec![
+                        "view".to_string(),
+                        "--vault-password-file".to_string(),
+                        vault_password_path,
+                        path.to_string_lossy().to_string(),
+                    ],
+                )?
+            }
+        };
+
+        let secret = secret.trim().to_string();
+        if secret.is_empty() {
+            return Err(Error::SecretReferenceNotResolved(
+                self.to_string(),
+                "the secret is empty".to_string(),
+            ));
+        }
+        register(&secret);
+        Ok(secret)
+    }
+
+    // The output is captured directly, rather than with `run_external_command`, because only
+    // stdout contains the secret, and it should not be echoed.
+    fn run_command(&self, binary: &str, args: Vec<String>) -> Result<String> {
+        let output = Command::new(binary)
+            .args(&args)
+            .output()
+            .map_err(|e| Error::SecretReferenceNotResolved(self.to_string(), e.to_string()))?;
+        if !output.status.success() {
+            return Err(Error::SecretReferenceNotResolved(
+                self.to_string(),
+                format!(
+                    "{binary} failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+}
+
+/// Register a secret, so that it will be removed from any text passed to `redact`.
+pub fn register(secret: &str) {
+    if secret.is_empty() {
+        return;
+    }
+    let mut secrets = SECRETS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if !secrets.iter().any(|s| s == secret) {
+        secrets.push(secret.to_string());
+    }
+}
+
+/// Replace any registered secrets in the text, for writing it to the output or the logs.
+pub fn redact(text: &str) -> String {
+    let secrets = SECRETS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    secrets.iter().fold(text.to_string(), |text, secret| {
+        text.replace(secret, REDACTED)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_reference_is_parsed_from_its_prefix() {
+        assert_eq!(
+            "env:FUNDING_KEY".parse::<SecretReference>().unwrap(),
+            SecretReference::Env("FUNDING_KEY".to_string())
+        );
+        assert_eq!(
+            "file:/tmp/key".parse::<SecretReference>().unwrap(),
+            SecretReference::File(PathBuf::from("/tmp/key"))
+        );
+        assert_eq!(
+            "vault:keys/funding.vault"
+                .parse::<SecretReference>()
+                .unwrap(),
+            SecretReference::Vault(PathBuf::from("keys/funding.vault"))
+        );
+        assert_eq!(
+            "keyring:funding".parse::<SecretReference>().unwrap(),
+            SecretReference::Keyring {
+                service: DEFAULT_KEYRING_SERVICE.to_string(),
+                account: "funding".to_string()
+            }
+        );
+        assert_eq!(
+            "keyring:autonomi/funding"
+                .parse::<SecretReference>()
+                .unwrap(),
+            SecretReference::Keyring {
+                service: "autonomi".to_string(),
+                account: "funding".to_string()
+            }
+        );
+        assert_eq!(
+            "0xabc".parse::<SecretReference>().unwrap(),
+            SecretReference::Value("0xabc".to_string())
+        );
+        assert!("env:".parse::<SecretReference>().is_err());
+        assert!("keyring:autonomi/".parse::<SecretReference>().is_err());
+    }
+
+    #[test]
+    fn test_secret_value_is_not_displayed() {
+        let reference = SecretReference::Value("0xabc".to_string());
+        assert!(!reference.to_string().contains("0xabc"));
+        assert!(!format!("{reference:?}").contains("0xabc"));
+    }
+
+    #[test]
+    fn test_resolved_secret_is_redacted() {
+        std::env::set_var("SECRETS_TEST_FUNDING_KEY", " 0xredactme\n");
+        let secret = SecretReference::Env("SECRETS_TEST_FUNDING_KEY".to_string())
+            .resolve()
+            .unwrap();
+        assert_eq!(secret, "0xredactme");
+        assert_eq!(
+            redact(r#"{"secret_key":"0xredactme","name":"alpha"}"#),
+            r#"{"secret_key":"********","name":"alpha"}"#
+        );
+    }
+}
@@ -6,9 +6,10 @@
 
 use crate::{
     ansible::provisioning::PrivateNodeProvisionInventory,
+    dry_run::{self, PlannedAction},
     error::{Error, Result},
+    executor::{get_system_command_executor, CommandExecutor},
     inventory::VirtualMachine,
-    run_external_command,
 };
 use log::debug;
 use std::{
@@ -86,6 +87,7 @@ impl RoutedVms {
 
 #[derive(Clone)]
 pub struct SshClient {
+    pub executor: Arc<dyn CommandExecutor>,
     pub private_key_path: PathBuf,
     /// The list of VMs that are routed through a gateway.
     pub routed_vms: Arc<RwLock<Option<RoutedVms>>>,
@@ -93,6 +95,7 @@ pub struct SshClient {
 impl SshClient {
     pub fn new(private_key_path: PathBuf) -> SshClient {
         SshClient {
+            executor: get_system_command_executor(),
             private_key_path,
             routed_vms: Arc::new(RwLock::new(None)),
         }
@@ -298,7 +301,7 @@ impl SshClient {
         let mut retries = 0;
         let max_retries = 10;
         while retries < max_retries {
-            let result = run_external_command(
+            let result = self.executor.run(
                 PathBuf::from("ssh"),
                 std::env::current_dir()?,
                 args.clone(),
@@ -382,7 +385,14 @@ impl SshClient {
         }
         args.extend(command_args);
 
-        let output = run_external_command(
+        if dry_run::is_enabled() {
+            dry_run::record(PlannedAction::Ssh {
+                host: format!("{user}@{ip_address}"),
+                command: command.to_string(),
+            });
+            return Ok(Vec::new());
+        }
+        let output = self.executor.run(
             PathBuf::from("ssh"),
             std::env::current_dir()?,
             args,
@@ -406,6 +416,13 @@ impl SshClient {
             })?
             .to_string_lossy()
             .to_string();
+        if dry_run::is_enabled() {
+            dry_run::record(PlannedAction::Ssh {
+                host: format!("{user}@{ip_address}"),
+                command: format!("bash /tmp/{file_name} (copied from {})", script.display()),
+            });
+            return Ok(Vec::new());
+        }
         let args = vec![
             "-i".to_string(),
             self.private_key_path.to_string_lossy().to_string(),
@@ -419,18 +436,19 @@ impl SshClient {
             script.to_string_lossy().to_string(),
             format!("{}@{}:/tmp/{}", user, ip_address, file_name),
         ];
-        run_external_command(
-            PathBuf::from("scp"),
-            std::env::current_dir()?,
-            args,
-            suppress_output,
-            false,
-        )
-        .map_err(|e| {
-            Error::SshCommandFailed(format!(
-                "Failed to copy script file to remote host {ip_address:?}: {e}"
-            ))
-        })?;
+        self.executor
+            .run(
+                PathBuf::from("scp"),
+                std::env::current_dir()?,
+                args,
+                suppress_output,
+                false,
+            )
+            .map_err(|e| {
+                Error::SshCommandFailed(format!(
+                    "Failed to copy script file to remote host {ip_address:?}: {e}"
+                ))
+            })?;
 
         let args = vec![
             "-i".to_string(),
@@ -446,16 +464,18 @@ impl SshClient {
             "bash".to_string(),
             format!("/tmp/{file_name}"),
         ];
-        let output = run_external_command(
-            PathBuf::from("ssh"),
-            std::env::current_dir()?,
-            args,
-            suppress_output,
-            false,
-        )
-        .map_err(|e| {
-            Error::SshCommandFailed(format!("Failed to execute command on remote host: {e}"))
-        })?;
+        let output = self
+            .executor
+            .run(
+                PathBuf::from("ssh"),
+                std::env::current_dir()?,
+                args,
+                suppress_output,
+                false,
+            )
+            .map_err(|e| {
+                Error::SshCommandFailed(format!("Failed to execute command on remote host: {e}"))
+            })?;
         Ok(output)
     }
 }
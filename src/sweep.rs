@@ -0,0 +1,358 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use crate::{
+    digital_ocean::{DigitalOceanClient, DEFAULT_WORKSPACE, ENVIRONMENT_TAG_PREFIX},
+    error::{Error, Result},
+    lock::get_held_environment_locks,
+    logs::line::parse_rfc3339,
+    object_store::{get_object_store, Bucket, ObjectStore},
+    terraform::{get_environment_terraform_runners, TerraformRunner},
+    CloudProvider,
+};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Duration,
+};
+
+/// Resources younger than this are not reported, because they can belong to a deployment that is
+/// still being created and does not yet have its environment details.
+const MINIMUM_ORPHAN_AGE: Duration = Duration::from_secs(60 * 60);
+/// The prefix for the state of each workspace in Terraform's S3 backend.
+const TERRAFORM_WORKSPACE_KEY_PREFIX: &str = "env:";
+
+/// A Terraform workspace that has no environment details.
+///
+/// A workspace whose state still has resources is reported, but it is not deleted by the sweep,
+/// because its resources need to be destroyed with the `clean` command.
+#[derive(Clone, Debug, Serialize)]
+pub struct OrphanedWorkspace {
+    pub name: String,
+    /// Either 'testnet' or 'clients'.
+    pub terraform_directory: String,
+    pub resource_count: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OrphanedDroplet {
+    pub id: usize,
+    pub name: String,
+    pub environment_name: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OrphanedVolume {
+    pub id: String,
+    pub name: String,
+    pub size_gigabytes: u64,
+}
+
+/// The resources that don't belong to a live environment.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SweepReport {
+    pub workspaces: Vec<OrphanedWorkspace>,
+    /// Environment details for environments that have no workspace.
+    pub environment_details: Vec<String>,
+    pub droplets: Vec<OrphanedDroplet>,
+    /// Detached volumes that were created for an environment which no longer exists.
+    pub volumes: Vec<OrphanedVolume>,
+}
+
+impl SweepReport {
+    pub fn is_empty(&self) -> bool {
+        self.workspaces.is_empty()
+            && self.environment_details.is_empty()
+            && self.droplets.is_empty()
+            && self.volumes.is_empty()
+    }
+
+    pub fn print_report(&self) {
+        println!("==== Orphaned Resources ====");
+        if self.is_empty() {
+            println!("No orphaned resources were found");
+            return;
+        }
+        if !self.workspaces.is_empty() {
+            println!("Workspaces without environment details:");
+            for workspace in self.workspaces.iter() {
+                if workspace.resource_count > 0 {
+                    println!(
+                        "  {} ({}): {} resources in state, use the clean command to remove it",
+                        workspace.name, workspace.terraform_directory, workspace.resource_count
+                    );
+                } else {
+                    println!(
+                        "  {} ({}): empty",
+                        workspace.name, workspace.terraform_directory
+                    );
+                }
+            }
+        }
+        if !self.environment_details.is_empty() {
+            println!("Environment details without a workspace:");
+            for name in self.environment_details.iter() {
+                println!("  {name}");
+            }
+        }
+        if !self.droplets.is_empty() {
+            println!("Droplets:");
+            for droplet in self.droplets.iter() {
+                println!(
+                    "  {} (ID {}, environment {})",
+                    droplet.name, droplet.id, droplet.environment_name
+                );
+            }
+        }
+        if !self.volumes.is_empty() {
+            println!("Detached volumes:");
+            for volume in self.volumes.iter() {
+                println!("  {} ({}GB)", volume.name, volume.size_gigabytes);
+            }
+        }
+    }
+}
+
+/// Finds and deletes the resources left behind by aborted deployments or incomplete cleanups.
+///
+/// An environment is live if it has a workspace in either the testnet or clients Terraform
+/// directory, and its workspace either has environment details or resources in its state.
+pub struct OrphanedResourceSweeper {
+    digital_ocean_client: DigitalOceanClient,
+    object_store: Arc<dyn ObjectStore>,
+    /// The runners for the testnet and clients Terraform directories.
+    terraform_runners: Vec<(String, TerraformRunner)>,
+}
+
+impl OrphanedResourceSweeper {
+    pub fn new(provider: CloudProvider) -> Result<Self> {
+        if !matches!(provider, CloudProvider::DigitalOcean) {
+            return Err(Error::CloudProviderNotSupported(provider.to_string()));
+        }
+        let digital_ocean_client = DigitalOceanClient::from_env()?;
+        std::env::set_var("DIGITALOCEAN_TOKEN", &digital_ocean_client.access_token);
+        let terraform_runners = get_environment_terraform_runners(provider)?;
+
+        Ok(Self {
+            digital_ocean_client,
+            object_store: get_object_store()?,
+            terraform_runners,
+        })
+    }
+
+    /// Find the resources that don't belong to a live environment.
+    ///
+    /// An environment whose lock is held is always treated as live, because a command is changing
+    /// it. Droplets, volumes and empty workspaces are also only reported once they are older than
+    /// an hour, so a deployment that is being created is not mistaken for an aborted one.
+    pub async fn find_orphaned_resources(&self) -> Result<SweepReport> {
+        let mut report = SweepReport::default();
+
+        eprintln!("Retrieving environment locks...");
+        let locked_environments = get_held_environment_locks()
+            .await?
+            .into_iter()
+            .map(|lock| lock.environment_name)
+            .collect::<BTreeSet<_>>();
+
+        eprintln!("Retrieving environment details...");
+        let environment_details = self
+            .object_store
+            .list_object_keys(Bucket::EnvironmentDetails, "")
+            .await?
+            .into_iter()
+            .collect::<BTreeSet<_>>();
+
+        // The environments that still have resources under Terraform's management.
+        let mut workspaces = BTreeSet::new();
+        let mut empty_orphaned_workspaces = BTreeSet::new();
+        for (directory, runner) in self.terraform_runners.iter() {
+            eprintln!("Retrieving {directory} workspaces...");
+            runner.init()?;
+            for workspace in runner.workspace_list()? {
+                if workspace == DEFAULT_WORKSPACE {
+                    continue;
+                }
+                if !environment_details.contains(&workspace)
+                    && !locked_environments.contains(&workspace)
+                {
+                    let resource_count = runner.show(&workspace)?.len();
+                    if resource_count == 0 {
+                        // The state of an empty workspace was written when it was created.
+                        let created_at = self
+                            .object_store
+                            .get_last_modified_time(
+                                Bucket::TerraformState,
+                                &format!("{TERRAFORM_WORKSPACE_KEY_PREFIX}/{workspace}/"),
+                            )
+                            .await?;
+                        if !is_old_enough(created_at) {
+                            workspaces.insert(workspace);
+                            continue;
+                        }
+                        empty_orphaned_workspaces.insert(workspace.clone());
+                    }
+                    report.workspaces.push(OrphanedWorkspace {
+                        name: workspace.clone(),
+                        terraform_directory: directory.clone(),
+                        resource_count,
+                    });
+                }
+                workspaces.insert(workspace);
+            }
+        }
+        report.environment_details = environment_details
+            .iter()
+            .filter(|name| !workspaces.contains(*name) && !locked_environments.contains(*name))
+            .cloned()
+            .collect();
+        let is_live = |environment_name: &str| {
+            locked_environments.contains(environment_name)
+                || (workspaces.contains(environment_name)
+                    && !empty_orphaned_workspaces.contains(environment_name))
+        };
+
+        eprintln!("Retrieving droplets...");
+        for droplet in self.digital_ocean_client.list_droplets(true).await? {
+            // Droplets without an environment tag were not created by this tool.
+            let Some(environment_name) = droplet
+                .tags
+                .iter()
+                .find_map(|tag| tag.strip_prefix(ENVIRONMENT_TAG_PREFIX))
+            else {
+                continue;
+            };
+            if !is_live(environment_name) && is_old_enough(parse_rfc3339(&droplet.created_at)) {
+                report.droplets.push(OrphanedDroplet {
+                    id: droplet.id,
+                    name: droplet.name.clone(),
+                    environment_name: environment_name.to_string(),
+                });
+            }
+        }
+
+        eprintln!("Retrieving volumes...");
+        // Volumes have no tags, so the environment is identified by the longest workspace or
+        // locked environment name that prefixes the volume name, e.g., 'alpha-2-node-1-volume-1'
+        // belongs to 'alpha-2' rather than 'alpha'.
+        let volume_name_regex = Regex::new(r"-volume-\d+$")?;
+        for volume in self.digital_ocean_client.list_volumes().await? {
+            if !volume.droplet_ids.is_empty()
+                || !volume_name_regex.is_match(&volume.name)
+                || !is_old_enough(parse_rfc3339(&volume.created_at))
+            {
+                continue;
+            }
+            let environment_name = workspaces
+                .iter()
+                .chain(locked_environments.iter())
+                .filter(|name| volume.name.starts_with(&format!("{name}-")))
+                .max_by_key(|name| name.len());
+            if environment_name.is_none_or(|name| !is_live(name.as_str())) {
+                report.volumes.push(OrphanedVolume {
+                    id: volume.id,
+                    name: volume.name,
+                    size_gigabytes: volume.size_gigabytes,
+                });
+            }
+        }
+
+        report.droplets.sort_by(|a, b| a.name.cmp(&b.name));
+        report.volumes.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(report)
+    }
+
+    /// Delete the resources in the report.
+    ///
+    /// A failure to delete one resource doesn't prevent the others from being deleted. Droplets
+    /// are deleted first, because their volumes can't be deleted until they are detached.
+    pub async fn sweep(&self, report: &SweepReport) -> Result<()> {
+        let mut failures = 0;
+
+        for droplet in report.droplets.iter() {
+            println!("Deleting droplet {}...", droplet.name);
+            if let Err(err) = self.digital_ocean_client.delete_droplet(droplet.id).await {
+                println!("Failed to delete droplet {}: {err}", droplet.name);
+                failures += 1;
+            }
+        }
+
+        for volume in report.volumes.iter() {
+            println!("Deleting volume {}...", volume.name);
+            if let Err(err) = self.digital_ocean_client.delete_volume(&volume.id).await {
+                println!("Failed to delete volume {}: {err}", volume.name);
+                failures += 1;
+            }
+        }
+
+        let runners = self
+            .terraform_runners
+            .iter()
+            .map(|(directory, runner)| (directory.as_str(), runner))
+            .collect::<BTreeMap<_, _>>();
+        for workspace in report.workspaces.iter() {
+            if workspace.resource_count > 0 {
+                println!(
+                    "Skipping workspace {}, which still has resources in its state",
+                    workspace.name
+                );
+                continue;
+            }
+            let Some(runner) = runners.get(workspace.terraform_directory.as_str()) else {
+                continue;
+            };
+            println!("Deleting workspace {}...", workspace.name);
+            // The selected workspace can't be deleted.
+            let result = runner
+                .workspace_select(DEFAULT_WORKSPACE)
+                .and_then(|_| runner.workspace_delete(&workspace.name));
+            if let Err(err) = result {
+                println!("Failed to delete workspace {}: {err}", workspace.name);
+                failures += 1;
+            }
+        }
+
+        for name in report.environment_details.iter() {
+            if let Err(err) = self
+                .object_store
+                .delete_object(Bucket::EnvironmentDetails, name)
+                .await
+            {
+                println!("Failed to delete environment details for {name}: {err}");
+                failures += 1;
+            }
+        }
+
+        if failures > 0 {
+            return Err(Error::OrphanedResourceDeletionFailed(failures));
+        }
+        Ok(())
+    }
+}
+
+// Resources with an unknown creation time are treated as new, so they are never deleted by mistake.
+fn is_old_enough(created_at: Option<DateTime<Utc>>) -> bool {
+    created_at.is_some_and(|created_at| {
+        (Utc::now() - created_at).to_std().unwrap_or_default() >= MINIMUM_ORPHAN_AGE
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_resources_older_than_the_minimum_age_are_old_enough() {
+        let now = Utc::now();
+        assert!(is_old_enough(Some(now - chrono::Duration::hours(2))));
+        assert!(!is_old_enough(Some(now - chrono::Duration::minutes(5))));
+        assert!(!is_old_enough(Some(now + chrono::Duration::minutes(5))));
+        assert!(!is_old_enough(None));
+    }
+}
@@ -5,15 +5,22 @@
 // Please see the LICENSE file for more details.
 
 use crate::{
+    dry_run::{self, PlannedAction},
     error::{Error, Result},
-    is_binary_on_path, run_external_command, CloudProvider,
+    executor::{get_system_command_executor, CommandExecutor},
+    is_binary_on_path, CloudProvider,
 };
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 #[derive(Clone)]
 pub struct TerraformRunner {
     pub binary_path: PathBuf,
+    pub executor: Arc<dyn CommandExecutor>,
     pub provider: CloudProvider,
     pub working_directory_path: PathBuf,
     pub state_bucket_name: String,
@@ -35,6 +42,7 @@ impl TerraformRunner {
         }
         let runner = TerraformRunner {
             binary_path,
+            executor: get_system_command_executor(),
             working_directory_path: working_directory,
             provider,
             state_bucket_name: state_bucket_name.to_string(),
@@ -57,7 +65,10 @@ impl TerraformRunner {
             args.push("-var".to_string());
             args.push(format!("{}={}", var.0, var.1));
         }
-        run_external_command(
+        if self.record_dry_run(&args) {
+            return Ok(());
+        }
+        self.executor.run(
             self.binary_path.clone(),
             self.working_directory_path.clone(),
             args,
@@ -84,7 +95,75 @@ impl TerraformRunner {
                 args.push(format!("{}={}", var.0, var.1));
             }
         }
-        run_external_command(
+        self.executor.run(
+            self.binary_path.clone(),
+            self.working_directory_path.clone(),
+            args,
+            false,
+            false,
+        )?;
+        Ok(())
+    }
+
+    /// Run a plan and save it to a file, so that it can be inspected with `show_plan` and then
+    /// applied exactly as it was planned.
+    pub fn plan_to_file(
+        &self,
+        vars: Vec<(String, String)>,
+        tfvars_filenames: Option<Vec<String>>,
+        plan_path: &Path,
+    ) -> Result<()> {
+        let mut args = vec![
+            "plan".to_string(),
+            format!("-out={}", plan_path.to_string_lossy()),
+        ];
+        if let Some(filenames) = tfvars_filenames {
+            for filename in filenames {
+                args.push(format!("-var-file={filename}"));
+            }
+        }
+        for var in vars.iter() {
+            args.push("-var".to_string());
+            args.push(format!("{}={}", var.0, var.1));
+        }
+        self.executor.run(
+            self.binary_path.clone(),
+            self.working_directory_path.clone(),
+            args,
+            false,
+            false,
+        )?;
+        Ok(())
+    }
+
+    pub fn show_plan(&self, plan_path: &Path) -> Result<TerraformPlan> {
+        let output = self.executor.run(
+            self.binary_path.clone(),
+            self.working_directory_path.clone(),
+            vec![
+                "show".to_string(),
+                "-json".to_string(),
+                plan_path.to_string_lossy().to_string(),
+            ],
+            true,
+            false,
+        )?;
+        if output.is_empty() {
+            return Err(Error::TerraformShowFailed);
+        }
+        let plan: TerraformPlan = serde_json::from_str(&output.join("\n"))?;
+        Ok(plan)
+    }
+
+    /// Apply a plan that was saved with `plan_to_file`.
+    ///
+    /// The variables were recorded in the plan, so they are not supplied again.
+    pub fn apply_plan(&self, plan_path: &Path) -> Result<()> {
+        let args = vec!["apply".to_string(), plan_path.to_string_lossy().to_string()];
+        if self.record_dry_run(&args) {
+            return Ok(());
+        }
+        self.executor.run(
             self.binary_path.clone(),
             self.working_directory_path.clone(),
             args,
@@ -111,7 +190,10 @@ impl TerraformRunner {
                 args.push(format!("{}={}", var.0, var.1));
             }
         }
-        run_external_command(
+        if self.record_dry_run(&args) {
+            return Ok(());
+        }
+        self.executor.run(
             self.binary_path.clone(),
             self.working_directory_path.clone(),
             args,
@@ -127,7 +209,7 @@ impl TerraformRunner {
             "-backend-config".to_string(),
             format!("bucket={}", self.state_bucket_name),
         ];
-        run_external_command(
+        self.executor.run(
             self.binary_path.clone(),
             self.working_directory_path.clone(),
             args,
@@ -140,7 +222,7 @@ impl TerraformRunner {
     pub fn show(&self, name: &str) -> Result<Vec<TerraformResource>> {
         self.workspace_select(name)?;
 
-        let output = run_external_command(
+        let output = self.executor.run(
             self.binary_path.clone(),
             self.working_directory_path.clone(),
             vec!["show".to_string(), "--json".to_string()],
@@ -151,18 +233,26 @@ impl TerraformRunner {
         let output = output.first().ok_or(Error::TerraformShowFailed)?;
         let show_output: Output = serde_json::from_str(output)?;
 
-        Ok(show_output.values.root_module.resources)
+        // There are no values when the state for the workspace is empty.
+        Ok(show_output
+            .values
+            .map(|values| values.root_module.resources)
+            .unwrap_or_default())
     }
 
     pub fn workspace_delete(&self, name: &str) -> Result<()> {
-        run_external_command(
+        let args = vec![
+            "workspace".to_string(),
+            "delete".to_string(),
+            name.to_string(),
+        ];
+        if self.record_dry_run(&args) {
+            return Ok(());
+        }
+        self.executor.run(
             self.binary_path.clone(),
             self.working_directory_path.clone(),
-            vec![
-                "workspace".to_string(),
-                "delete".to_string(),
-                name.to_string(),
-            ],
+            args,
             true,
             false,
         )?;
@@ -170,7 +260,7 @@ impl TerraformRunner {
     }
 
     pub fn workspace_list(&self) -> Result<Vec<String>> {
-        let output = run_external_command(
+        let output = self.executor.run(
             self.binary_path.clone(),
             self.working_directory_path.clone(),
             vec!["workspace".to_string(), "list".to_string()],
@@ -186,10 +276,14 @@ impl TerraformRunner {
     }
 
     pub fn workspace_new(&self, name: &str) -> Result<()> {
-        run_external_command(
+        let args = vec!["workspace".to_string(), "new".to_string(), name.to_string()];
+        if self.record_dry_run(&args) {
+            return Ok(());
+        }
+        self.executor.run(
             self.binary_path.clone(),
             self.working_directory_path.clone(),
-            vec!["workspace".to_string(), "new".to_string(), name.to_string()],
+            args,
             false,
             false,
         )?;
@@ -197,7 +291,7 @@ impl TerraformRunner {
     }
 
     pub fn workspace_select(&self, name: &str) -> Result<()> {
-        run_external_command(
+        self.executor.run(
             self.binary_path.clone(),
             self.working_directory_path.clone(),
             vec![
@@ -210,11 +304,46 @@ impl TerraformRunner {
         )?;
         Ok(())
     }
+
+    /// During a dry run, record a command that would change the infrastructure or its state,
+    /// rather than running it. Returns true if the command was recorded.
+    fn record_dry_run(&self, args: &[String]) -> bool {
+        if !dry_run::is_enabled() {
+            return false;
+        }
+        dry_run::record(PlannedAction::Terraform {
+            command: dry_run::format_command(&self.binary_path.to_string_lossy(), args),
+        });
+        true
+    }
+}
+
+/// Get a runner for each of the Terraform directories that environments are created from, i.e.,
+/// 'testnet' and 'clients', along with the name of the directory.
+///
+/// The paths are relative to the current directory, in the same way as the deployer.
+pub fn get_environment_terraform_runners(
+    provider: CloudProvider,
+) -> Result<Vec<(String, TerraformRunner)>> {
+    let state_bucket_name = std::env::var("TERRAFORM_STATE_BUCKET_NAME")?;
+    let terraform_path = std::env::current_dir()?.join("resources").join("terraform");
+    ["testnet", "clients"]
+        .into_iter()
+        .map(|directory| {
+            let runner = TerraformRunner::new(
+                PathBuf::from("terraform"),
+                terraform_path.join(directory).join(provider.to_string()),
+                provider,
+                &state_bucket_name,
+            )?;
+            Ok((directory.to_string(), runner))
+        })
+        .collect()
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 struct Output {
-    values: Values,
+    values: Option<Values>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -224,6 +353,7 @@ struct Values {
 
 #[derive(Serialize, Deserialize, Debug)]
 struct Module {
+    #[serde(default)]
     resources: Vec<TerraformResource>,
 }
 
@@ -238,3 +368,63 @@ pub struct TerraformResource {
     pub values: HashMap<String, serde_json::Value>,
     pub sensitive_values: HashMap<String, serde_json::Value>,
 }
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TerraformPlan {
+    #[serde(default)]
+    pub resource_changes: Vec<TerraformResourceChange>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TerraformResourceChange {
+    pub address: String,
+    #[serde(rename = "type")]
+    pub resource_type: String,
+    #[serde(rename = "name")]
+    pub resource_name: String,
+    pub module_address: Option<String>,
+    pub index: Option<serde_json::Value>,
+    pub change: TerraformChange,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TerraformChange {
+    /// One of `["no-op"]`, `["create"]`, `["read"]`, `["update"]`, `["delete"]`, or a replacement,
+    /// which is `["delete", "create"]` or `["create", "delete"]`.
+    pub actions: Vec<String>,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+}
+
+impl TerraformChange {
+    fn has_action(&self, action: &str) -> bool {
+        self.actions.iter().any(|a| a == action)
+    }
+
+    pub fn is_create(&self) -> bool {
+        self.has_action("create") && !self.has_action("delete")
+    }
+
+    pub fn is_delete(&self) -> bool {
+        self.has_action("delete") && !self.has_action("create")
+    }
+
+    pub fn is_replace(&self) -> bool {
+        self.has_action("delete") && self.has_action("create")
+    }
+
+    pub fn is_update(&self) -> bool {
+        self.has_action("update")
+    }
+
+    /// Get the value of an attribute before and after the change.
+    pub fn get_attribute_change(
+        &self,
+        attribute: &str,
+    ) -> (Option<&serde_json::Value>, Option<&serde_json::Value>) {
+        (
+            self.before.as_ref().and_then(|v| v.get(attribute)),
+            self.after.as_ref().and_then(|v| v.get(attribute)),
+        )
+    }
+}
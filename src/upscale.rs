@@ -20,6 +20,7 @@ use std::{collections::HashSet, time::Duration};
 
 #[derive(Clone)]
 pub struct UpscaleOptions {
+    pub allow_destructive_changes: bool,
     pub ansible_verbose: bool,
     pub ant_version: Option<String>,
     pub current_inventory: DeploymentInventory,
@@ -162,6 +163,7 @@ impl TestnetDeployer {
             Some(&options.current_inventory.environment_details),
         )
         .await?;
+        infra_run_options.allow_destructive_changes = options.allow_destructive_changes;
         infra_run_options.peer_cache_node_vm_count = Some(desired_peer_cache_node_vm_count);
         infra_run_options.node_vm_count = Some(desired_node_vm_count);
         infra_run_options.full_cone_private_node_vm_count =
@@ -170,6 +172,7 @@ impl TestnetDeployer {
             Some(desired_symmetric_private_node_vm_count);
         infra_run_options.client_vm_count = Some(desired_client_vm_count);
 
+        self.print_infra_cost_estimate(&infra_run_options).await;
         if options.plan {
             self.plan(&infra_run_options)?;
             return Ok(());
@@ -503,6 +506,7 @@ impl TestnetDeployer {
             Some(&options.current_inventory.environment_details),
         )
         .await?;
+        infra_run_options.allow_destructive_changes = options.allow_destructive_changes;
         infra_run_options.client_vm_count = Some(desired_client_vm_count);
 
         if options.plan {
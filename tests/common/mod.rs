@@ -0,0 +1,343 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+// Each test crate only uses some of the harness.
+#![allow(dead_code)]
+
+use sn_testnet_deploy::{
+    ansible::inventory::generate_environment_inventory,
+    error::{Error, Result},
+    executor::CommandExecutor,
+    object_store::{BucketNames, LocalObjectStore},
+    CloudProvider, TestnetDeployBuilder, TestnetDeployer,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+};
+use tempfile::TempDir;
+
+type Responder = Box<dyn Fn(&[String]) -> Result<Vec<String>> + Send + Sync>;
+
+struct Response {
+    binary: String,
+    pattern: String,
+    responder: Responder,
+}
+
+/// A command that was passed to the fake executor.
+#[derive(Clone, Debug)]
+pub struct RecordedCommand {
+    /// The file name of the binary, e.g., 'terraform'.
+    pub binary: String,
+    pub args: Vec<String>,
+    /// The contents of the extra vars file passed to `ansible-playbook`, which is removed once
+    /// the playbook has run.
+    pub extra_vars: Option<String>,
+    pub working_directory_path: PathBuf,
+}
+
+impl RecordedCommand {
+    pub fn command_line(&self) -> String {
+        format!("{} {}", self.binary, self.args.join(" "))
+    }
+
+    pub fn contains(&self, pattern: &str) -> bool {
+        self.command_line().contains(pattern)
+    }
+}
+
+/// Replays recorded tool output rather than running the tools, and records the commands.
+///
+/// A response applies to a command for its binary whose arguments contain its pattern. The first
+/// response that was added for a command is used, so specific responses should be added before
+/// general ones. A command without a response succeeds with no output.
+#[derive(Default)]
+pub struct FakeCommandExecutor {
+    commands: Mutex<Vec<RecordedCommand>>,
+    responses: Mutex<Vec<Response>>,
+}
+
+impl FakeCommandExecutor {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    pub fn respond_with<F>(&self, binary: &str, pattern: &str, responder: F)
+    where
+        F: Fn(&[String]) -> Result<Vec<String>> + Send + Sync + 'static,
+    {
+        self.responses.lock().unwrap().push(Response {
+            binary: binary.to_string(),
+            pattern: pattern.to_string(),
+            responder: Box::new(responder),
+        });
+    }
+
+    pub fn respond_with_fixture(&self, binary: &str, pattern: &str, fixture_name: &str) {
+        let lines = read_fixture_lines(fixture_name);
+        self.respond_with(binary, pattern, move |_| Ok(lines.clone()));
+    }
+
+    pub fn respond_with_lines(&self, binary: &str, pattern: &str, lines: &[&str]) {
+        let lines = lines
+            .iter()
+            .map(|line| line.to_string())
+            .collect::<Vec<_>>();
+        self.respond_with(binary, pattern, move |_| Ok(lines.clone()));
+    }
+
+    pub fn fail(&self, binary: &str, pattern: &str) {
+        let binary_name = binary.to_string();
+        self.respond_with(binary, pattern, move |_| {
+            Err(Error::ExternalCommandRunFailed {
+                binary: binary_name.clone(),
+                exit_status: failed_exit_status(),
+            })
+        });
+    }
+
+    /// All the commands that were run, in order.
+    pub fn commands(&self) -> Vec<RecordedCommand> {
+        self.commands.lock().unwrap().clone()
+    }
+
+    pub fn commands_for(&self, binary: &str) -> Vec<RecordedCommand> {
+        self.commands()
+            .into_iter()
+            .filter(|command| command.binary == binary)
+            .collect()
+    }
+}
+
+impl CommandExecutor for FakeCommandExecutor {
+    fn run(
+        &self,
+        binary_path: PathBuf,
+        working_directory_path: PathBuf,
+        args: Vec<String>,
+        _suppress_stdout: bool,
+        _suppress_stderr: bool,
+    ) -> Result<Vec<String>> {
+        let binary = binary_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let command = RecordedCommand {
+            binary: binary.clone(),
+            args: args.clone(),
+            extra_vars: read_extra_vars(&args),
+            working_directory_path,
+        };
+        let command_line = command.command_line();
+        self.commands.lock().unwrap().push(command);
+
+        let responses = self.responses.lock().unwrap();
+        match responses
+            .iter()
+            .find(|response| response.binary == binary && command_line.contains(&response.pattern))
+        {
+            Some(response) => (response.responder)(&args),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+fn failed_exit_status() -> std::process::ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    std::process::ExitStatus::from_raw(1 << 8)
+}
+
+pub fn get_fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+pub fn read_fixture_lines(name: &str) -> Vec<String> {
+    std::fs::read_to_string(get_fixture_path(name))
+        .unwrap_or_else(|err| panic!("Failed to read fixture {name}: {err}"))
+        .lines()
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Get the value of an argument that follows a flag, e.g., the path after '--inventory'.
+pub fn get_arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .map(|value| value.as_str())
+}
+
+/// Read the extra vars from the file passed to `ansible-playbook` as `--extra-vars @path`.
+pub fn read_extra_vars(args: &[String]) -> Option<String> {
+    get_arg_value(args, "--extra-vars")
+        .and_then(|value| value.strip_prefix('@'))
+        .and_then(|path| std::fs::read_to_string(path).ok())
+}
+
+/// Respond to the `antctl_inventory.yml` playbook for an inventory by copying the node registry
+/// fixture to the destination in the extra vars, for each of the named VMs, in the same layout
+/// as the files fetched by the playbook.
+pub fn respond_with_node_registries(
+    executor: &FakeCommandExecutor,
+    inventory_pattern: &str,
+    vm_names: &[&str],
+) {
+    let vm_names = vm_names
+        .iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<_>>();
+    executor.respond_with(
+        "ansible-playbook",
+        &format!("{inventory_pattern} "),
+        move |args| {
+            if !args.iter().any(|arg| arg == "antctl_inventory.yml") {
+                return Ok(Vec::new());
+            }
+            let extra_vars: serde_json::Value =
+                serde_json::from_str(&read_extra_vars(args).unwrap_or("{}".to_string()))?;
+            let dest = PathBuf::from(extra_vars["dest"].as_str().unwrap_or_default());
+            for vm_name in vm_names.iter() {
+                let registry_dir_path = dest.join(vm_name).join("var").join("antctl");
+                std::fs::create_dir_all(&registry_dir_path)?;
+                std::fs::copy(
+                    get_fixture_path("node_registry.json"),
+                    registry_dir_path.join("node_registry.json"),
+                )?;
+            }
+            Ok(Vec::new())
+        },
+    );
+}
+
+/// Respond to the `antctl status --json` command that is run over SSH with the status in a
+/// fixture.
+///
+/// On the VM, the status is piped through `jq` to select the version of the first node, so the
+/// same selection is applied to the fixture here.
+pub fn respond_with_antctl_status(executor: &FakeCommandExecutor, fixture_name: &str) {
+    let status: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(get_fixture_path(fixture_name))
+            .unwrap_or_else(|err| panic!("Failed to read fixture {fixture_name}: {err}")),
+    )
+    .unwrap_or_else(|err| panic!("Failed to parse fixture {fixture_name}: {err}"));
+    let version = status["nodes"]
+        .as_array()
+        .and_then(|nodes| nodes.first())
+        .map(|node| node["version"].as_str().unwrap_or("null").to_string());
+    executor.respond_with("ssh", "antctl status --json", move |_| {
+        Ok(version.clone().into_iter().collect())
+    });
+}
+
+/// The data directory is shared by all the tests in a crate, so it is only set once.
+fn set_data_directory() {
+    static DATA_DIR: OnceLock<TempDir> = OnceLock::new();
+    DATA_DIR.get_or_init(|| {
+        let data_dir = TempDir::new().unwrap();
+        std::env::set_var("XDG_DATA_HOME", data_dir.path());
+
+        // A fresh pricing cache prevents the cost estimate from calling the Digital Ocean API.
+        let testnet_deploy_dir_path = data_dir.path().join("autonomi").join("testnet-deploy");
+        std::fs::create_dir_all(&testnet_deploy_dir_path).unwrap();
+        std::fs::write(
+            testnet_deploy_dir_path.join("digital_ocean_pricing.json"),
+            serde_json::json!({
+                "fetched_at": chrono::Utc::now().to_rfc3339(),
+                "sizes": {},
+            })
+            .to_string(),
+        )
+        .unwrap();
+        data_dir
+    });
+}
+
+/// A deployer for an environment whose commands are run by a fake executor.
+///
+/// The resources directory is a temporary copy, with the Ansible inventory files generated for
+/// the environment, so the tests don't write to the repository. The objects are kept in a local
+/// store in the same temporary directory.
+pub struct TestEnvironment {
+    pub deployer: TestnetDeployer,
+    pub executor: Arc<FakeCommandExecutor>,
+    pub name: String,
+    pub temp_dir: TempDir,
+}
+
+impl TestEnvironment {
+    pub fn new(name: &str) -> Self {
+        set_data_directory();
+        std::env::set_var("DO_PAT", "fake-token");
+
+        let temp_dir = TempDir::new().unwrap();
+        let working_directory_path = temp_dir.path().join("resources");
+        let inventory_dir_path = working_directory_path.join("ansible").join("inventory");
+        std::fs::create_dir_all(&inventory_dir_path).unwrap();
+        std::fs::create_dir_all(
+            working_directory_path
+                .join("terraform")
+                .join("testnet")
+                .join("digital-ocean"),
+        )
+        .unwrap();
+        let base_inventory_path = inventory_dir_path.join("dev_inventory_digital_ocean.yml");
+        std::fs::copy(
+            Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("resources")
+                .join("ansible")
+                .join("inventory")
+                .join("dev_inventory_digital_ocean.yml"),
+            &base_inventory_path,
+        )
+        .unwrap();
+        generate_environment_inventory(name, &base_inventory_path, &inventory_dir_path).unwrap();
+
+        // The runners check that these files exist, but they are never read.
+        let terraform_binary_path = temp_dir.path().join("terraform");
+        let ssh_secret_key_path = temp_dir.path().join("id_rsa");
+        let vault_password_path = temp_dir.path().join("vault-password");
+        for path in [
+            &terraform_binary_path,
+            &ssh_secret_key_path,
+            &vault_password_path,
+        ] {
+            std::fs::write(path, "").unwrap();
+        }
+
+        let executor = FakeCommandExecutor::new();
+        let object_store =
+            LocalObjectStore::new(temp_dir.path().join("object-store"), BucketNames::default());
+        let deployer = TestnetDeployBuilder::default()
+            .command_executor(executor.clone())
+            .environment_name(name)
+            .object_store(Arc::new(object_store))
+            .provider(CloudProvider::DigitalOcean)
+            .ssh_secret_key_path(ssh_secret_key_path)
+            .state_bucket_name("fake-state-bucket".to_string())
+            .terraform_binary_path(terraform_binary_path)
+            .vault_password_path(vault_password_path)
+            .working_directory(working_directory_path)
+            .build()
+            .unwrap();
+
+        Self {
+            deployer,
+            executor,
+            name: name.to_string(),
+            temp_dir,
+        }
+    }
+
+    /// The path of an inventory file for the environment, which identifies the inventory that a
+    /// command is run against, e.g., '.alpha_node_inventory_digital_ocean.yml'.
+    pub fn inventory_file_name(&self, tag: &str) -> String {
+        format!(".{}_{tag}_inventory_digital_ocean.yml", self.name)
+    }
+}
@@ -0,0 +1,102 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+mod common;
+
+use alloy::primitives::Address;
+use color_eyre::Result;
+use common::TestEnvironment;
+use evmlib::common::U256;
+use sn_testnet_deploy::{
+    funding::ledger::{LedgerEntry, TransferKind},
+    object_store::Bucket,
+};
+
+fn get_entry(timestamp: &str, kind: TransferKind, token_amount: u64) -> LedgerEntry {
+    let mut entry = LedgerEntry::from_transfer::<String, String>(
+        "alpha",
+        kind,
+        "alpha-client-1",
+        Address::ZERO,
+        Address::ZERO,
+        U256::from(token_amount),
+        U256::ZERO,
+        &Ok(format!("0x{token_amount:064x}")),
+    );
+    entry.timestamp = timestamp.to_string();
+    entry
+}
+
+#[tokio::test]
+async fn test_funding_ledger_merges_the_parts_from_every_run() -> Result<()> {
+    let env = TestEnvironment::new("ledger-alpha");
+
+    // An entry recorded by a run on another machine, which is only in the object store.
+    let stored_entry = get_entry("2024-01-01T10:00:00+00:00", TransferKind::Deposit, 100);
+    env.deployer
+        .object_store
+        .put_object_contents(
+            Bucket::Testnet,
+            "funding-ledgers/ledger-alpha/20240101T100000Z-0000abcd.jsonl",
+            format!("{}\n", serde_json::to_string(&stored_entry)?).into_bytes(),
+        )
+        .await?;
+
+    let ledger = env.deployer.ansible_provisioner.get_funding_ledger();
+    let deposit_entry = get_entry("2024-01-02T10:00:00+00:00", TransferKind::Deposit, 200);
+    let drain_entry = get_entry("2024-01-03T10:00:00+00:00", TransferKind::Drain, 150);
+    ledger.record(&drain_entry)?;
+    ledger.record(&deposit_entry)?;
+    ledger.sync().await?;
+
+    // Another run for the same environment, which syncs its own part of the ledger.
+    let other_ledger = env.deployer.ansible_provisioner.get_funding_ledger();
+    let other_entry = get_entry("2024-01-04T10:00:00+00:00", TransferKind::Drain, 50);
+    other_ledger.record(&other_entry)?;
+    other_ledger.sync().await?;
+
+    let expected_entries = vec![stored_entry, deposit_entry, drain_entry, other_entry];
+    assert_eq!(ledger.get_entries().await?, expected_entries);
+    // Syncing again should not duplicate any of the entries.
+    ledger.sync().await?;
+    assert_eq!(other_ledger.get_entries().await?, expected_entries);
+
+    let mut line_counts = Vec::new();
+    for key in env
+        .deployer
+        .object_store
+        .list_object_keys(Bucket::Testnet, "funding-ledgers/ledger-alpha/")
+        .await?
+    {
+        let contents = env
+            .deployer
+            .object_store
+            .get_object_contents(Bucket::Testnet, &key)
+            .await?
+            .unwrap_or_default();
+        line_counts.push(String::from_utf8(contents)?.lines().count());
+    }
+    line_counts.sort();
+    assert_eq!(line_counts, vec![1, 1, 2]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_funding_ledger_includes_local_entries_that_were_not_uploaded() -> Result<()> {
+    let env = TestEnvironment::new("ledger-beta");
+    let ledger = env.deployer.ansible_provisioner.get_funding_ledger();
+    let entry = get_entry("2024-01-02T10:00:00+00:00", TransferKind::Deposit, 200);
+    ledger.record(&entry)?;
+
+    assert!(env
+        .deployer
+        .object_store
+        .list_object_keys(Bucket::Testnet, "funding-ledgers/ledger-beta/")
+        .await?
+        .is_empty());
+    assert_eq!(ledger.get_entries().await?, vec![entry]);
+    Ok(())
+}
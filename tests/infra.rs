@@ -0,0 +1,271 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+mod common;
+
+use color_eyre::{eyre::eyre, Result};
+use common::{get_arg_value, TestEnvironment};
+use semver::Version;
+use sn_testnet_deploy::{
+    infra::InfraRunOptions, inventory::DeploymentInventory, upscale::UpscaleOptions, BinaryOption,
+    EnvironmentDetails,
+};
+use std::{path::Path, time::Duration};
+
+const GENESIS_MULTIADDR: &str =
+    "/ip4/178.62.10.4/udp/43711/quic-v1/p2p/12D3KooWLvmkUDQRthtZv9CrzozRLk9ZVEHXgmx6UxVMiho5aded";
+
+fn get_environment_details() -> EnvironmentDetails {
+    EnvironmentDetails {
+        region: "lon1".to_string(),
+        ..Default::default()
+    }
+}
+
+fn get_upscale_options(name: &str) -> UpscaleOptions {
+    let mut current_inventory = DeploymentInventory::empty(
+        name,
+        BinaryOption::Versioned {
+            ant_version: None,
+            antctl_version: None,
+            antnode_version: None,
+        },
+    );
+    current_inventory.environment_details = get_environment_details();
+    UpscaleOptions {
+        allow_destructive_changes: false,
+        ansible_verbose: false,
+        ant_version: None,
+        current_inventory,
+        desired_client_vm_count: None,
+        desired_full_cone_private_node_count: None,
+        desired_full_cone_private_node_vm_count: None,
+        desired_node_count: None,
+        desired_node_vm_count: Some(4),
+        desired_peer_cache_node_count: None,
+        desired_peer_cache_node_vm_count: None,
+        desired_symmetric_private_node_count: None,
+        desired_symmetric_private_node_vm_count: None,
+        desired_uploaders_count: None,
+        funding_wallet_secret_key: None,
+        gas_amount: None,
+        interval: Duration::from_millis(200),
+        infra_only: true,
+        max_archived_log_files: 1,
+        max_log_files: 1,
+        network_dashboard_branch: None,
+        node_env_variables: None,
+        plan: false,
+        public_rpc: false,
+        provision_only: false,
+        start_delayed_verifier: false,
+        start_random_verifier: false,
+        start_performance_verifier: false,
+        token_amount: None,
+    }
+}
+
+#[tokio::test]
+async fn test_generate_existing_reads_vm_counts_and_sizes_from_terraform_state() -> Result<()> {
+    let env = TestEnvironment::new("alpha");
+    env.executor
+        .respond_with_fixture("terraform", "show --json", "terraform_show_state.json");
+
+    let options = InfraRunOptions::generate_existing(
+        "alpha",
+        "lon1",
+        &env.deployer.terraform_runner,
+        Some(&get_environment_details()),
+    )
+    .await?;
+
+    assert!(options.enable_build_vm);
+    assert_eq!(options.genesis_vm_count, Some(1));
+    assert_eq!(options.genesis_node_volume_size, Some(40));
+    assert_eq!(options.node_vm_count, Some(2));
+    assert_eq!(options.node_vm_size.as_deref(), Some("s-4vcpu-8gb"));
+    assert_eq!(options.node_image_id.as_deref(), Some("180654321"));
+    assert_eq!(options.node_volume_size, Some(90));
+    assert_eq!(options.peer_cache_node_vm_count, Some(1));
+    assert_eq!(
+        options.peer_cache_node_vm_size.as_deref(),
+        Some("s-2vcpu-4gb")
+    );
+    assert_eq!(options.peer_cache_node_volume_size, Some(80));
+    assert_eq!(options.symmetric_private_node_vm_count, Some(0));
+    assert_eq!(options.full_cone_private_node_vm_count, Some(0));
+    assert_eq!(
+        options.tfvars_filenames,
+        Some(vec![
+            "dev.tfvars".to_string(),
+            "dev-images-lon1.tfvars".to_string()
+        ])
+    );
+
+    let commands = env.executor.commands_for("terraform");
+    assert_eq!(commands.len(), 2);
+    assert_eq!(commands[0].args, vec!["workspace", "select", "alpha"]);
+    assert_eq!(commands[1].args, vec!["show", "--json"]);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upscale_infra_only_applies_plan_with_desired_node_vm_count() -> Result<()> {
+    let env = TestEnvironment::new("alpha");
+    env.executor
+        .respond_with_fixture("terraform", "show --json", "terraform_show_state.json");
+    env.executor
+        .respond_with_fixture("terraform", "show -json", "terraform_show_plan.json");
+
+    env.deployer.upscale(&get_upscale_options("alpha")).await?;
+
+    let commands = env.executor.commands_for("terraform");
+    let plan = commands
+        .iter()
+        .find(|command| command.args.first().is_some_and(|arg| arg == "plan"))
+        .expect("terraform plan should have run");
+    assert!(plan.contains("-var-file=dev.tfvars"));
+    assert!(plan.contains("-var-file=dev-images-lon1.tfvars"));
+    assert!(plan.contains("-var node_vm_count=4"));
+    assert!(plan.contains("-var node_droplet_size=s-4vcpu-8gb"));
+    assert!(plan.contains("-var node_volume_size=90"));
+    assert!(plan.contains("-var peer_cache_node_vm_count=1"));
+    assert!(plan.contains("-var genesis_vm_count=1"));
+
+    // The plan only creates resources, so it is applied without asking for confirmation.
+    let apply = commands.last().expect("terraform should have run");
+    assert_eq!(apply.args.len(), 2);
+    assert_eq!(apply.args[0], "apply");
+    assert!(apply.args[1].ends_with("infra.tfplan"));
+
+    // The infrastructure is only changed, so Ansible doesn't run.
+    assert!(env.executor.commands_for("ansible-playbook").is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upscale_applies_destructive_plan_when_destructive_changes_are_allowed() -> Result<()>
+{
+    let env = TestEnvironment::new("alpha");
+    env.executor
+        .respond_with_fixture("terraform", "show --json", "terraform_show_state.json");
+    env.executor.respond_with_fixture(
+        "terraform",
+        "show -json",
+        "terraform_show_plan_replace.json",
+    );
+
+    let mut options = get_upscale_options("alpha");
+    options.allow_destructive_changes = true;
+    env.deployer.upscale(&options).await?;
+
+    // The plan replaces a node VM, but it is applied without asking for confirmation.
+    let commands = env.executor.commands_for("terraform");
+    let apply = commands.last().expect("terraform should have run");
+    assert_eq!(apply.args[0], "apply");
+    assert!(apply.args[1].ends_with("infra.tfplan"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upscale_provisions_peer_cache_and_generic_nodes_after_applying_plan() -> Result<()> {
+    let env = TestEnvironment::new("alpha");
+    env.executor
+        .respond_with_fixture("terraform", "show --json", "terraform_show_state.json");
+    env.executor
+        .respond_with_fixture("terraform", "show -json", "terraform_show_plan.json");
+    for (tag, fixture_name) in [
+        ("genesis", "ansible_inventory_genesis.json"),
+        ("node", "ansible_inventory_nodes.json"),
+        ("peer_cache_node", "ansible_inventory_peer_cache_nodes.json"),
+    ] {
+        env.executor.respond_with_fixture(
+            "ansible-inventory",
+            &env.inventory_file_name(tag),
+            fixture_name,
+        );
+    }
+    env.executor.respond_with_lines(
+        "ssh",
+        "initial_peers_config.first == true",
+        &[GENESIS_MULTIADDR],
+    );
+
+    let mut options = get_upscale_options("alpha");
+    options.current_inventory.binary_option = BinaryOption::Versioned {
+        ant_version: None,
+        antctl_version: Some(Version::new(0, 12, 0)),
+        antnode_version: Some(Version::new(0, 3, 5)),
+    };
+    options
+        .current_inventory
+        .environment_details
+        .rewards_address = Some("0x03B770D9cD32077cC0bF330c13C114a87643B124".to_string());
+    options.desired_node_count = Some(10);
+    options.desired_peer_cache_node_count = Some(5);
+    options.infra_only = false;
+    env.deployer.upscale(&options).await?;
+
+    // The nodes are only provisioned once the new VMs have been created.
+    let commands = env.executor.commands();
+    let apply_index = commands
+        .iter()
+        .position(|command| {
+            command.binary == "terraform" && command.args.first().is_some_and(|arg| arg == "apply")
+        })
+        .expect("terraform apply should have run");
+    let first_playbook_index = commands
+        .iter()
+        .position(|command| command.binary == "ansible-playbook")
+        .expect("ansible-playbook should have run");
+    assert!(apply_index < first_playbook_index);
+
+    // Each of the VMs in the inventories is new, so SSH must be available on all of them.
+    let ssh_commands = env.executor.commands_for("ssh");
+    for ip_address in ["178.62.10.1", "178.62.10.2", "178.62.10.3"] {
+        assert!(ssh_commands
+            .iter()
+            .any(|command| command.contains(&format!("root@{ip_address} bash --version"))));
+    }
+
+    let playbooks = env.executor.commands_for("ansible-playbook");
+    assert_eq!(playbooks.len(), 2);
+    for (command, playbook, tag, node_count) in [
+        (&playbooks[0], "peer_cache_node.yml", "peer_cache_node", "5"),
+        (&playbooks[1], "nodes.yml", "node", "10"),
+    ] {
+        assert_eq!(command.args.last().map(|arg| arg.as_str()), Some(playbook));
+        assert_eq!(
+            get_arg_value(&command.args, "--inventory")
+                .and_then(|path| Path::new(path).file_name())
+                .map(|name| name.to_string_lossy().to_string()),
+            Some(env.inventory_file_name(tag))
+        );
+        let extra_vars: serde_json::Value = serde_json::from_str(
+            command
+                .extra_vars
+                .as_deref()
+                .ok_or_else(|| eyre!("The playbook was run without extra vars"))?,
+        )?;
+        assert_eq!(extra_vars["testnet_name"], "alpha");
+        assert_eq!(extra_vars["genesis_multiaddr"], GENESIS_MULTIADDR);
+        assert_eq!(
+            extra_vars["network_contacts_url"],
+            "http://178.62.10.4/bootstrap_cache.json"
+        );
+        assert_eq!(extra_vars["node_instance_count"], node_count);
+        assert_eq!(extra_vars["version"], "0.3.5");
+        assert_eq!(
+            extra_vars["rewards_address"],
+            "0x03B770D9cD32077cC0bF330c13C114a87643B124"
+        );
+    }
+    // Only the Peer Cache nodes write the cache files used by older nodes.
+    let peer_cache_extra_vars: serde_json::Value =
+        serde_json::from_str(playbooks[0].extra_vars.as_deref().unwrap_or("{}"))?;
+    assert_eq!(peer_cache_extra_vars["write_older_cache_files"], "true");
+    Ok(())
+}
@@ -0,0 +1,196 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+mod common;
+
+use color_eyre::Result;
+use common::{
+    get_arg_value, respond_with_antctl_status, respond_with_node_registries, TestEnvironment,
+};
+use semver::Version;
+use sn_testnet_deploy::{
+    ansible::inventory::AnsibleInventoryType, inventory::DeploymentInventoryService,
+    write_environment_details, BinaryOption, DeploymentType, EnvironmentDetails,
+};
+use std::net::{IpAddr, Ipv4Addr};
+
+#[test]
+fn test_get_inventory_parses_ansible_inventory_output() -> Result<()> {
+    let env = TestEnvironment::new("alpha");
+    env.executor.respond_with_fixture(
+        "ansible-inventory",
+        &env.inventory_file_name("node"),
+        "ansible_inventory_nodes.json",
+    );
+
+    let mut vms = env
+        .deployer
+        .ansible_provisioner
+        .ansible_runner
+        .get_inventory(AnsibleInventoryType::Nodes, false)?;
+    vms.sort_by(|a, b| a.name.cmp(&b.name));
+
+    assert_eq!(vms.len(), 2);
+    assert_eq!(vms[0].id, 401000001);
+    assert_eq!(vms[0].name, "alpha-node-1");
+    assert_eq!(
+        vms[0].public_ip_addr,
+        IpAddr::V4(Ipv4Addr::new(178, 62, 10, 1))
+    );
+    assert_eq!(
+        vms[0].private_ip_addr,
+        IpAddr::V4(Ipv4Addr::new(10, 106, 0, 2))
+    );
+    assert_eq!(vms[1].name, "alpha-node-2");
+
+    let commands = env.executor.commands_for("ansible-inventory");
+    assert_eq!(commands.len(), 1);
+    assert!(get_arg_value(&commands[0].args, "--inventory")
+        .is_some_and(|path| path.ends_with(&env.inventory_file_name("node"))));
+    assert_eq!(
+        commands[0].args.last().map(|arg| arg.as_str()),
+        Some("--list")
+    );
+    Ok(())
+}
+
+#[test]
+fn test_get_node_registries_loads_the_fetched_registries() -> Result<()> {
+    let env = TestEnvironment::new("alpha");
+    respond_with_node_registries(
+        &env.executor,
+        &env.inventory_file_name("node"),
+        &["alpha-node-1", "alpha-node-2"],
+    );
+
+    let registries = env
+        .deployer
+        .ansible_provisioner
+        .get_node_registries(&AnsibleInventoryType::Nodes)?;
+
+    let mut vm_names = registries
+        .retrieved_registries
+        .iter()
+        .map(|(vm_name, _)| vm_name.clone())
+        .collect::<Vec<_>>();
+    vm_names.sort();
+    assert_eq!(vm_names, vec!["alpha-node-1", "alpha-node-2"]);
+    assert!(registries.failed_vms.is_empty());
+
+    let commands = env.executor.commands_for("ansible-playbook");
+    assert_eq!(commands.len(), 1);
+    assert_eq!(
+        commands[0].args.last().map(|arg| arg.as_str()),
+        Some("antctl_inventory.yml")
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_generate_or_retrieve_inventory_fails_when_workspace_does_not_exist() -> Result<()> {
+    let env = TestEnvironment::new("alpha");
+    env.executor
+        .respond_with_lines("terraform", "workspace list", &["  default", "* beta"]);
+
+    let inventory_service = DeploymentInventoryService::from(&env.deployer);
+    let result = inventory_service
+        .generate_or_retrieve_inventory("alpha", false, None)
+        .await;
+
+    assert!(result.is_err());
+    let commands = env.executor.commands();
+    assert_eq!(commands.len(), 1);
+    assert_eq!(commands[0].command_line(), "terraform workspace list");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_generate_or_retrieve_inventory_builds_the_inventory_of_an_environment() -> Result<()>
+{
+    let env = TestEnvironment::new("alpha");
+    write_environment_details(
+        env.deployer.object_store.as_ref(),
+        "alpha",
+        &EnvironmentDetails {
+            // A bootstrap deployment has no client wallets or genesis multiaddr to retrieve.
+            deployment_type: DeploymentType::Bootstrap,
+            region: "lon1".to_string(),
+            ..Default::default()
+        },
+    )
+    .await?;
+    env.executor
+        .respond_with_lines("terraform", "workspace list", &["  default", "* alpha"]);
+    for (tag, fixture_name) in [
+        ("ant_client", "ansible_inventory_clients.json"),
+        ("genesis", "ansible_inventory_genesis.json"),
+        ("node", "ansible_inventory_nodes.json"),
+        ("peer_cache_node", "ansible_inventory_peer_cache_nodes.json"),
+    ] {
+        env.executor.respond_with_fixture(
+            "ansible-inventory",
+            &env.inventory_file_name(tag),
+            fixture_name,
+        );
+    }
+    env.executor.respond_with_fixture(
+        "ansible-inventory",
+        "--list",
+        "ansible_inventory_empty.json",
+    );
+    respond_with_node_registries(
+        &env.executor,
+        &env.inventory_file_name("node"),
+        &["alpha-node-1", "alpha-node-2"],
+    );
+    respond_with_antctl_status(&env.executor, "antctl_status.json");
+    env.executor.respond_with_lines(
+        "ssh",
+        "antctl --version",
+        &["Autonomi Node Manager v0.12.0"],
+    );
+
+    let inventory_service = DeploymentInventoryService::from(&env.deployer);
+    let inventory = inventory_service
+        .generate_or_retrieve_inventory("alpha", false, None)
+        .await?;
+
+    assert_eq!(
+        inventory.environment_details.deployment_type,
+        DeploymentType::Bootstrap
+    );
+    assert_eq!(inventory.environment_details.region, "lon1");
+    assert_eq!(
+        inventory
+            .genesis_vm
+            .as_ref()
+            .map(|genesis_vm| genesis_vm.vm.name.as_str()),
+        Some("alpha-genesis-bootstrap")
+    );
+    let mut node_vm_names = inventory
+        .node_vms
+        .iter()
+        .map(|node_vm| node_vm.vm.name.clone())
+        .collect::<Vec<_>>();
+    node_vm_names.sort();
+    assert_eq!(node_vm_names, vec!["alpha-node-1", "alpha-node-2"]);
+    assert_eq!(inventory.peer_cache_node_vms.len(), 1);
+    assert!(inventory.client_vms.is_empty());
+    assert!(inventory.failed_node_registry_vms.is_empty());
+    match inventory.binary_option {
+        BinaryOption::Versioned {
+            ant_version,
+            antctl_version,
+            antnode_version,
+        } => {
+            assert_eq!(ant_version, None);
+            assert_eq!(antctl_version, Some(Version::new(0, 12, 0)));
+            assert_eq!(antnode_version, Some(Version::new(0, 3, 5)));
+        }
+        _ => panic!("The binary option should be versioned"),
+    }
+    Ok(())
+}
@@ -0,0 +1,117 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+use color_eyre::Result;
+use sn_testnet_deploy::object_store::{Bucket, BucketNames, LocalObjectStore, ObjectStore};
+use tempfile::TempDir;
+
+fn get_local_object_store(temp_dir: &TempDir) -> LocalObjectStore {
+    LocalObjectStore::new(
+        temp_dir.path().join("object-store"),
+        BucketNames {
+            testnet: "custom-testnet".to_string(),
+            ..Default::default()
+        },
+    )
+}
+
+#[tokio::test]
+async fn test_local_object_store_keeps_objects_under_the_mapped_bucket_name() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let object_store = get_local_object_store(&temp_dir);
+
+    object_store
+        .put_object_contents(Bucket::Testnet, "testnet-locks/alpha.json", b"{}".to_vec())
+        .await?;
+
+    assert!(temp_dir
+        .path()
+        .join("object-store")
+        .join("custom-testnet")
+        .join("testnet-locks")
+        .join("alpha.json")
+        .is_file());
+    assert_eq!(
+        object_store
+            .get_object_contents(Bucket::Testnet, "testnet-locks/alpha.json")
+            .await?,
+        Some(b"{}".to_vec())
+    );
+    assert_eq!(
+        object_store
+            .get_object_contents(Bucket::Testnet, "testnet-locks/beta.json")
+            .await?,
+        None
+    );
+    assert!(object_store
+        .list_object_keys(Bucket::EnvironmentDetails, "")
+        .await?
+        .is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_object_store_lists_downloads_and_deletes_folders() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let object_store = get_local_object_store(&temp_dir);
+    for key in [
+        "testnet-logs/alpha/alpha-node-1/antnode.log",
+        "testnet-logs/alpha/alpha-node-2/antnode.log",
+        "testnet-logs/alpha/manifest.json",
+        "testnet-logs/beta/manifest.json",
+    ] {
+        object_store
+            .put_object_contents(Bucket::Testnet, key, key.as_bytes().to_vec())
+            .await?;
+    }
+
+    assert_eq!(
+        object_store
+            .list_object_keys(Bucket::Testnet, "testnet-logs/alpha")
+            .await?,
+        vec![
+            "testnet-logs/alpha/alpha-node-1/antnode.log",
+            "testnet-logs/alpha/alpha-node-2/antnode.log",
+            "testnet-logs/alpha/manifest.json",
+        ]
+    );
+    assert!(
+        object_store
+            .folder_exists(Bucket::Testnet, "testnet-logs/alpha")
+            .await?
+    );
+    // The folder only contains other folders.
+    assert!(
+        !object_store
+            .folder_exists(Bucket::Testnet, "testnet-logs")
+            .await?
+    );
+
+    let dest_path = temp_dir.path().join("logs");
+    object_store
+        .download_folder(Bucket::Testnet, "testnet-logs/alpha", &dest_path)
+        .await?;
+    let log_path = dest_path
+        .join("testnet-logs")
+        .join("alpha")
+        .join("alpha-node-1")
+        .join("antnode.log");
+    assert_eq!(
+        std::fs::read_to_string(log_path)?,
+        "testnet-logs/alpha/alpha-node-1/antnode.log"
+    );
+
+    object_store
+        .delete_folder(Bucket::Testnet, "testnet-logs/alpha")
+        .await?;
+    assert_eq!(
+        object_store
+            .list_object_keys(Bucket::Testnet, "testnet-logs/")
+            .await?,
+        vec!["testnet-logs/beta/manifest.json"]
+    );
+    Ok(())
+}
@@ -0,0 +1,114 @@
+// Copyright (c) 2023, MaidSafe.
+// All rights reserved.
+//
+// This SAFE Network Software is licensed under the BSD-3-Clause license.
+// Please see the LICENSE file for more details.
+
+mod common;
+
+use color_eyre::{eyre::eyre, Result};
+use common::{get_arg_value, TestEnvironment};
+use sn_testnet_deploy::{CloudProvider, NodeType, UpgradeOptions};
+use std::{path::Path, time::Duration};
+
+fn get_upgrade_options(name: &str, node_type: Option<NodeType>) -> UpgradeOptions {
+    UpgradeOptions {
+        ansible_verbose: false,
+        branch: None,
+        custom_inventory: None,
+        env_variables: Some(vec![("ANT_LOG".to_string(), "all".to_string())]),
+        force: false,
+        forks: 50,
+        interval: Duration::from_millis(200),
+        name: name.to_string(),
+        node_type,
+        pre_upgrade_delay: None,
+        provider: CloudProvider::DigitalOcean,
+        repo_owner: None,
+        version: Some("0.3.5".to_string()),
+    }
+}
+
+/// The inventory file names that the `upgrade_nodes.yml` playbook was run against, in order.
+fn get_upgraded_inventories(env: &TestEnvironment) -> Result<Vec<String>> {
+    env.executor
+        .commands_for("ansible-playbook")
+        .iter()
+        .filter(|command| {
+            command
+                .args
+                .last()
+                .is_some_and(|arg| arg == "upgrade_nodes.yml")
+        })
+        .map(|command| {
+            get_arg_value(&command.args, "--inventory")
+                .and_then(|path| Path::new(path).file_name())
+                .map(|name| name.to_string_lossy().to_string())
+                .ok_or_else(|| eyre!("The playbook was run without an inventory"))
+        })
+        .collect()
+}
+
+#[test]
+fn test_upgrade_nodes_upgrades_each_node_type_with_genesis_last() -> Result<()> {
+    let env = TestEnvironment::new("alpha");
+
+    env.deployer
+        .ansible_provisioner
+        .upgrade_nodes(&get_upgrade_options("alpha", None))?;
+
+    assert_eq!(
+        get_upgraded_inventories(&env)?,
+        vec![
+            env.inventory_file_name("peer_cache_node"),
+            env.inventory_file_name("node"),
+            env.inventory_file_name("symmetric_private_node"),
+            env.inventory_file_name("genesis"),
+        ]
+    );
+    for command in env.executor.commands_for("ansible-playbook") {
+        let extra_vars: serde_json::Value = serde_json::from_str(
+            command
+                .extra_vars
+                .as_deref()
+                .ok_or_else(|| eyre!("The playbook was run without extra vars"))?,
+        )?;
+        assert!(get_arg_value(&command.args, "--extra-vars")
+            .is_some_and(|value| value.starts_with('@')));
+        assert_eq!(extra_vars["antnode_version"], "0.3.5");
+        assert_eq!(extra_vars["interval"], "200");
+        assert_eq!(get_arg_value(&command.args, "--forks"), Some("50"));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_upgrade_nodes_for_node_type_only_upgrades_that_inventory() -> Result<()> {
+    let env = TestEnvironment::new("alpha");
+
+    env.deployer
+        .ansible_provisioner
+        .upgrade_nodes(&get_upgrade_options("alpha", Some(NodeType::PeerCache)))?;
+
+    assert_eq!(
+        get_upgraded_inventories(&env)?,
+        vec![env.inventory_file_name("peer_cache_node")]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_upgrade_nodes_continues_when_a_playbook_fails() -> Result<()> {
+    let env = TestEnvironment::new("alpha");
+    env.executor.fail(
+        "ansible-playbook",
+        &env.inventory_file_name("peer_cache_node"),
+    );
+
+    env.deployer
+        .ansible_provisioner
+        .upgrade_nodes(&get_upgrade_options("alpha", None))?;
+
+    assert_eq!(get_upgraded_inventories(&env)?.len(), 4);
+    Ok(())
+}